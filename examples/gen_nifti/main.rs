@@ -16,7 +16,7 @@ fn main() {
     // generate some test data 256x256 float32
     let data = ndarray::Array3::<f32>::zeros((256, 256, 1));
 
-    let extension1 = Extension::new(2, vec![0, 0, 0, 0]);
+    let extension1 = Extension::new(2, vec![0; 8]);
 
     let extension2 = Extension::from_str(6, "Hello World!");
 
@@ -0,0 +1,12 @@
+//! Reading a NIfTI header or object from arbitrary bytes must never panic.
+//!
+//! Run with `cargo fuzz run read_object` from the repository root.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use nifti::{InMemNiftiObject, NiftiHeader, StreamedNiftiObject};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = NiftiHeader::from_reader(data);
+    let _ = InMemNiftiObject::from_reader(data);
+    let _ = StreamedNiftiObject::from_reader(data);
+});
@@ -1,6 +1,7 @@
 //! Types for error handling go here.
 use crate::typedef::NiftiType;
 use quick_error::quick_error;
+use std::fmt;
 use std::io::Error as IOError;
 
 quick_error! {
@@ -11,8 +12,11 @@ quick_error! {
         /// An invalid NIfTI-1 file was parsed.
         /// This is detected when reading the file's magic code,
         /// which should be either `b"ni1\0"` or `b"n+1\0`.
-        InvalidFormat {
-            display("Invalid NIfTI-1 file")
+        /// The first 16 bytes of the header are kept for diagnostics (for
+        /// instance, a GZip stream which was not decoded starts with the
+        /// bytes `1f 8b`).
+        InvalidFormat(leading: [u8; 16]) {
+            display("Invalid NIfTI-1 file (leading bytes: {})", HexBytes(leading))
         }
         /// The field `dim` is in an invalid state, as a consequence of
         /// `dim[0]` or one of the elements in `1..dim[0] + 1` not being
@@ -61,6 +65,17 @@ quick_error! {
             from()
             source(err)
         }
+        /// A text field of the header is not valid UTF-8
+        Utf8(err: std::str::Utf8Error) {
+            from()
+            source(err)
+            display("Header text field is not valid UTF-8: {}", err)
+        }
+        /// A JSON sidecar file could not be parsed
+        InvalidSidecar(err: Box<dyn std::error::Error + Send + Sync>) {
+            source(&**err)
+            display("Invalid JSON sidecar: {}", err)
+        }
         /// Raw data buffer length and volume dimensions are incompatible
         IncompatibleLength(got: usize, expected: usize) {
             display("The buffer length ({}) and header dimensions ({} elements) are incompatible", got, expected)
@@ -69,13 +84,32 @@ quick_error! {
         IncorrectDescriptionLength(len: usize) {
             display("Description length ({} bytes) is greater than 80 bytes.", len)
         }
+        /// A text field of the header is longer than its fixed capacity
+        IncorrectFieldLength(field: &'static str, len: usize, max: usize) {
+            display("Length of `{}` ({} bytes) is greater than {} bytes.", field, len, max)
+        }
+        /// The header does not specify a data type (`datatype` is 0,
+        /// `DT_UNKNOWN`), which usually means that it was not initialized
+        NoDataType {
+            display("No data type specified in header (datatype is 0)")
+        }
+        /// The header's `vox_offset` is negative or not an integer
+        InvalidVoxOffset(vox_offset: f32) {
+            display("Invalid vox_offset `{}` (must be a non-negative integer)", vox_offset)
+        }
+        /// An extension's `esize` is smaller than the 8 bytes of its
+        /// `esize` and `ecode` fields, or is not a multiple of 16 when writing
+        InvalidExtensionSize(esize: i32) {
+            display("Invalid extension size `{}`", esize)
+        }
         /// Header contains a code which is not valid for the given attribute
         InvalidCode(typename: &'static str, code: i16) {
             display("invalid code `{}` for header field {}", code, typename)
         }
         /// Could not reserve enough memory for volume data
         ReserveVolume(bytes: usize, err: std::collections::TryReserveError) {
-            display("Could not reserve {} bytes of memory for volume data", bytes)
+            display("Wanted {} bytes of memory for volume data, but the allocation failed \
+                     (consider reading the volume in slices with `ReaderStreamedOptions`)", bytes)
             source(err)
         }
         /// Could not reserve enough memory for extended data
@@ -84,6 +118,63 @@ quick_error! {
             source(err)
         }
 
+        /// The output data type could not be determined from the given options
+        MissingDataType {
+            display("No output data type was specified")
+        }
+
+        /// A voxel size is not a positive finite number
+        InvalidVoxelSize(size: f64) {
+            display("Invalid voxel size `{}` (must be positive)", size)
+        }
+
+        /// The qfac (`pixdim[0]`) is neither 1 nor -1
+        InvalidQfac(qfac: f32) {
+            display("Invalid qfac pixdim[0] = `{}` (must be 1 or -1)", qfac)
+        }
+
+        /// An affine transformation cannot be inverted
+        SingularAffine {
+            display("The affine transformation is not invertible")
+        }
+
+        /// A voxel holds a value which is not valid for the volume's data
+        /// type or intent
+        InvalidVoxelValue(index: usize, value: f64) {
+            display("Invalid value `{}` in voxel #{}", value, index)
+        }
+
+        /// A voxel value does not fit in the range of the requested type
+        ValueOutOfRange(value: f64, coords: Vec<u16>) {
+            display("Value `{}` at {:?} is out of range for the requested type", value, coords)
+        }
+
+        /// The dimensions of a header and a volume, or of two volumes, do
+        /// not agree
+        DimMismatch(left: Vec<u16>, right: Vec<u16>) {
+            display("Dimensions {:?} and {:?} do not match", left, right)
+        }
+        /// The data types of a header and a volume do not agree
+        DataTypeMismatch(header: NiftiType, volume: NiftiType) {
+            display("Header data type {:?} does not match volume data type {:?}", header, volume)
+        }
+        /// The scaling (`scl_slope` and `scl_inter`) of a header and a volume
+        /// do not agree
+        ScalingMismatch(header: (f32, f32), volume: (f32, f32)) {
+            display("Header scaling {:?} does not match volume scaling {:?}", header, volume)
+        }
+
+        /// A volume in a sequence differs from the first one in a property
+        /// which they must share (such as the data type or byte order)
+        VolumeMismatch(index: usize, property: &'static str) {
+            display("Volume #{} differs from the first volume in {}", index, property)
+        }
+
+        /// The volume does not match the checksum stored in its extensions
+        ChecksumMismatch {
+            display("Volume data does not match its checksum extension")
+        }
+
         /// Attempted a type conversion that is not supported by this crate
         InvalidTypeConversion(from: NiftiType, to: &'static str) {
             display("Invalid type conversion from {:?} to {}", from, to)
@@ -91,5 +182,55 @@ quick_error! {
     }
 }
 
+/// Helper for displaying bytes in hexadecimal.
+struct HexBytes<'a>(&'a [u8]);
+
+impl fmt::Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
 /// Alias type for results originated from this crate.
 pub type Result<T> = ::std::result::Result<T, NiftiError>;
+
+#[cfg(test)]
+mod tests {
+    use super::NiftiError;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn test_source_chaining() {
+        let err = NiftiError::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        let source = err.source().expect("I/O errors should have a source");
+        let source = source
+            .downcast_ref::<io::Error>()
+            .expect("source should be the inner I/O error");
+        assert_eq!(source.kind(), io::ErrorKind::NotFound);
+        assert_eq!(source.to_string(), "no such file");
+
+        let err = NiftiError::MissingVolumeFile(io::ErrorKind::NotFound.into());
+        assert!(err.source().unwrap().is::<io::Error>());
+
+        let reserve_err = Vec::<u8>::new().try_reserve(usize::MAX).unwrap_err();
+        let err = NiftiError::ReserveVolume(usize::MAX, reserve_err.clone());
+        assert!(err
+            .source()
+            .unwrap()
+            .is::<std::collections::TryReserveError>());
+        let err = NiftiError::ReserveExtended(usize::MAX, reserve_err);
+        assert!(err
+            .source()
+            .unwrap()
+            .is::<std::collections::TryReserveError>());
+
+        assert!(NiftiError::BadVolumeSize.source().is_none());
+    }
+}
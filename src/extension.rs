@@ -8,6 +8,28 @@ use crate::error::{NiftiError, Result};
 use byteordered::{ByteOrdered, Endian};
 use std::io::{ErrorKind as IoErrorKind, Read};
 
+/// Extension code used by this crate to mark `Int16` volumes which actually
+/// hold the bits of half precision floats (IEEE 754 binary16), along with the
+/// extension data `"float16"`.
+///
+/// Since NIfTI-1 has no half precision data type, this is a convention of this
+/// crate, and the code is not registered in the standard. Other tools will
+/// read such volumes as plain 16-bit integers.
+pub const ECODE_FLOAT16: i32 = 1016;
+
+const FLOAT16_TAG: &[u8] = b"float16";
+
+/// Extension code used by this crate to store a checksum of the volume, with
+/// the extension data `"sha256:"` followed by the SHA-256 digest of the voxel
+/// data (in little endian byte order) as 64 lowercase hexadecimal digits.
+///
+/// As with [`ECODE_FLOAT16`], this is a convention of this crate, and the
+/// code is not registered in the standard. See
+/// [`ReaderOptions::verify_checksum`](../object/struct.ReaderOptions.html#method.verify_checksum).
+pub const ECODE_SHA256: i32 = 1017;
+
+const SHA256_TAG: &[u8] = b"sha256:";
+
 /// Data type for the extender code.
 #[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub struct Extender([u8; 4]);
@@ -82,6 +104,41 @@ impl Extension {
         Extension::new(ecode, edata)
     }
 
+    /// Create the extension marking a volume as holding half precision floats
+    /// (see [`ECODE_FLOAT16`]).
+    pub fn float16() -> Self {
+        Extension::from_str(ECODE_FLOAT16, "float16")
+    }
+
+    /// Whether this is the extension marking a volume as holding half
+    /// precision floats (see [`ECODE_FLOAT16`]).
+    pub fn is_float16(&self) -> bool {
+        self.ecode == ECODE_FLOAT16 && self.edata.starts_with(FLOAT16_TAG)
+    }
+
+    /// Create the extension holding the given SHA-256 digest of a volume (see
+    /// [`ECODE_SHA256`]), as obtained from
+    /// [`InMemNiftiVolume::sha256`](../volume/inmem/struct.InMemNiftiVolume.html#method.sha256).
+    pub fn sha256(digest: &[u8; 32]) -> Self {
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Extension::from_str(ECODE_SHA256, &format!("sha256:{}", hex))
+    }
+
+    /// Retrieve the SHA-256 digest held by this extension, or `None` if this
+    /// is not a well-formed checksum extension (see [`ECODE_SHA256`]).
+    pub fn sha256_digest(&self) -> Option<[u8; 32]> {
+        if self.ecode != ECODE_SHA256 {
+            return None;
+        }
+        let hex = self.edata.strip_prefix(SHA256_TAG)?.get(..64)?;
+        let mut digest = [0; 32];
+        for (d, pair) in digest.iter_mut().zip(hex.chunks_exact(2)) {
+            let pair = std::str::from_utf8(pair).ok()?;
+            *d = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(digest)
+    }
+
     /// Obtain the claimed extension raw size (`esize` field).
     pub fn size(&self) -> i32 {
         self.esize
@@ -105,7 +162,7 @@ impl Extension {
 
 /// Data type for aggregating the extender code and
 /// all extensions.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct ExtensionSequence {
     extender: Extender,
     extensions: Vec<Extension>,
@@ -139,6 +196,11 @@ impl ExtensionSequence {
     }
 
     /// Read a sequence of extensions from a source, up until `len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidExtensionSize` if an extension claims to be
+    ///   smaller than its own 8-byte prelude.
     pub fn from_reader<S, E>(
         extender: Extender,
         mut source: ByteOrdered<S, E>,
@@ -154,6 +216,9 @@ impl ExtensionSequence {
             while offset < len {
                 let esize = source.read_i32()?;
                 let ecode = source.read_i32()?;
+                if esize < 8 {
+                    return Err(NiftiError::InvalidExtensionSize(esize));
+                }
 
                 let data_size = (esize as usize).saturating_sub(8);
                 let mut edata = Vec::new();
@@ -169,7 +234,7 @@ impl ExtensionSequence {
                 }
 
                 extensions.push(Extension::new(ecode, edata));
-                offset += esize as usize;
+                offset = offset.saturating_add(esize as usize);
             }
         }
 
@@ -180,10 +245,16 @@ impl ExtensionSequence {
     }
 
     /// Obtain an iterator to the extensions.
-    pub fn iter(&self) -> ::std::slice::Iter<Extension> {
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Extension> {
         self.extensions.iter()
     }
 
+    /// Obtain an iterator to the extensions with the given code (`ecode`), in
+    /// the order in which they appear in the sequence.
+    pub fn find_by_code(&self, code: i32) -> impl Iterator<Item = &Extension> {
+        self.extensions.iter().filter(move |e| e.code() == code)
+    }
+
     /// Whether the sequence of extensions is empty.
     pub fn is_empty(&self) -> bool {
         self.extensions.is_empty()
@@ -194,6 +265,28 @@ impl ExtensionSequence {
         self.extensions.len()
     }
 
+    /// Check that every extension in the sequence can be written as is.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidExtensionSize` if an extension's size is lower
+    ///   than 8 or not a multiple of 16, as required by the NIfTI-1 standard.
+    /// - `NiftiError::IncompatibleLength` if an extension's data length is not
+    ///   its size minus the 8 bytes of the `esize` and `ecode` fields.
+    pub fn validate(&self) -> Result<()> {
+        for extension in &self.extensions {
+            let esize = extension.size();
+            if esize < 8 || esize % 16 != 0 {
+                return Err(NiftiError::InvalidExtensionSize(esize));
+            }
+            let data_len = extension.data().len();
+            if data_len != esize as usize - 8 {
+                return Err(NiftiError::IncompatibleLength(data_len, esize as usize - 8));
+            }
+        }
+        Ok(())
+    }
+
     /// Return the number of bytes the extensions take on disk
     pub fn bytes_on_disk(&self) -> usize {
         self.extensions
@@ -205,4 +298,106 @@ impl ExtensionSequence {
     pub fn extender(&self) -> Extender {
         self.extender
     }
+
+    /// Whether the sequence contains the extension marking the volume as
+    /// holding half precision floats (see [`ECODE_FLOAT16`]).
+    pub fn has_float16(&self) -> bool {
+        self.extensions.iter().any(Extension::is_float16)
+    }
+
+    /// Retrieve the SHA-256 digest of the first well-formed checksum
+    /// extension in the sequence (see [`ECODE_SHA256`]), if any.
+    pub fn sha256_digest(&self) -> Option<[u8; 32]> {
+        self.extensions.iter().find_map(Extension::sha256_digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Extender, Extension, ExtensionSequence, ECODE_SHA256};
+    use crate::error::NiftiError;
+
+    fn sequence(extensions: Vec<Extension>) -> ExtensionSequence {
+        ExtensionSequence::new(Extender::from([1, 0, 0, 0]), extensions)
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(sequence(vec![]).validate().is_ok());
+        assert!(sequence(vec![
+            Extension::new(2, vec![0; 8]),
+            Extension::from_str(6, "Hello World!"),
+            Extension::new(4, vec![1; 24]),
+        ])
+        .validate()
+        .is_ok());
+
+        // not a multiple of 16
+        assert!(matches!(
+            sequence(vec![Extension::new(2, vec![0; 4])]).validate(),
+            Err(NiftiError::InvalidExtensionSize(12))
+        ));
+        assert!(matches!(
+            sequence(vec![Extension::new(2, vec![])]).validate(),
+            Err(NiftiError::InvalidExtensionSize(8))
+        ));
+        // too small
+        let extension = Extension {
+            esize: 0,
+            ecode: 2,
+            edata: vec![],
+        };
+        assert!(matches!(
+            sequence(vec![extension]).validate(),
+            Err(NiftiError::InvalidExtensionSize(0))
+        ));
+        // data length does not match the size
+        let extension = Extension {
+            esize: 32,
+            ecode: 2,
+            edata: vec![0; 8],
+        };
+        assert!(matches!(
+            sequence(vec![Extension::new(2, vec![0; 8]), extension]).validate(),
+            Err(NiftiError::IncompatibleLength(8, 24))
+        ));
+    }
+
+    #[test]
+    fn test_find_by_code() {
+        let extensions = sequence(vec![
+            Extension::from_str(6, "first comment"),
+            Extension::new(2, vec![0; 8]),
+            Extension::from_str(6, "second comment"),
+            Extension::new(4, vec![1; 24]),
+        ]);
+
+        let comments: Vec<_> = extensions.find_by_code(6).collect();
+        assert_eq!(comments.len(), 2);
+        assert!(comments[0].data().starts_with(b"first comment"));
+        assert!(comments[1].data().starts_with(b"second comment"));
+
+        let dicom: Vec<_> = extensions.find_by_code(2).collect();
+        assert_eq!(dicom, vec![&Extension::new(2, vec![0; 8])]);
+
+        assert_eq!(extensions.find_by_code(8).count(), 0);
+    }
+
+    #[test]
+    fn test_sha256_extension() {
+        let digest: [u8; 32] = std::array::from_fn(|i| (i * 8) as u8);
+        let extension = Extension::sha256(&digest);
+        assert_eq!(extension.code(), ECODE_SHA256);
+        assert_eq!(extension.size() % 16, 0);
+        assert!(extension.data().starts_with(b"sha256:00081018"));
+        assert_eq!(extension.sha256_digest(), Some(digest));
+
+        let extensions = sequence(vec![
+            Extension::from_str(6, "a comment"),
+            Extension::from_str(ECODE_SHA256, "sha256:not hex"),
+            extension,
+        ]);
+        assert_eq!(extensions.sha256_digest(), Some(digest));
+        assert_eq!(sequence(vec![]).sha256_digest(), None);
+    }
 }
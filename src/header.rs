@@ -4,8 +4,10 @@
 #[cfg(feature = "nalgebra_affine")]
 use crate::affine::*;
 use crate::error::{NiftiError, Result};
+use crate::extension::ExtensionSequence;
 use crate::typedef::*;
 use crate::util::{is_gz_file, validate_dim, validate_dimensionality};
+use crate::volume::NiftiVolume;
 use byteordered::{ByteOrdered, Endian, Endianness};
 use flate2::bufread::GzDecoder;
 #[cfg(feature = "nalgebra_affine")]
@@ -15,8 +17,9 @@ use num_traits::FromPrimitive;
 use num_traits::ToPrimitive;
 #[cfg(feature = "nalgebra_affine")]
 use simba::scalar::SubsetOf;
+use std::convert::TryFrom;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::ops::Deref;
 use std::path::Path;
 
@@ -25,6 +28,59 @@ pub const MAGIC_CODE_NI1: &[u8; 4] = b"ni1\0";
 /// Magic code for full NIFTI-1 files (extention ".nii[.gz]").
 pub const MAGIC_CODE_NIP1: &[u8; 4] = b"n+1\0";
 
+/// Value of `sizeof_hdr` in a NIfTI-1 header.
+pub const SIZEOF_HDR_NI1: i32 = 348;
+/// Value of `sizeof_hdr` in a NIfTI-1 header, when read in the opposite
+/// byte order.
+pub const SIZEOF_HDR_NI1_SWAPPED: i32 = 1_543_569_408;
+/// Value of `sizeof_hdr` in a NIfTI-2 header.
+pub const SIZEOF_HDR_NI2: i32 = 540;
+/// Value of `sizeof_hdr` in a NIfTI-2 header, when read in the opposite
+/// byte order.
+pub const SIZEOF_HDR_NI2_SWAPPED: i32 = 469_893_120;
+
+/// The version of the NIfTI format of a header.
+///
+/// Only NIfTI-1 headers can be read by this crate, but NIfTI-2 headers
+/// can be told apart with [`detect_version_and_endianness`].
+///
+/// [`detect_version_and_endianness`]: fn.detect_version_and_endianness.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NiftiVersion {
+    /// NIfTI-1, with a header of 348 bytes.
+    Nifti1,
+    /// NIfTI-2, with a header of 540 bytes.
+    Nifti2,
+}
+
+/// Detect the format version and byte order of a header from its
+/// `sizeof_hdr` field, as read in the system's native byte order from the
+/// first 4 bytes of the header. Returns `None` if the value is not one of
+/// the known sentinel values.
+///
+/// # Example
+///
+/// ```
+/// use nifti::header::{detect_version_and_endianness, NiftiVersion};
+/// use nifti::Endianness;
+///
+/// let bytes = 348_i32.to_be_bytes();
+/// let sizeof_hdr = i32::from_ne_bytes(bytes);
+/// assert_eq!(
+///     detect_version_and_endianness(sizeof_hdr),
+///     Some((NiftiVersion::Nifti1, Endianness::Big)),
+/// );
+/// ```
+pub fn detect_version_and_endianness(sizeof_hdr: i32) -> Option<(NiftiVersion, Endianness)> {
+    match sizeof_hdr {
+        SIZEOF_HDR_NI1 => Some((NiftiVersion::Nifti1, Endianness::native())),
+        SIZEOF_HDR_NI1_SWAPPED => Some((NiftiVersion::Nifti1, Endianness::native().to_opposite())),
+        SIZEOF_HDR_NI2 => Some((NiftiVersion::Nifti2, Endianness::native())),
+        SIZEOF_HDR_NI2_SWAPPED => Some((NiftiVersion::Nifti2, Endianness::native().to_opposite())),
+        _ => None,
+    }
+}
+
 /// The NIFTI-1 header data type.
 /// All fields are public and named after the specification's header file.
 /// The type of each field was adjusted according to their use and
@@ -156,10 +212,47 @@ pub struct NiftiHeader {
     pub endianness: Endianness,
 }
 
+/// A non-fatal issue found in a NIFTI-1 header, as reported by
+/// [`NiftiHeader::warnings`](struct.NiftiHeader.html#method.warnings).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum HeaderWarning {
+    /// `pixdim[0]` (the qfac) is neither 1 nor -1 (fixed to 1).
+    InvalidQfac(f32),
+    /// `scl_slope` is NaN (fixed to 1).
+    NanSlope,
+    /// `scl_inter` is NaN (fixed to 0).
+    NanIntercept,
+    /// `bitpix` does not match the size of `datatype`: the declared and the
+    /// expected number of bits, respectively.
+    BitpixMismatch(i16, i16),
+    /// The norm of the quaternion parameters `b`, `c` and `d` exceeds 1
+    /// (normalized).
+    NonUnitQuaternion(f64),
+}
+
+impl std::fmt::Display for HeaderWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HeaderWarning::InvalidQfac(qfac) => write!(f, "Invalid qfac pixdim[0] = {}", qfac),
+            HeaderWarning::NanSlope => write!(f, "NaN scaling slope"),
+            HeaderWarning::NanIntercept => write!(f, "NaN scaling intercept"),
+            HeaderWarning::BitpixMismatch(bitpix, expected) => write!(
+                f,
+                "bitpix is {}, but the data type requires {}",
+                bitpix, expected
+            ),
+            HeaderWarning::NonUnitQuaternion(norm) => {
+                write!(f, "Quaternion parameters have norm {} > 1", norm)
+            }
+        }
+    }
+}
+
 impl Default for NiftiHeader {
     fn default() -> NiftiHeader {
         NiftiHeader {
-            sizeof_hdr: 348,
+            sizeof_hdr: SIZEOF_HDR_NI1,
             data_type: [0; 10],
             db_name: [0; 18],
             extents: 0,
@@ -213,6 +306,57 @@ impl Default for NiftiHeader {
 }
 
 impl NiftiHeader {
+    /// Create a header for a single-file 3D volume of the given shape and data
+    /// type, with unit voxel spacing. All other fields are set to their
+    /// defaults.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::InconsistentDim` if one of the dimensions is zero, or
+    /// `NiftiError::BadVolumeSize` if one of them does not fit in a `u16`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use nifti::{NiftiHeader, NiftiType};
+    /// let header = NiftiHeader::new_3d([64, 64, 10], NiftiType::Uint8)?;
+    /// assert_eq!(header.dim()?, &[64, 64, 10]);
+    /// assert_eq!(header.bitpix, 8);
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    pub fn new_3d(shape: [u64; 3], datatype: NiftiType) -> Result<NiftiHeader> {
+        NiftiHeader::with_shape(&shape, datatype)
+    }
+
+    /// Create a header for a single-file 4D volume of the given shape and data
+    /// type, with unit voxel spacing. All other fields are set to their
+    /// defaults.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::InconsistentDim` if one of the dimensions is zero, or
+    /// `NiftiError::BadVolumeSize` if one of them does not fit in a `u16`.
+    pub fn new_4d(shape: [u64; 4], datatype: NiftiType) -> Result<NiftiHeader> {
+        NiftiHeader::with_shape(&shape, datatype)
+    }
+
+    fn with_shape(shape: &[u64], datatype: NiftiType) -> Result<NiftiHeader> {
+        let mut dim = [1; 8];
+        dim[0] = shape.len() as u16;
+        for (d, &s) in dim[1..].iter_mut().zip(shape) {
+            *d = u16::try_from(s).map_err(|_| NiftiError::BadVolumeSize)?;
+        }
+        let _ = validate_dim(&dim)?;
+        Ok(NiftiHeader {
+            dim,
+            datatype: datatype as i16,
+            bitpix: (datatype.size_of() * 8) as i16,
+            pixdim: [1.; 8],
+            magic: *MAGIC_CODE_NIP1,
+            ..NiftiHeader::default()
+        })
+    }
+
     /// Retrieve a NIFTI header, along with its byte order, from a file in the file system.
     /// If the file's name ends with ".gz", the file is assumed to need GZip decoding.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<NiftiHeader> {
@@ -228,6 +372,8 @@ impl NiftiHeader {
     /// Read a NIfTI-1 header, along with its byte order, from the given byte stream.
     /// It is assumed that the input is currently at the start of the
     /// NIFTI header.
+    ///
+    /// Malformed or truncated input results in an error, never in a panic.
     pub fn from_reader<S>(input: S) -> Result<NiftiHeader>
     where
         S: Read,
@@ -235,14 +381,142 @@ impl NiftiHeader {
         parse_header_1(input)
     }
 
+    /// Read a NIfTI-1 header, along with its byte order, from the given
+    /// seekable byte stream, starting at the absolute position `offset`.
+    ///
+    /// This is useful for headers embedded in other containers. The stream is
+    /// left right after the header.
+    pub fn from_reader_at<R>(mut input: R, offset: u64) -> Result<NiftiHeader>
+    where
+        R: Read + Seek,
+    {
+        let _ = input.seek(SeekFrom::Start(offset))?;
+        parse_header_1(input)
+    }
+
+    /// Parse a NIfTI-1 header from an in-memory byte slice with a known
+    /// byte order, without relying on the file system or GZip decoding.
+    ///
+    /// The slice must contain at least the 348 bytes of the header.
+    /// Unlike [`from_reader`](#method.from_reader), the byte order is not
+    /// inferred: if `sizeof_hdr` does not read as 348 in the given
+    /// endianness, an error is returned.
+    pub fn parse_from_slice(bytes: &[u8], endianness: Endianness) -> Result<NiftiHeader> {
+        let mut h = NiftiHeader::default();
+        let mut input = ByteOrdered::runtime(bytes, endianness);
+
+        let mut leading = [0; 16];
+        let n = bytes.len().min(16);
+        leading[..n].copy_from_slice(&bytes[..n]);
+
+        h.endianness = endianness;
+        h.sizeof_hdr = input.read_i32()?;
+        if h.sizeof_hdr != SIZEOF_HDR_NI1 {
+            return Err(NiftiError::InvalidFormat(leading));
+        }
+        input.read_exact(&mut h.data_type)?;
+        input.read_exact(&mut h.db_name)?;
+        h.extents = input.read_i32()?;
+        h.session_error = input.read_i16()?;
+        h.regular = input.read_u8()?;
+        h.dim_info = input.read_u8()?;
+        h.dim[0] = input.read_u16()?;
+        parse_header_2(h, input, leading)
+    }
+
     /// Fix some commonly invalid fields.
     ///
     /// Currently, only the following problems are fixed:
     /// - If `pixdim[0]` isn't equal to -1.0 or 1.0, it will be set to 1.0
+    /// - If `scl_slope` is NaN, it will be set to 1.0
+    /// - If `scl_inter` is NaN, it will be set to 0.0
+    /// - If the quaternion parameters exceed unit length, they are normalized
+    ///   (see [`normalize_qform`](#method.normalize_qform))
+    ///
+    /// The fixed fields can be told apart by comparing against the original
+    /// header.
     pub fn fix(&mut self) {
         if !self.is_pixdim_0_valid() {
             self.pixdim[0] = 1.0;
         }
+        if self.scl_slope.is_nan() {
+            self.scl_slope = 1.0;
+        }
+        if self.scl_inter.is_nan() {
+            self.scl_inter = 0.0;
+        }
+        self.normalize_qform();
+    }
+
+    /// Collect the non-fatal issues of this header, such as those which
+    /// [`fix`](#method.fix) would recover from. An empty list is returned
+    /// for a well-formed header.
+    pub fn warnings(&self) -> Vec<HeaderWarning> {
+        let mut warnings = Vec::new();
+        if !self.is_pixdim_0_valid() {
+            warnings.push(HeaderWarning::InvalidQfac(self.pixdim[0]));
+        }
+        if self.scl_slope.is_nan() {
+            warnings.push(HeaderWarning::NanSlope);
+        }
+        if self.scl_inter.is_nan() {
+            warnings.push(HeaderWarning::NanIntercept);
+        }
+        if let Ok(datatype) = self.data_type() {
+            let expected = (datatype.size_of() * 8) as i16;
+            if self.bitpix != expected {
+                warnings.push(HeaderWarning::BitpixMismatch(self.bitpix, expected));
+            }
+        }
+        let (b, c, d) = (
+            f64::from(self.quatern_b),
+            f64::from(self.quatern_c),
+            f64::from(self.quatern_d),
+        );
+        let norm = (b * b + c * c + d * d).sqrt();
+        if norm > 1. {
+            warnings.push(HeaderWarning::NonUnitQuaternion(norm));
+        }
+        warnings
+    }
+
+    /// Normalize the quaternion parameters (`quatern_b`, `quatern_c` and
+    /// `quatern_d`) if `b² + c² + d²` exceeds 1, such as due to floating
+    /// point drift, in which case the rotation of the 'qform' transformation
+    /// is undefined. The parameters are left untouched otherwise.
+    pub fn normalize_qform(&mut self) {
+        let (b, c, d) = (
+            f64::from(self.quatern_b),
+            f64::from(self.quatern_c),
+            f64::from(self.quatern_d),
+        );
+        let norm = (b * b + c * c + d * d).sqrt();
+        if norm > 1. {
+            self.quatern_b = (b / norm) as f32;
+            self.quatern_c = (c / norm) as f32;
+            self.quatern_d = (d / norm) as f32;
+        }
+    }
+
+    /// Clear the fields which have no meaning for the dimensionality of the
+    /// volume, as given by `dim[0]`:
+    ///
+    /// - `pixdim[i]` is set to 0 for every `i > dim[0]`;
+    /// - if the volume is purely spatial (`dim[0] <= 3`), `toffset` and
+    ///   `slice_duration` are set to 0 and the time unit of `xyzt_units` is
+    ///   cleared, leaving the spatial unit untouched.
+    ///
+    /// `pixdim[0]` (the qfac) and all other fields are left as is.
+    pub fn sanitize(&mut self) {
+        let rank = usize::from(self.dim[0]).min(7);
+        for pixdim in &mut self.pixdim[rank + 1..] {
+            *pixdim = 0.;
+        }
+        if rank <= 3 {
+            self.toffset = 0.;
+            self.slice_duration = 0.;
+            self.xyzt_units &= 0o0007;
+        }
     }
 
     /// Retrieve and validate the dimensions of the volume. Unlike how NIfTI-1
@@ -252,32 +526,122 @@ impl NiftiHeader {
     /// # Error
     ///
     /// `NiftiError::InconsistentDim` if `dim[0]` does not represent a valid
-    /// dimensionality, or any of the real dimensions are zero.
+    /// dimensionality (between 1 and 7), or any of the real dimensions are zero.
     pub fn dim(&self) -> Result<&[u16]> {
         validate_dim(&self.dim)
     }
 
+    /// Append a new trailing dimension of the given length to the volume, such
+    /// as a time axis to a 3D volume, or a singleton dimension.
+    ///
+    /// # Error
+    ///
+    /// - `NiftiError::InconsistentDim` if the current dimensions are invalid,
+    ///   if the volume already has 7 dimensions, or if `len` is zero.
+    /// - `NiftiError::BadVolumeSize` if `len` does not fit in a `u16`.
+    pub fn push_dim(&mut self, len: u64) -> Result<()> {
+        let rank = self.dim()?.len();
+        if rank == 7 {
+            return Err(NiftiError::InconsistentDim(0, 8));
+        }
+        let len = u16::try_from(len).map_err(|_| NiftiError::BadVolumeSize)?;
+        if len == 0 {
+            return Err(NiftiError::InconsistentDim(rank as u8 + 1, 0));
+        }
+        self.dim[0] += 1;
+        self.dim[rank + 1] = len;
+        Ok(())
+    }
+
     /// Retrieve and validate the number of dimensions of the volume. This is
     /// `dim[0]` after the necessary byte order conversions.
     ///
     /// # Error
     ///
-    /// `NiftiError::` if `dim[0]` does not represent a valid dimensionality
+    /// `NiftiError::InconsistentDim` if `dim[0]` does not represent a valid dimensionality
     /// (it must be positive and not higher than 7).
     pub fn dimensionality(&self) -> Result<usize> {
         validate_dimensionality(&self.dim)
     }
 
+    /// Retrieve the number of dimensions of the volume, ignoring trailing
+    /// dimensions of length 1. For example, a volume with `dim` set to
+    /// `[4, 64, 64, 30, 1, 1, 1, 1]` is effectively 3D. The result is never
+    /// lower than 1.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::InconsistentDim` if the dimensions are invalid.
+    pub fn effective_dimensionality(&self) -> Result<usize> {
+        let dim = self.dim()?;
+        let rank = dim.iter().rposition(|&d| d != 1).map_or(1, |i| i + 1);
+        Ok(rank)
+    }
+
     /// Get the data type as a validated enum.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::NoDataType` if `datatype` is 0 (`DT_UNKNOWN`), which
+    /// usually stems from an uninitialized header, or
+    /// `NiftiError::InvalidCode` if it is any other unknown code.
     pub fn data_type(&self) -> Result<NiftiType> {
-        FromPrimitive::from_i16(self.datatype)
-            .ok_or(NiftiError::InvalidCode("datatype", self.datatype))
+        if self.datatype == 0 {
+            return Err(NiftiError::NoDataType);
+        }
+        NiftiType::try_from(self.datatype)
+    }
+
+    /// Compute the `vox_offset` of a single file (".nii") holding this header
+    /// and the given extensions: the size of the header (`sizeof_hdr`), plus
+    /// the 4 bytes of the extender and the size of the extensions on disk,
+    /// rounded up to a multiple of 16 bytes.
+    ///
+    /// Header files of a file pair (".hdr") should use a `vox_offset` of 0
+    /// instead.
+    pub fn compute_vox_offset(&self, extensions: &ExtensionSequence) -> u64 {
+        let offset = self.sizeof_hdr.max(0) as u64 + 4 + extensions.bytes_on_disk() as u64;
+        offset.div_ceil(16) * 16
+    }
+
+    /// Check that the given volume is described by this header, by comparing
+    /// their dimensions and data types.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InconsistentDim` if the header's dimensions are invalid.
+    /// - `NiftiError::NoDataType` or `NiftiError::InvalidCode` if the header's
+    ///   data type is invalid (see [`data_type`](#method.data_type)).
+    /// - `NiftiError::DimMismatch` if the dimensions differ.
+    /// - `NiftiError::DataTypeMismatch` if the data types differ.
+    pub fn matches_volume(&self, volume: &dyn NiftiVolume) -> Result<()> {
+        let dim = self.dim()?;
+        if dim != volume.dim() {
+            return Err(NiftiError::DimMismatch(dim.to_vec(), volume.dim().to_vec()));
+        }
+        let datatype = self.data_type()?;
+        if datatype != volume.data_type() {
+            return Err(NiftiError::DataTypeMismatch(datatype, volume.data_type()));
+        }
+        Ok(())
+    }
+
+    /// Get the raw code of the spatial units (`xyzt_units & 0o007`), which
+    /// may not correspond to any known unit.
+    pub fn space_unit_code(&self) -> i32 {
+        i32::from(self.xyzt_units & 0o0007)
+    }
+
+    /// Get the raw code of the time units (`xyzt_units & 0o070`), which may
+    /// not correspond to any known unit.
+    pub fn time_unit_code(&self) -> i32 {
+        i32::from(self.xyzt_units & 0o0070)
     }
 
     /// Get the spatial units type as a validated unit enum.
     pub fn xyzt_to_space(&self) -> Result<Unit> {
-        let space_code = self.xyzt_units & 0o0007;
-        FromPrimitive::from_u8(space_code).ok_or(NiftiError::InvalidCode(
+        let space_code = self.space_unit_code();
+        FromPrimitive::from_i32(space_code).ok_or(NiftiError::InvalidCode(
             "xyzt units (space)",
             space_code as i16,
         ))
@@ -285,8 +649,8 @@ impl NiftiHeader {
 
     /// Get the time units type as a validated unit enum.
     pub fn xyzt_to_time(&self) -> Result<Unit> {
-        let time_code = self.xyzt_units & 0o0070;
-        FromPrimitive::from_u8(time_code).ok_or(NiftiError::InvalidCode(
+        let time_code = self.time_unit_code();
+        FromPrimitive::from_i32(time_code).ok_or(NiftiError::InvalidCode(
             "xyzt units (time)",
             time_code as i16,
         ))
@@ -297,6 +661,40 @@ impl NiftiHeader {
         Ok((self.xyzt_to_space()?, self.xyzt_to_time()?))
     }
 
+    /// Get the acquisition time of each frame of the volume, in seconds,
+    /// as `toffset + i * pixdim[4]` for each index `i` along the time axis
+    /// (the 4th dimension). Volumes with less than 4 dimensions have a single
+    /// frame, at `toffset`. Times are converted from the time unit of the
+    /// header, and assumed to be in seconds if the unit is unknown.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InconsistentDim` if the dimensions are invalid.
+    /// - `NiftiError::InvalidCode` if the time unit is invalid or is not a
+    ///   unit of time (such as `Unit::Hz`).
+    /// - `NiftiError::InvalidVoxelSize` if the volume has a time axis and
+    ///   `pixdim[4]` is not a positive finite number.
+    pub fn frame_times(&self) -> Result<Vec<f64>> {
+        let dim = self.dim()?;
+        let time_unit = self.xyzt_to_time()?;
+        let seconds_per_unit = match time_unit {
+            Unit::Unknown | Unit::Sec => 1.,
+            Unit::Msec => 1e-3,
+            Unit::Usec => 1e-6,
+            unit => return Err(NiftiError::InvalidCode("xyzt units (time)", unit as i16)),
+        };
+        let toffset = f64::from(self.toffset) * seconds_per_unit;
+        if dim.len() < 4 {
+            return Ok(vec![toffset]);
+        }
+        let step = f64::from(self.pixdim[4]);
+        if !(step.is_finite() && step > 0.) {
+            return Err(NiftiError::InvalidVoxelSize(step));
+        }
+        let step = step * seconds_per_unit;
+        Ok((0..dim[3]).map(|i| toffset + f64::from(i) * step).collect())
+    }
+
     /// Get the slice order as a validated enum.
     pub fn slice_order(&self) -> Result<SliceOrder> {
         FromPrimitive::from_u8(self.slice_code).ok_or(NiftiError::InvalidCode(
@@ -305,6 +703,21 @@ impl NiftiHeader {
         ))
     }
 
+    /// Retrieve the magic code matching the layout of this header: `b"n+1\0"`
+    /// if the volume follows the header in the same file (`vox_offset` is
+    /// positive), or `b"ni1\0"` if the volume is in a separate file.
+    ///
+    /// Only the NIfTI-1 layout is supported, so the current value of `magic`
+    /// (which could be that of another version, such as `b"n+2\0"`) is not
+    /// taken into account.
+    pub fn canonical_magic(&self) -> [u8; 4] {
+        if self.vox_offset > 0. {
+            *MAGIC_CODE_NIP1
+        } else {
+            *MAGIC_CODE_NI1
+        }
+    }
+
     /// Get the intent as a validated enum.
     pub fn intent(&self) -> Result<Intent> {
         FromPrimitive::from_i16(self.intent_code)
@@ -323,21 +736,32 @@ impl NiftiHeader {
             .ok_or(NiftiError::InvalidCode("sform", self.sform_code))
     }
 
-    /// Ensure that the current `descrip` field is valid and is exactly equal to 80 bytes.
+    /// Check that the current `descrip` field can be written, without modifying it.
     ///
-    /// Descriptions shorter than 80 bytes will be extended with trailing zeros.
-    pub fn validate_description(&mut self) -> Result<()> {
+    /// Descriptions are stored in a fixed field of 80 bytes, so any description of up to 80
+    /// bytes is accepted. Writers call [`validate_description`](#method.validate_description)
+    /// on a copy of the reference header, which pads shorter descriptions with zeros.
+    pub fn check_description(&self) -> Result<()> {
         let len = self.descrip.len();
         if len > 80 {
             Err(NiftiError::IncorrectDescriptionLength(len))
         } else {
-            if len < 80 {
-                self.descrip.extend((len..80).map(|_| 0));
-            }
             Ok(())
         }
     }
 
+    /// Ensure that the current `descrip` field is valid and is exactly equal to 80 bytes.
+    ///
+    /// Descriptions shorter than 80 bytes will be extended with trailing zeros.
+    pub fn validate_description(&mut self) -> Result<()> {
+        self.check_description()?;
+        let len = self.descrip.len();
+        if len < 80 {
+            self.descrip.extend((len..80).map(|_| 0));
+        }
+        Ok(())
+    }
+
     /// Safely set the `descrip` field using a buffer.
     pub fn set_description<D>(&mut self, description: D) -> Result<()>
     where
@@ -368,6 +792,62 @@ impl NiftiHeader {
         self.set_description(description.into().as_bytes())
     }
 
+    /// Safely set the `aux_file` field using a &str, padded with zeros.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::IncorrectFieldLength` if `aux_file` is longer than 24 bytes.
+    pub fn set_aux_file(&mut self, aux_file: &str) -> Result<()> {
+        set_text_field(&mut self.aux_file, "aux_file", aux_file)
+    }
+
+    /// Safely set the `intent_name` field using a &str, padded with zeros.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::IncorrectFieldLength` if `intent_name` is longer than 16 bytes.
+    pub fn set_intent_name(&mut self, intent_name: &str) -> Result<()> {
+        set_text_field(&mut self.intent_name, "intent_name", intent_name)
+    }
+
+    /// Get the `descrip` field as a string, with trailing NUL bytes removed.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::Utf8` if the field is not valid UTF-8.
+    pub fn descrip_str(&self) -> Result<&str> {
+        get_text_field(&self.descrip)
+    }
+
+    /// Get the `aux_file` field as a string, with trailing NUL bytes removed.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::Utf8` if the field is not valid UTF-8.
+    pub fn aux_file_str(&self) -> Result<&str> {
+        get_text_field(&self.aux_file)
+    }
+
+    /// Get the `intent_name` field as a string, with trailing NUL bytes removed.
+    ///
+    /// # Error
+    ///
+    /// `NiftiError::Utf8` if the field is not valid UTF-8.
+    pub fn intent_name_str(&self) -> Result<&str> {
+        get_text_field(&self.intent_name)
+    }
+
+    /// Set the voxel sizes along the three spatial axes (`pixdim[1..=3]`), in
+    /// the spatial unit of `xyzt_units`. The qfac (`pixdim[0]`) and the
+    /// remaining entries, such as the time step in `pixdim[4]`, are kept.
+    ///
+    /// Note that the 'sform' and 'qform' transformations are not updated.
+    pub fn set_pixdim_spatial(&mut self, spacing: [f64; 3]) {
+        for (p, s) in self.pixdim[1..=3].iter_mut().zip(spacing) {
+            *p = s as f32;
+        }
+    }
+
     /// Check whether `pixdim[0]` is either -1 or 1.
     #[inline]
     fn is_pixdim_0_valid(&self) -> bool {
@@ -399,6 +879,83 @@ impl NiftiHeader {
         }
     }
 
+    /// Retrieve both the 'sform' and the 'qform' transformations, each only
+    /// if its code (`sform_code` or `qform_code`) is not 0 (unknown).
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidCode` if a code does not name a known method;
+    /// - `NiftiError::InvalidVoxelSize` if a spacing (`pixdim[1..4]`) is
+    ///   negative, and `NiftiError::InvalidQfac` if `pixdim[0]` is neither 1
+    ///   nor -1, when the 'qform' is retrieved. Unlike
+    ///   [`qform_affine`](#method.qform_affine), this does not panic.
+    #[allow(clippy::type_complexity)]
+    pub fn affines<T>(&self) -> Result<(Option<Matrix4<T>>, Option<Matrix4<T>>)>
+    where
+        T: RealField,
+        f32: SubsetOf<T>,
+    {
+        let sform = match self.sform()? {
+            XForm::Unknown => None,
+            _ => Some(self.sform_affine()),
+        };
+        let qform = match self.qform()? {
+            XForm::Unknown => None,
+            _ => {
+                if let Some(&d) = self.pixdim[1..4].iter().find(|&&d| d < 0.) {
+                    return Err(NiftiError::InvalidVoxelSize(f64::from(d)));
+                }
+                if !self.is_pixdim_0_valid() {
+                    return Err(NiftiError::InvalidQfac(self.pixdim[0]));
+                }
+                Some(self.qform_affine())
+            }
+        };
+        Ok((sform, qform))
+    }
+
+    /// Retrieve the best of available transformations, as in
+    /// [`affine`](#method.affine), failing as in
+    /// [`affines`](#method.affines) instead of panicking.
+    pub(crate) fn checked_affine<T>(&self) -> Result<Matrix4<T>>
+    where
+        T: RealField,
+        f32: SubsetOf<T>,
+    {
+        let (sform, qform) = self.affines()?;
+        Ok(sform.or(qform).unwrap_or_else(|| self.base_affine()))
+    }
+
+    /// Check whether the best available transformation (see [`affine`]) is
+    /// oblique, meaning that its voxel axes are not aligned with the axes of
+    /// the world space.
+    ///
+    /// Each voxel axis (column of the rotation part of the affine) is
+    /// normalized, and the volume is considered oblique if any of them deviates
+    /// from a world axis by more than `tol`, measured as `1 - max(|c_i|)`.
+    /// Axes of zero length are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Fails as in [`affines`], if a code is invalid or if the 'qform' is
+    /// retrieved from invalid spacings or qfac.
+    ///
+    /// [`affine`]: #method.affine
+    /// [`affines`]: #method.affines
+    pub fn is_oblique(&self, tol: f64) -> Result<bool> {
+        let (rotation, _) = affine_and_translation(&self.checked_affine::<f64>()?);
+        for column in rotation.column_iter() {
+            let norm = column.norm();
+            if norm == 0.0 {
+                continue;
+            }
+            if 1.0 - column.amax() / norm > tol {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     /// Retrieve affine transformation from 'sform' fields.
     pub fn sform_affine<T>(&self) -> Matrix4<T>
     where
@@ -475,7 +1032,7 @@ impl NiftiHeader {
     ///
     /// * 'sform' from unmodified `affine`, with `sform_code` set to `AlignedAnat`.
     /// * 'qform' from a quaternion built from `affine`. However, the 'qform' won't be used by most
-    /// nifti readers because the `qform_code` will be set to `Unknown`.
+    ///   nifti readers because the `qform_code` will be set to `Unknown`.
     pub fn set_affine<T>(&mut self, affine: &Matrix4<T>)
     where
         T: RealField,
@@ -569,14 +1126,38 @@ impl NiftiHeader {
     }
 }
 
-fn parse_header_1<S>(input: S) -> Result<NiftiHeader>
+/// Copy `value` into a fixed size text field, padding it with zeros.
+fn set_text_field(field: &mut [u8], name: &'static str, value: &str) -> Result<()> {
+    let bytes = value.as_bytes();
+    if bytes.len() > field.len() {
+        return Err(NiftiError::IncorrectFieldLength(
+            name,
+            bytes.len(),
+            field.len(),
+        ));
+    }
+    field.fill(0);
+    field[..bytes.len()].copy_from_slice(bytes);
+    Ok(())
+}
+
+fn get_text_field(field: &[u8]) -> Result<&str> {
+    let len = field.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    Ok(std::str::from_utf8(&field[..len])?)
+}
+
+fn parse_header_1<S>(mut input: S) -> Result<NiftiHeader>
 where
     S: Read,
 {
     let mut h = NiftiHeader::default();
 
+    // keep the leading bytes around for error reporting
+    let mut leading = [0; 16];
+    input.read_exact(&mut leading)?;
+
     // try the system's native endianness first
-    let mut input = ByteOrdered::native(input);
+    let mut input = ByteOrdered::native((&leading[..]).chain(input));
 
     h.sizeof_hdr = input.read_i32()?;
     input.read_exact(&mut h.data_type)?;
@@ -587,7 +1168,7 @@ where
     h.dim_info = input.read_u8()?;
     h.dim[0] = input.read_u16()?;
 
-    if h.dim[0] > 7 {
+    if is_opposite_endianness(h.sizeof_hdr, h.dim[0]) {
         h.endianness = Endianness::native().to_opposite();
 
         // swap bytes read so far, continue with the opposite endianness
@@ -595,16 +1176,39 @@ where
         h.extents = h.extents.swap_bytes();
         h.session_error = h.session_error.swap_bytes();
         h.dim[0] = h.dim[0].swap_bytes();
-        parse_header_2(h, input.into_opposite())
+        parse_header_2(h, input.into_opposite(), leading)
     } else {
         // all is well
         h.endianness = Endianness::native();
-        parse_header_2(h, input)
+        parse_header_2(h, input, leading)
+    }
+}
+
+/// Decide whether a header is in the opposite of the native byte order,
+/// given its `sizeof_hdr` and `dim[0]` as read in the native byte order.
+///
+/// `dim[0]` is only a valid dimensionality (at most 7) in one of the byte
+/// orders, unless it is ambiguous (such as 0, which reads the same either
+/// way) or invalid in both. In those cases, `sizeof_hdr` is checked against
+/// its expected value in each byte order before falling back to `dim[0]`.
+fn is_opposite_endianness(sizeof_hdr: i32, dim0: u16) -> bool {
+    match (dim0 <= 7, dim0.swap_bytes() <= 7) {
+        (true, false) => false,
+        (false, true) => true,
+        _ => match sizeof_hdr {
+            SIZEOF_HDR_NI1 => false,
+            SIZEOF_HDR_NI1_SWAPPED => true,
+            _ => dim0 > 7,
+        },
     }
 }
 
 /// second part of header parsing
-fn parse_header_2<S, E>(mut h: NiftiHeader, mut input: ByteOrdered<S, E>) -> Result<NiftiHeader>
+fn parse_header_2<S, E>(
+    mut h: NiftiHeader,
+    mut input: ByteOrdered<S, E>,
+    leading: [u8; 16],
+) -> Result<NiftiHeader>
 where
     S: Read,
     E: Endian,
@@ -661,7 +1265,7 @@ where
     debug_assert_eq!(h.descrip.len(), 80);
 
     if &h.magic != MAGIC_CODE_NI1 && &h.magic != MAGIC_CODE_NIP1 {
-        Err(NiftiError::InvalidFormat)
+        Err(NiftiError::InvalidFormat(leading))
     } else {
         Ok(h)
     }
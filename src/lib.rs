@@ -63,8 +63,8 @@
 //!
 #![deny(missing_debug_implementations)]
 #![warn(missing_docs, unused_extern_crates, trivial_casts, unused_results)]
-#![allow(clippy::unit_arg)]
-#![recursion_limit = "128"]
+#![allow(clippy::unit_arg, non_local_definitions)]
+#![recursion_limit = "256"]
 
 #[cfg(all(test, feature = "nalgebra_affine"))]
 #[macro_use]
@@ -79,13 +79,12 @@ pub mod object;
 pub mod typedef;
 mod util;
 pub mod volume;
-#[cfg(feature = "ndarray_volumes")]
 pub mod writer;
 
 pub use byteordered::Endianness;
 pub use error::{NiftiError, Result};
-pub use extension::{Extender, Extension, ExtensionSequence};
-pub use header::NiftiHeader;
+pub use extension::{Extender, Extension, ExtensionSequence, ECODE_FLOAT16, ECODE_SHA256};
+pub use header::{HeaderWarning, NiftiHeader};
 pub use object::{
     InMemNiftiObject, NiftiObject, ReaderOptions, ReaderStreamedOptions, StreamedNiftiObject,
 };
@@ -94,5 +93,6 @@ pub use volume::element::DataElement;
 #[cfg(feature = "ndarray_volumes")]
 pub use volume::ndarray::IntoNdArray;
 pub use volume::{
-    InMemNiftiVolume, NiftiVolume, RandomAccessNiftiVolume, Sliceable, StreamedNiftiVolume,
+    concat_volumes, ClampBehavior, ConversionPolicy, InMemNiftiVolume, NiftiVolume,
+    RandomAccessNiftiVolume, Sliceable, StreamedNiftiVolume, VolumeDiff, VolumeWithHeader,
 };
@@ -1,16 +1,45 @@
 //! Module for handling and retrieving complete NIFTI-1 objects.
 
+#[cfg(feature = "nalgebra_affine")]
+use crate::affine::affine_and_translation;
 use crate::error::NiftiError;
 use crate::error::Result;
-use crate::extension::{Extender, ExtensionSequence};
-use crate::header::NiftiHeader;
+use crate::extension::{Extender, Extension, ExtensionSequence};
 use crate::header::MAGIC_CODE_NI1;
-use crate::util::{into_img_file_gz, is_gz_file, open_file_maybe_gz};
-use crate::volume::inmem::InMemNiftiVolume;
+#[cfg(feature = "sha2")]
+use crate::header::MAGIC_CODE_NIP1;
+use crate::header::{HeaderWarning, NiftiHeader};
+use crate::typedef::{Intent, NiftiType};
+#[cfg(feature = "sha2")]
+use crate::util::adapt_bytes;
+#[cfg(feature = "json")]
+use crate::util::into_sidecar_file;
+use crate::util::{into_img_file_gz, is_gz_file, nb_bytes_for_data, open_file_maybe_gz};
+#[cfg(feature = "ndarray_volumes")]
+use crate::volume::element::DataElement;
+use crate::volume::inmem::{InMemNiftiVolume, VolumeWithHeader};
+#[cfg(feature = "ndarray_volumes")]
+use crate::volume::ndarray::IntoNdArray;
 use crate::volume::streamed::StreamedNiftiVolume;
 use crate::volume::{FromSource, FromSourceOptions, NiftiVolume};
+#[cfg(feature = "sha2")]
+use crate::writer::write_header;
+#[cfg(feature = "ndarray_volumes")]
+use crate::writer::WriterOptions;
 use byteordered::ByteOrdered;
+#[cfg(feature = "sha2")]
+use byteordered::Endianness;
+use either::Either;
 use flate2::bufread::GzDecoder;
+#[cfg(feature = "nalgebra_affine")]
+use nalgebra::Matrix4;
+#[cfg(feature = "ndarray_volumes")]
+use ndarray::{Array, IxDyn};
+#[cfg(feature = "sha2")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "nalgebra_affine")]
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::{self, BufReader, Read};
 use std::path::Path;
@@ -22,6 +51,15 @@ pub use crate::util::{GzDecodedFile, MaybeGzDecodedFile};
 pub struct ReaderOptions {
     /// Whether to automatically fix value in the header
     fix_header: bool,
+    /// Whether to reject headers with questionable values
+    strict: bool,
+    /// Whether to decode volumes marked with the float16 extension
+    interpret_f16_extension: bool,
+    /// Whether to recover from known header problems before reading the volume
+    lenient: bool,
+    /// Whether to check the volume against its checksum extension
+    #[cfg(feature = "sha2")]
+    verify_checksum: bool,
 }
 
 impl ReaderOptions {
@@ -38,6 +76,56 @@ impl ReaderOptions {
         self
     }
 
+    /// Sets the options to reject headers with questionable values,
+    /// which would otherwise be accepted.
+    ///
+    /// Currently, the following problems are reported:
+    /// - `NiftiError::InvalidVoxOffset` if `vox_offset` is negative or not an
+    ///   integer, since the volume would be read from a truncated offset.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the options to recover from known header problems, in order to
+    /// load whatever can be loaded.
+    ///
+    /// Currently, this enables the following behaviors:
+    /// - the fixes of [`fix_header`](#method.fix_header);
+    /// - if `dim[0]` is higher than 7, or claims more dimensions than the
+    ///   leading positive values of `dim`, it is lowered to the number of
+    ///   leading positive dimensions (trailing zeros in `dim` are a common
+    ///   mistake of some writers), before the volume is read.
+    ///
+    /// Headers which cannot be recovered are still rejected. This does not
+    /// disable the checks of [`strict`](#method.strict).
+    pub fn lenient(&mut self, lenient: bool) -> &mut Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Sets the options to decode `Int16` volumes marked with the float16
+    /// extension (see [`ECODE_FLOAT16`](../extension/constant.ECODE_FLOAT16.html))
+    /// into `Float32` volumes, as written by
+    /// [`WriterOptions::write_nifti_f16`](../writer/struct.WriterOptions.html#method.write_nifti_f16).
+    ///
+    /// The header's `datatype` and `bitpix` are updated accordingly. Volumes
+    /// without the extension are left untouched.
+    pub fn interpret_f16_extension(&mut self, interpret_f16_extension: bool) -> &mut Self {
+        self.interpret_f16_extension = interpret_f16_extension;
+        self
+    }
+
+    /// Sets the options to check the volume against the checksum extension
+    /// (see [`ECODE_SHA256`](../extension/constant.ECODE_SHA256.html)), if
+    /// present, failing with `NiftiError::ChecksumMismatch` if they disagree.
+    /// Objects without the extension are not checked.
+    #[cfg(feature = "sha2")]
+    pub fn verify_checksum(&mut self, verify_checksum: bool) -> &mut Self {
+        self.verify_checksum = verify_checksum;
+        self
+    }
+
     /// Retrieve the full contents of a NIFTI object.
     ///
     /// The given file system path is used as reference. If the file only contains the header, this
@@ -56,16 +144,223 @@ impl ReaderOptions {
     where
         P: AsRef<Path>,
     {
-        let file = BufReader::new(File::open(&path)?);
-        let mut obj = if is_gz_file(&path) {
-            InMemNiftiObject::from_file_impl(path, GzDecoder::new(file), Default::default())
+        self.read_file_with_progress(path, no_progress)
+    }
+
+    /// Retrieve the full contents of a NIFTI object, along with the contents
+    /// of its BIDS JSON sidecar, if one exists.
+    ///
+    /// The sidecar is the file next to `path` with the same name and the
+    /// extension ".json" (so "sub-01_T1w.json" for "sub-01_T1w.nii.gz").
+    /// Its contents are parsed as JSON, or `None` is returned if the file
+    /// does not exist. The object is read as in
+    /// [`read_file`](#method.read_file).
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidSidecar` if the sidecar is not valid JSON.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nifti::ReaderOptions;
+    ///
+    /// let (obj, sidecar) = ReaderOptions::new().read_with_sidecar("sub-01_T1w.nii.gz")?;
+    /// if let Some(tr) = sidecar.and_then(|json| json["RepetitionTime"].as_f64()) {
+    ///     println!("TR: {} s", tr);
+    /// }
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn read_with_sidecar<P>(
+        &self,
+        path: P,
+    ) -> Result<(InMemNiftiObject, Option<serde_json::Value>)>
+    where
+        P: AsRef<Path>,
+    {
+        let sidecar_path = into_sidecar_file(path.as_ref().to_owned());
+        let obj = self.read_file(path)?;
+        let sidecar = match std::fs::read(sidecar_path) {
+            Ok(json) => Some(
+                serde_json::from_slice(&json).map_err(|e| NiftiError::InvalidSidecar(e.into()))?,
+            ),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok((obj, sidecar))
+    }
+
+    /// Retrieve the header and the volume of a NIFTI object, with the volume
+    /// converted to an ndarray of the given element type.
+    ///
+    /// This combines [`read_file`](#method.read_file) with
+    /// [`IntoNdArray::into_ndarray`](../volume/ndarray/trait.IntoNdArray.html).
+    /// The extensions are discarded.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nifti::ReaderOptions;
+    ///
+    /// let (header, volume) = ReaderOptions::new().read_file_ndarray::<f32, _>("minimal.nii.gz")?;
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn read_file_ndarray<T, P>(&self, path: P) -> Result<(NiftiHeader, Array<T, IxDyn>)>
+    where
+        T: DataElement,
+        P: AsRef<Path>,
+    {
+        let obj = self.read_file(path)?;
+        let GenericNiftiObject { header, volume, .. } = obj;
+        Ok((header, volume.into_ndarray()?))
+    }
+
+    /// Retrieve the header and a single timepoint of a NIfTI object, without
+    /// loading the rest of the volume into memory.
+    ///
+    /// The last axis of the volume is taken as the time axis, so the `k`-th
+    /// timepoint is the `k`-th sub-volume spanning all other axes (such as
+    /// the `k`-th 3D volume of a 4D file). The returned header is the one in
+    /// the file, describing the full volume. The voxel data before the
+    /// timepoint is skipped, which for GZip-compressed files means that it is
+    /// decoded and discarded. The volume file of a header file is searched
+    /// for as in [`read_file`](#method.read_file). As in `read_file`, a
+    /// half precision volume is decoded if
+    /// [`interpret_f16_extension`](#method.interpret_f16_extension) is set,
+    /// in which case the header is updated to match.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if the volume is 1D.
+    /// - `NiftiError::OutOfBounds` if `k` is not lower than the number of
+    ///   timepoints.
+    pub fn read_timepoint<P>(&self, path: P, k: u64) -> Result<(NiftiHeader, InMemNiftiVolume)>
+    where
+        P: AsRef<Path>,
+    {
+        let mut stream = open_file_maybe_gz(&path)?;
+        let mut header = self.read_header(&mut stream)?;
+
+        let dim = header.dim()?;
+        let rank = dim.len();
+        if rank < 2 {
+            return Err(NiftiError::IncorrectVolumeDimensionality(2, rank as u16));
+        }
+        if k >= u64::from(dim[rank - 1]) {
+            let mut coords = vec![0; rank];
+            coords[rank - 1] = u16::try_from(k).unwrap_or(u16::MAX);
+            return Err(NiftiError::OutOfBounds(coords));
+        }
+        let mut sub_header = header.clone();
+        sub_header.dim[0] -= 1;
+        sub_header.dim[rank] = 1;
+        let nb_bytes = nb_bytes_for_data(&sub_header)?;
+
+        let vox_offset = header.vox_offset as u64;
+        let (extensions, mut stream) = if &header.magic == MAGIC_CODE_NI1 {
+            // the extensions span the rest of the header file
+            let extender = Extender::from_reader_optional(&mut stream)?.unwrap_or_default();
+            let mut ext_data = Vec::new();
+            let _ = stream.read_to_end(&mut ext_data)?;
+            let extensions = ExtensionSequence::from_reader(
+                extender,
+                ByteOrdered::runtime(&ext_data[..], header.endianness),
+                ext_data.len(),
+            )?;
+
+            let mut img_path = into_img_file_gz(path.as_ref().to_path_buf());
+            let file = open_file_maybe_gz(&img_path).or_else(|e| {
+                if e.kind() == io::ErrorKind::NotFound {
+                    let _ = img_path.set_extension("");
+                    open_file_maybe_gz(&img_path)
+                } else {
+                    Err(e)
+                }
+            });
+            let mut file = file.map_err(NiftiError::MissingVolumeFile)?;
+            skip_bytes(&mut file, vox_offset)?;
+            (extensions, file)
         } else {
-            InMemNiftiObject::from_file_impl(path, file, Default::default())
-        }?;
-        if self.fix_header {
-            obj.header.fix();
+            let extender = Extender::from_reader(&mut stream)?;
+            let len = (header.vox_offset as usize).saturating_sub(352);
+            let extensions = ExtensionSequence::from_reader(
+                extender,
+                ByteOrdered::runtime(&mut stream, header.endianness),
+                len,
+            )?;
+            (extensions, stream)
+        };
+        skip_bytes(&mut stream, k * nb_bytes as u64)?;
+
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(nb_bytes)
+            .map_err(|e| NiftiError::ReserveVolume(nb_bytes, e))?;
+        let nb_bytes_read = (&mut stream)
+            .take(nb_bytes as u64)
+            .read_to_end(&mut raw_data)?;
+        if nb_bytes_read != nb_bytes {
+            return Err(NiftiError::IncompatibleLength(nb_bytes_read, nb_bytes));
         }
-        Ok(obj)
+        let volume = InMemNiftiVolume::from_raw_data(&sub_header, raw_data)?;
+        let volume = self.decode_f16_if_requested(&mut header, &extensions, volume)?;
+        Ok((header, volume))
+    }
+
+    /// Retrieve the full contents of a NIFTI object, reporting the progress of
+    /// reading the volume.
+    ///
+    /// Behaves like [`read_file`](#method.read_file), but `progress` is called
+    /// with the number of volume bytes read so far and the total number of bytes
+    /// expected, as given by the header. When the file is GZip-compressed, both
+    /// numbers refer to the decompressed data. Upon success, the last call
+    /// reports the total number of bytes as read.
+    pub fn read_file_with_progress<P, F>(
+        &self,
+        path: P,
+        mut progress: F,
+    ) -> Result<InMemNiftiObject>
+    where
+        P: AsRef<Path>,
+        F: FnMut(usize, usize),
+    {
+        let mut file = open_file_maybe_gz(&path)?;
+        let header = self.read_header(&mut file)?;
+        let obj = InMemNiftiObject::from_file_impl_with_header(
+            path,
+            header,
+            file,
+            Default::default(),
+            &mut progress,
+        )?;
+        self.finish(obj)
+    }
+
+    /// Retrieve the full contents of a NIFTI object, along with the
+    /// non-fatal issues found in its header, as listed by
+    /// [`NiftiHeader::warnings`](struct.NiftiHeader.html#method.warnings).
+    ///
+    /// The warnings describe the header as stored in the file, before any
+    /// fix requested in these options is applied.
+    pub fn read_file_with_warnings<P>(
+        &self,
+        path: P,
+    ) -> Result<(InMemNiftiObject, Vec<HeaderWarning>)>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = open_file_maybe_gz(&path)?;
+        let (header, warnings) = self.read_header_with_warnings(&mut file)?;
+        let obj = InMemNiftiObject::from_file_impl_with_header(
+            path,
+            header,
+            file,
+            Default::default(),
+            &mut |_, _| {},
+        )?;
+        Ok((self.finish(obj)?, warnings))
     }
 
     /// Retrieve a NIFTI object as separate header and volume files.
@@ -76,20 +371,89 @@ impl ReaderOptions {
         P: AsRef<Path>,
         Q: AsRef<Path>,
     {
-        let file = BufReader::new(File::open(&hdr_path)?);
-        let mut obj = if is_gz_file(&hdr_path) {
-            InMemNiftiObject::from_file_pair_impl(
-                GzDecoder::new(file),
-                vol_path,
-                Default::default(),
-            )
+        let mut file = open_file_maybe_gz(&hdr_path)?;
+        let header = self.read_header(&mut file)?;
+        let obj = InMemNiftiObject::from_file_pair_impl_with_header(
+            header,
+            file,
+            vol_path,
+            Default::default(),
+            &mut no_progress,
+        )?;
+        self.finish(obj)
+    }
+
+    /// Read the header, recovering from known problems if requested.
+    ///
+    /// The header is fixed before the volume is read, so that the fixed
+    /// scaling is the one applied to the voxel values, and checked in strict
+    /// mode before any memory is allocated for the volume.
+    fn read_header<R: Read>(&self, stream: R) -> Result<NiftiHeader> {
+        self.read_header_with_warnings(stream)
+            .map(|(header, _)| header)
+    }
+
+    /// Read the header as in `read_header`, also collecting the warnings of
+    /// the header as it was read.
+    fn read_header_with_warnings<R: Read>(
+        &self,
+        stream: R,
+    ) -> Result<(NiftiHeader, Vec<HeaderWarning>)> {
+        let mut header = NiftiHeader::from_reader(stream)?;
+        let warnings = header.warnings();
+        if self.fix_header || self.lenient {
+            header.fix();
+        }
+        if self.lenient {
+            clamp_trailing_dims(&mut header);
+        }
+        if self.strict {
+            check_vox_offset(&header)?;
+        }
+        Ok((header, warnings))
+    }
+
+    /// Apply the post-processing and checks requested in these options.
+    fn finish(&self, obj: InMemNiftiObject) -> Result<InMemNiftiObject> {
+        #[cfg(feature = "sha2")]
+        if self.verify_checksum {
+            if let Some(digest) = obj.extensions.sha256_digest() {
+                if obj.volume.sha256() != digest {
+                    return Err(NiftiError::ChecksumMismatch);
+                }
+            }
+        }
+        let GenericNiftiObject {
+            mut header,
+            extensions,
+            volume,
+        } = obj;
+        let volume = self.decode_f16_if_requested(&mut header, &extensions, volume)?;
+        Ok(GenericNiftiObject {
+            header,
+            extensions,
+            volume,
+        })
+    }
+
+    /// Decode a volume marked with the float16 extension to `Float32` and
+    /// update the header to match, if requested in these options.
+    fn decode_f16_if_requested(
+        &self,
+        header: &mut NiftiHeader,
+        extensions: &ExtensionSequence,
+        volume: InMemNiftiVolume,
+    ) -> Result<InMemNiftiVolume> {
+        if self.interpret_f16_extension
+            && header.datatype == NiftiType::Int16 as i16
+            && extensions.has_float16()
+        {
+            header.datatype = NiftiType::Float32 as i16;
+            header.bitpix = 32;
+            volume.decode_f16()
         } else {
-            InMemNiftiObject::from_file_pair_impl(file, vol_path, Default::default())
-        }?;
-        if self.fix_header {
-            obj.header.fix();
+            Ok(volume)
         }
-        Ok(obj)
     }
 }
 
@@ -98,6 +462,8 @@ impl ReaderOptions {
 pub struct ReaderStreamedOptions {
     /// Whether to automatically fix value in the header
     fix_header: bool,
+    /// Whether to reject headers with questionable values
+    strict: bool,
 }
 
 impl ReaderStreamedOptions {
@@ -114,6 +480,17 @@ impl ReaderStreamedOptions {
         self
     }
 
+    /// Sets the options to reject headers with questionable values,
+    /// which would otherwise be accepted.
+    ///
+    /// Currently, the following problems are reported:
+    /// - `NiftiError::InvalidVoxOffset` if `vox_offset` is negative or not an
+    ///   integer, since the volume would be read from a truncated offset.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
     /// Retrieve the NIfTI object and prepare the volume for streamed reading.
     ///
     /// The given file system path is used as reference. If the file only contains the header, this
@@ -138,12 +515,15 @@ impl ReaderStreamedOptions {
     where
         P: AsRef<Path>,
     {
-        let reader = open_file_maybe_gz(&path)?;
-        let mut obj = StreamedNiftiObject::from_file_impl(path, reader, None)?;
-        if self.fix_header {
-            obj.header.fix();
-        }
-        Ok(obj)
+        let mut reader = open_file_maybe_gz(&path)?;
+        let header = self.read_header(&mut reader)?;
+        StreamedNiftiObject::from_file_impl_with_header(
+            path,
+            header,
+            reader,
+            None,
+            &mut no_progress,
+        )
     }
 
     /// Retrieve the NIfTI object and prepare the volume for streamed reading,
@@ -160,12 +540,15 @@ impl ReaderStreamedOptions {
     where
         P: AsRef<Path>,
     {
-        let reader = open_file_maybe_gz(&path)?;
-        let mut obj = StreamedNiftiObject::from_file_impl(path, reader, Some(slice_rank))?;
-        if self.fix_header {
-            obj.header.fix();
-        }
-        Ok(obj)
+        let mut reader = open_file_maybe_gz(&path)?;
+        let header = self.read_header(&mut reader)?;
+        StreamedNiftiObject::from_file_impl_with_header(
+            path,
+            header,
+            reader,
+            Some(slice_rank),
+            &mut no_progress,
+        )
     }
 
     /// Retrieve a NIfTI object as separate header and volume files, for streamed volume reading.
@@ -195,13 +578,15 @@ impl ReaderStreamedOptions {
         P: AsRef<Path>,
         Q: AsRef<Path>,
     {
-        let reader = open_file_maybe_gz(hdr_path)?;
-        let mut obj =
-            StreamedNiftiObject::from_file_pair_impl(reader, vol_path, Default::default())?;
-        if self.fix_header {
-            obj.header.fix();
-        }
-        Ok(obj)
+        let mut reader = open_file_maybe_gz(hdr_path)?;
+        let header = self.read_header(&mut reader)?;
+        StreamedNiftiObject::from_file_pair_impl_with_header(
+            header,
+            reader,
+            vol_path,
+            Default::default(),
+            &mut no_progress,
+        )
     }
 
     /// Retrieve a NIfTI object as separate header and volume files, for streamed volume reading,
@@ -218,12 +603,28 @@ impl ReaderStreamedOptions {
         P: AsRef<Path>,
         Q: AsRef<Path>,
     {
-        let reader = open_file_maybe_gz(hdr_path)?;
-        let mut obj = StreamedNiftiObject::from_file_pair_impl(reader, vol_path, Some(slice_rank))?;
+        let mut reader = open_file_maybe_gz(hdr_path)?;
+        let header = self.read_header(&mut reader)?;
+        StreamedNiftiObject::from_file_pair_impl_with_header(
+            header,
+            reader,
+            vol_path,
+            Some(slice_rank),
+            &mut no_progress,
+        )
+    }
+
+    /// Read the header, fixing it before the volume is read if requested,
+    /// and checking it in strict mode.
+    fn read_header<R: Read>(&self, stream: R) -> Result<NiftiHeader> {
+        let mut header = NiftiHeader::from_reader(stream)?;
         if self.fix_header {
-            obj.header.fix();
+            header.fix();
         }
-        Ok(obj)
+        if self.strict {
+            check_vox_offset(&header)?;
+        }
+        Ok(header)
     }
 }
 
@@ -243,6 +644,18 @@ pub trait NiftiObject {
     /// Obtain a reference to the object's extensions.
     fn extensions(&self) -> &ExtensionSequence;
 
+    /// Obtain the number of extensions in the object.
+    fn extension_count(&self) -> usize {
+        self.extensions().len()
+    }
+
+    /// Obtain the total size of the object's extensions on disk, in bytes,
+    /// as claimed by their `esize` fields. The 4-byte extender is not
+    /// included.
+    fn extension_bytes(&self) -> u64 {
+        self.extensions().bytes_on_disk() as u64
+    }
+
     /// Obtain a reference to the object's volume.
     fn volume(&self) -> &Self::Volume;
 
@@ -286,6 +699,12 @@ where
     }
 }
 
+impl<V> AsRef<NiftiHeader> for GenericNiftiObject<V> {
+    fn as_ref(&self) -> &NiftiHeader {
+        &self.header
+    }
+}
+
 /// A NIfTI object containing an in-memory volume.
 pub type InMemNiftiObject = GenericNiftiObject<InMemNiftiVolume>;
 
@@ -313,9 +732,14 @@ impl InMemNiftiObject {
 
         let file = BufReader::new(File::open(&path)?);
         if gz {
-            Self::from_file_impl(path, GzDecoder::new(file), Default::default())
+            Self::from_file_impl(
+                path,
+                GzDecoder::new(file),
+                Default::default(),
+                &mut no_progress,
+            )
         } else {
-            Self::from_file_impl(path, file, Default::default())
+            Self::from_file_impl(path, file, Default::default(), &mut no_progress)
         }
     }
 
@@ -335,10 +759,342 @@ impl InMemNiftiObject {
 
         let file = BufReader::new(File::open(&hdr_path)?);
         if gz {
-            Self::from_file_pair_impl(GzDecoder::new(file), vol_path, Default::default())
+            Self::from_file_pair_impl(
+                GzDecoder::new(file),
+                vol_path,
+                Default::default(),
+                &mut no_progress,
+            )
+        } else {
+            Self::from_file_pair_impl(file, vol_path, Default::default(), &mut no_progress)
+        }
+    }
+
+    /// Construct a NIfTI object from the full contents of a single file
+    /// (".nii" or ".nii.gz") held in memory, such as a response body
+    /// downloaded over HTTP. Any buffer which can be viewed as a byte slice
+    /// is accepted, including `Vec<u8>` and `bytes::Bytes`. GZip compressed
+    /// data is detected by its magic number and decoded on the fly.
+    ///
+    /// The voxel data is copied into the object's volume, so the buffer is
+    /// not retained.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nifti::InMemNiftiObject;
+    ///
+    /// let data: Vec<u8> = std::fs::read("minimal.nii.gz")?;
+    /// let obj = InMemNiftiObject::from_bytes(data)?;
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    pub fn from_bytes<B>(data: B) -> Result<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let data = data.as_ref();
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Self::from_reader(GzDecoder::new(data))
         } else {
-            Self::from_file_pair_impl(file, vol_path, Default::default())
+            Self::from_reader(data)
+        }
+    }
+
+    /// Move the volume out of the object and convert it into an ndarray of
+    /// `f64`, with the scaling (`scl_slope` and `scl_inter`) applied.
+    ///
+    /// This is equivalent to `obj.into_volume().into_ndarray::<f64>()`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nifti::{InMemNiftiObject, ReaderOptions};
+    ///
+    /// let obj: InMemNiftiObject = ReaderOptions::new().read_file("minimal.nii.gz")?;
+    /// let volume = obj.into_ndarray_f64()?;
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn into_ndarray_f64(self) -> Result<Array<f64, IxDyn>> {
+        self.volume.into_ndarray()
+    }
+
+    /// Write the object to a file, along with its extensions.
+    ///
+    /// The output format follows from the file extension, as in
+    /// [`WriterOptions::new`](../writer/struct.WriterOptions.html#method.new).
+    /// The header is used as the reference header, and the volume is written
+    /// as is, with [`write_raw_volume`](../writer/struct.WriterOptions.html#method.write_raw_volume).
+    /// Use [`WriterOptions`](../writer/struct.WriterOptions.html) directly for
+    /// more control over the output.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use nifti::{NiftiObject, ReaderOptions};
+    ///
+    /// let mut obj = ReaderOptions::new().read_file("minimal.nii.gz")?;
+    /// obj.header_mut().set_description_str("edited")?;
+    /// obj.write("edited.nii.gz")?;
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn write<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        WriterOptions::new(path)
+            .reference_header(&self.header)
+            .with_extensions(self.extensions.clone())
+            .write_raw_volume(&self.volume)
+    }
+
+    /// Check that the voxel values are consistent with the volume's data type
+    /// and intent, as a quality control measure.
+    ///
+    /// The following checks are made on the scaled values:
+    /// - with an integer data type, all values must be finite, which may
+    ///   otherwise happen with an invalid `scl_slope` or `scl_inter`;
+    /// - with the `Intent::Label` intent, all values must be non-negative
+    ///   integers.
+    ///
+    /// Complex and RGB(A) values are not checked, unless the volume is a
+    /// label volume.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidVoxelValue` with the index (in column major
+    ///   order) and value of the first invalid voxel.
+    /// - `NiftiError::UnsupportedDataType` if the volume is a label volume and
+    ///   its data type is not a real scalar type.
+    pub fn validate_values(&self) -> Result<()> {
+        let is_label = self.header.intent_code == Intent::Label as i16;
+        let datatype = self.volume.data_type();
+        if !is_label && matches!(datatype, NiftiType::Float32 | NiftiType::Float64) {
+            return Ok(());
+        }
+        let values = match self.volume.iter_f64() {
+            Ok(values) => values,
+            Err(_) if !is_label => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for (index, value) in values.enumerate() {
+            if !value.is_finite() || (is_label && (value < 0. || value.fract() != 0.)) {
+                return Err(NiftiError::InvalidVoxelValue(index, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether this object is equal to another, as with `==`, except
+    /// that the extensions may appear in any order. Extensions are compared
+    /// by code and content, so duplicate extensions must appear the same
+    /// number of times in both objects.
+    pub fn eq_ignoring_extension_order(&self, other: &InMemNiftiObject) -> bool {
+        if self.header != other.header
+            || self.volume != other.volume
+            || self.extensions.extender() != other.extensions.extender()
+            || self.extensions.len() != other.extensions.len()
+        {
+            return false;
+        }
+        fn sorted(extensions: &ExtensionSequence) -> Vec<&Extension> {
+            let mut extensions: Vec<_> = extensions.iter().collect();
+            extensions.sort_by(|a, b| (a.code(), a.data()).cmp(&(b.code(), b.data())));
+            extensions
         }
+        sorted(&self.extensions) == sorted(&other.extensions)
+    }
+
+    /// Compute a SHA-256 digest of the object's header and volume, for use as
+    /// a content identifier.
+    ///
+    /// The digest does not depend on how the object was stored: the header
+    /// and voxel data are hashed in the native byte order, and as if written
+    /// to a single ".nii" file (with `vox_offset` set to 352 and the "n+1"
+    /// magic code), regardless of the original file layout, byte order and
+    /// compression. Since the byte order is the one of the machine, digests
+    /// are only comparable between machines of the same endianness.
+    /// The extensions are not included.
+    #[cfg(feature = "sha2")]
+    pub fn content_hash(&self) -> [u8; 32] {
+        let header = NiftiHeader {
+            vox_offset: 352.,
+            magic: *MAGIC_CODE_NIP1,
+            endianness: Endianness::native(),
+            ..self.header.clone()
+        };
+        let mut hasher = Sha256::new();
+        write_header(ByteOrdered::native(&mut hasher), &header)
+            .expect("writing to a hasher should not fail");
+        hasher.update(adapt_bytes(
+            self.volume.raw_data(),
+            self.volume.data_type(),
+            self.volume.endianness(),
+        ));
+        hasher.finalize().into()
+    }
+
+    /// Reorder the volume's first three axes so that it is stored in the
+    /// closest canonical (RAS+) orientation, and update the header to match.
+    ///
+    /// Each voxel axis is assigned to the world axis it is most aligned with,
+    /// as given by the best available transformation (see
+    /// [`NiftiHeader::affine`]), and is reversed if it points towards the
+    /// negative direction. The dimensions, voxel sizes, `dim_info` and the
+    /// 'sform' and 'qform' transformations (when their codes are set) are
+    /// updated accordingly, so that every voxel keeps its world coordinates.
+    /// If neither transformation is set, the resulting affine is stored with
+    /// [`NiftiHeader::set_affine`].
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if the volume has less
+    ///   than three dimensions.
+    /// - `NiftiError::InvalidCode` if the header contains an invalid 'sform'
+    ///   or 'qform' code.
+    /// - `NiftiError::InvalidVoxelSize` if a spacing (`pixdim[1..4]`) is
+    ///   negative, and `NiftiError::InvalidQfac` if `pixdim[0]` is neither 1
+    ///   nor -1, when the 'qform' code is set.
+    ///
+    /// [`NiftiHeader::affine`]: ../header/struct.NiftiHeader.html#method.affine
+    /// [`NiftiHeader::set_affine`]: ../header/struct.NiftiHeader.html#method.set_affine
+    #[cfg(feature = "nalgebra_affine")]
+    pub fn apply_affine_reorient(&mut self) -> Result<()> {
+        let header = &mut self.header;
+        let (sform, qform) = header.affines::<f64>()?;
+        let affine = sform.or(qform).unwrap_or_else(|| header.affine());
+        let sform = sform
+            .map(|sform| header.sform().map(|code| (code, sform)))
+            .transpose()?;
+        let qform = qform
+            .map(|qform| header.qform().map(|code| (code, qform)))
+            .transpose()?;
+        let (rotation, _) = affine_and_translation(&affine);
+
+        // greedily pair voxel axes with world axes, most aligned first
+        let mut pairs: Vec<(f64, usize, usize)> = (0..3)
+            .flat_map(|v| (0..3).map(move |w| (v, w)))
+            .map(|(v, w)| (rotation[(w, v)].abs(), v, w))
+            .collect();
+        pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        let mut world_of = [None; 3];
+        let mut perm = [None; 3];
+        for (_, v, w) in pairs {
+            if world_of[v].is_none() && perm[w].is_none() {
+                world_of[v] = Some(w);
+                perm[w] = Some(v);
+            }
+        }
+        let world_of = world_of.map(Option::unwrap);
+        let perm = perm.map(Option::unwrap);
+        let flip = [0, 1, 2].map(|w| rotation[(w, perm[w])] < 0.);
+
+        self.volume = self.volume.reorient_spatial(perm, flip)?;
+
+        // maps new voxel coordinates to old voxel coordinates
+        let mut transform = Matrix4::<f64>::identity();
+        for w in 0..3 {
+            let v = perm[w];
+            transform[(v, v)] = 0.;
+            if flip[w] {
+                transform[(v, w)] = -1.;
+                transform[(v, 3)] = f64::from(header.dim[v + 1]) - 1.;
+            } else {
+                transform[(v, w)] = 1.;
+            }
+        }
+        let old_dim = header.dim;
+        let old_pixdim = header.pixdim;
+        for w in 0..3 {
+            header.dim[w + 1] = old_dim[perm[w] + 1];
+            header.pixdim[w + 1] = old_pixdim[perm[w] + 1];
+        }
+        // `dim_info` holds three 2-bit axis numbers (1-based, 0 if unknown)
+        let mut dim_info = 0;
+        for shift in [0, 2, 4] {
+            let axis = (header.dim_info >> shift) & 0b11;
+            let axis = if axis == 0 {
+                0
+            } else {
+                world_of[usize::from(axis) - 1] as u8 + 1
+            };
+            dim_info |= axis << shift;
+        }
+        header.dim_info = dim_info;
+
+        match (sform, qform) {
+            (None, None) => header.set_affine(&(affine * transform)),
+            (sform, qform) => {
+                if let Some((code, sform)) = sform {
+                    header.set_sform(&(sform * transform), code);
+                }
+                if let Some((code, qform)) = qform {
+                    header.set_qform(&(qform * transform), code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the block of `shape` voxels starting at voxel `starts` into a
+    /// new object. The volume keeps its data type, scaling and byte order
+    /// (see [`InMemNiftiVolume::crop_shape`]), and the header's dimensions are
+    /// updated. The translations of the 'sform' and 'qform' transformations
+    /// (when their codes are set) are moved to the new first voxel, so that
+    /// every voxel keeps its world coordinates. The extensions are retained.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if `starts` or `shape`
+    ///   do not have one element per axis of the volume.
+    /// - `NiftiError::OutOfBounds` if the block goes beyond the volume's
+    ///   dimensions, with the coordinates of its last voxel.
+    /// - `NiftiError::AxisOutOfBounds` if the block is empty along an axis.
+    /// - `NiftiError::InvalidCode` if the header contains an invalid 'sform'
+    ///   or 'qform' code.
+    /// - `NiftiError::InvalidVoxelSize` if a spacing (`pixdim[1..4]`) is
+    ///   negative, and `NiftiError::InvalidQfac` if `pixdim[0]` is neither 1
+    ///   nor -1, when the 'qform' code is set.
+    ///
+    /// [`InMemNiftiVolume::crop_shape`]: ../volume/inmem/struct.InMemNiftiVolume.html#method.crop_shape
+    #[cfg(feature = "nalgebra_affine")]
+    pub fn crop(&self, starts: &[u64], shape: &[u64]) -> Result<InMemNiftiObject> {
+        let volume = self.volume.crop_shape(starts, shape)?;
+
+        let mut header = self.header.clone();
+        let (sform, qform) = header.affines::<f64>()?;
+        let mut offset = nalgebra::Vector4::new(0., 0., 0., 1.);
+        for (o, &start) in offset.iter_mut().zip(starts).take(3) {
+            *o = start as f64;
+        }
+        if let Some(sform) = sform {
+            let origin = sform * offset;
+            header.srow_x[3] = origin[0] as f32;
+            header.srow_y[3] = origin[1] as f32;
+            header.srow_z[3] = origin[2] as f32;
+        }
+        if let Some(qform) = qform {
+            let origin = qform * offset;
+            header.quatern_x = origin[0] as f32;
+            header.quatern_y = origin[1] as f32;
+            header.quatern_z = origin[2] as f32;
+        }
+        for (d, &len) in header.dim[1..].iter_mut().zip(shape) {
+            *d = len as u16;
+        }
+
+        Ok(GenericNiftiObject {
+            header,
+            extensions: self.extensions.clone(),
+            volume,
+        })
+    }
+}
+
+impl From<InMemNiftiObject> for VolumeWithHeader {
+    fn from(obj: InMemNiftiObject) -> Self {
+        VolumeWithHeader::from_parts_unchecked(obj.header, obj.volume)
     }
 }
 
@@ -374,7 +1130,7 @@ impl StreamedNiftiObject<MaybeGzDecodedFile> {
     )]
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let reader = open_file_maybe_gz(&path)?;
-        Self::from_file_impl(path, reader, None)
+        Self::from_file_impl(path, reader, None, &mut no_progress)
     }
 
     /// Retrieve the NIfTI object and prepare the volume for streamed reading,
@@ -389,7 +1145,7 @@ impl StreamedNiftiObject<MaybeGzDecodedFile> {
     )]
     pub fn from_file_rank<P: AsRef<Path>>(path: P, slice_rank: u16) -> Result<Self> {
         let reader = open_file_maybe_gz(&path)?;
-        Self::from_file_impl(path, reader, Some(slice_rank))
+        Self::from_file_impl(path, reader, Some(slice_rank), &mut no_progress)
     }
 
     /// Retrieve a NIfTI object as separate header and volume files, for
@@ -420,7 +1176,7 @@ impl StreamedNiftiObject<MaybeGzDecodedFile> {
         Q: AsRef<Path>,
     {
         let reader = open_file_maybe_gz(hdr_path)?;
-        Self::from_file_pair_impl(reader, vol_path, Default::default())
+        Self::from_file_pair_impl(reader, vol_path, Default::default(), &mut no_progress)
     }
 
     /// Retrieve a NIfTI object as separate header and volume files, for
@@ -437,7 +1193,7 @@ impl StreamedNiftiObject<MaybeGzDecodedFile> {
         Q: AsRef<Path>,
     {
         let reader = open_file_maybe_gz(hdr_path)?;
-        Self::from_file_pair_impl(reader, vol_path, Some(slice_rank))
+        Self::from_file_pair_impl(reader, vol_path, Some(slice_rank), &mut no_progress)
     }
 }
 
@@ -445,10 +1201,13 @@ impl<V> GenericNiftiObject<V> {
     /// Construct a NIfTI object from a data reader, first by fetching the
     /// header, the extensions, and then the volume.
     ///
+    /// If the source ends right after the header, without the 4-byte
+    /// extender, the object is assumed to have no extensions.
+    ///
     /// # Errors
     ///
     /// - `NiftiError::NoVolumeData` if the source only contains (or claims to contain)
-    /// a header.
+    ///   a header.
     pub fn from_reader<R>(mut source: R) -> Result<Self>
     where
         R: Read,
@@ -458,13 +1217,15 @@ impl<V> GenericNiftiObject<V> {
         if &header.magic == MAGIC_CODE_NI1 {
             return Err(NiftiError::NoVolumeData);
         }
-        let extender = Extender::from_reader(&mut source)?;
+        // a stream ending right after the header has no extensions
+        let extender = Extender::from_reader_optional(&mut source)?.unwrap_or_default();
 
         let (volume, extensions) = GenericNiftiObject::from_reader_with_extensions(
             source,
             &header,
             extender,
             Default::default(),
+            &mut no_progress,
         )?;
 
         Ok(GenericNiftiObject {
@@ -474,6 +1235,45 @@ impl<V> GenericNiftiObject<V> {
         })
     }
 
+    /// Construct a NIfTI object from separate header and volume readers, as
+    /// in a file pair (".hdr" and ".img") held in memory.
+    ///
+    /// The header is read from `hdr`, followed by the optional extender and
+    /// the extensions, which span the rest of `hdr`. The volume is then read
+    /// from `img`, after skipping the first `vox_offset` bytes.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidExtensionSize` if an extension claims to be
+    ///   smaller than its own 8-byte prelude.
+    pub fn from_hdr_img_readers<H, I>(mut hdr: H, mut img: I) -> Result<Self>
+    where
+        H: Read,
+        I: Read,
+        V: FromSource<I>,
+    {
+        let header = NiftiHeader::from_reader(&mut hdr)?;
+        // a stream ending right after the header has no extensions
+        let extender = Extender::from_reader_optional(&mut hdr)?.unwrap_or_default();
+        let mut ext_data = Vec::new();
+        let _ = hdr.read_to_end(&mut ext_data)?;
+        let extensions = ExtensionSequence::from_reader(
+            extender,
+            ByteOrdered::runtime(&ext_data[..], header.endianness),
+            ext_data.len(),
+        )?;
+
+        let vox_offset = header.vox_offset as u64;
+        let _ = io::copy(&mut (&mut img).take(vox_offset), &mut io::sink())?;
+        let volume = V::from_reader(img, &header, Default::default())?;
+
+        Ok(GenericNiftiObject {
+            header,
+            extensions,
+            volume,
+        })
+    }
+
     /// Read a NIFTI volume, and extensions, from a data reader. The header,
     /// extender code and expected byte order of the volume's data must be
     /// known in advance.
@@ -482,6 +1282,7 @@ impl<V> GenericNiftiObject<V> {
         header: &NiftiHeader,
         extender: Extender,
         options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
     ) -> Result<(V, ExtensionSequence)>
     where
         R: Read,
@@ -489,7 +1290,7 @@ impl<V> GenericNiftiObject<V> {
     {
         // fetch extensions
         let len = header.vox_offset as usize;
-        let len = if len < 352 { 0 } else { len - 352 };
+        let len = len.saturating_sub(352);
 
         let ext = {
             let source = ByteOrdered::runtime(&mut source, header.endianness);
@@ -497,13 +1298,17 @@ impl<V> GenericNiftiObject<V> {
         };
 
         // fetch volume (rest of file)
-        Ok((V::from_reader(source, header, options)?, ext))
+        Ok((
+            V::from_reader_with_progress(source, header, options, progress)?,
+            ext,
+        ))
     }
 
     fn from_file_impl<P, R>(
         path: P,
         mut stream: R,
         options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
     ) -> Result<Self>
     where
         P: AsRef<Path>,
@@ -512,6 +1317,24 @@ impl<V> GenericNiftiObject<V> {
         V: FromSource<MaybeGzDecodedFile>,
     {
         let header = NiftiHeader::from_reader(&mut stream)?;
+        Self::from_file_impl_with_header(path, header, stream, options, progress)
+    }
+
+    /// Read a NIFTI object from a stream positioned right after the given
+    /// header. See `from_file_impl`.
+    fn from_file_impl_with_header<P, R>(
+        path: P,
+        header: NiftiHeader,
+        mut stream: R,
+        options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        R: Read,
+        V: FromSource<R>,
+        V: FromSource<MaybeGzDecodedFile>,
+    {
         let (volume, ext) = if &header.magic == MAGIC_CODE_NI1 {
             // extensions and volume are in another file
 
@@ -522,38 +1345,50 @@ impl<V> GenericNiftiObject<V> {
             let img_path = path.as_ref().to_path_buf();
             let mut img_path_gz = into_img_file_gz(img_path);
 
-            Self::from_file_with_extensions(&img_path_gz, &header, extender, options.clone())
-                .or_else(|e| {
-                    match e {
-                        NiftiError::Io(ref io_e) if io_e.kind() == io::ErrorKind::NotFound => {
-                            // try .img file instead (remove .gz extension)
-                            let has_ext = img_path_gz.set_extension("");
-                            debug_assert!(has_ext);
-                            Self::from_file_with_extensions(img_path_gz, &header, extender, options)
-                        }
-                        e => Err(e),
-                    }
-                })
-                .map_err(|e| {
-                    if let NiftiError::Io(io_e) = e {
-                        NiftiError::MissingVolumeFile(io_e)
-                    } else {
-                        e
+            Self::from_file_with_extensions(
+                &img_path_gz,
+                &header,
+                extender,
+                options.clone(),
+                progress,
+            )
+            .or_else(|e| {
+                match e {
+                    NiftiError::Io(ref io_e) if io_e.kind() == io::ErrorKind::NotFound => {
+                        // try .img file instead (remove .gz extension)
+                        let has_ext = img_path_gz.set_extension("");
+                        debug_assert!(has_ext);
+                        Self::from_file_with_extensions(
+                            img_path_gz,
+                            &header,
+                            extender,
+                            options,
+                            progress,
+                        )
                     }
-                })?
+                    e => Err(e),
+                }
+            })
+            .map_err(|e| {
+                if let NiftiError::Io(io_e) = e {
+                    NiftiError::MissingVolumeFile(io_e)
+                } else {
+                    e
+                }
+            })?
         } else {
             // extensions and volume are in the same source
 
             let extender = Extender::from_reader(&mut stream)?;
             let len = header.vox_offset as usize;
-            let len = if len < 352 { 0 } else { len - 352 };
+            let len = len.saturating_sub(352);
 
             let ext = {
                 let stream = ByteOrdered::runtime(&mut stream, header.endianness);
                 ExtensionSequence::from_reader(extender, stream, len)?
             };
 
-            let volume = FromSource::from_reader(stream, &header, options)?;
+            let volume = FromSource::from_reader_with_progress(stream, &header, options, progress)?;
 
             (volume, ext)
         };
@@ -569,6 +1404,7 @@ impl<V> GenericNiftiObject<V> {
         mut hdr_stream: S,
         vol_path: Q,
         options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
     ) -> Result<Self>
     where
         S: Read,
@@ -576,9 +1412,26 @@ impl<V> GenericNiftiObject<V> {
         V: FromSource<MaybeGzDecodedFile>,
     {
         let header = NiftiHeader::from_reader(&mut hdr_stream)?;
+        Self::from_file_pair_impl_with_header(header, hdr_stream, vol_path, options, progress)
+    }
+
+    /// Read a NIFTI object from a header stream positioned right after the
+    /// given header, and a separate volume file. See `from_file_pair_impl`.
+    fn from_file_pair_impl_with_header<S, Q>(
+        header: NiftiHeader,
+        hdr_stream: S,
+        vol_path: Q,
+        options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Self>
+    where
+        S: Read,
+        Q: AsRef<Path>,
+        V: FromSource<MaybeGzDecodedFile>,
+    {
         let extender = Extender::from_reader_optional(hdr_stream)?.unwrap_or_default();
         let (volume, extensions) =
-            Self::from_file_with_extensions(vol_path, &header, extender, options)?;
+            Self::from_file_with_extensions(vol_path, &header, extender, options, progress)?;
 
         Ok(GenericNiftiObject {
             header,
@@ -595,12 +1448,46 @@ impl<V> GenericNiftiObject<V> {
         header: &NiftiHeader,
         extender: Extender,
         options: <V as FromSourceOptions>::Options,
+        progress: &mut dyn FnMut(usize, usize),
     ) -> Result<(V, ExtensionSequence)>
     where
         P: AsRef<Path>,
         V: FromSource<MaybeGzDecodedFile>,
     {
         let reader = open_file_maybe_gz(path)?;
-        Self::from_reader_with_extensions(reader, header, extender, options)
+        Self::from_reader_with_extensions(reader, header, extender, options, progress)
+    }
+}
+
+/// Progress callback which does nothing.
+fn no_progress(_read: usize, _total: usize) {}
+
+/// Check that `vox_offset` is a non-negative integer.
+fn check_vox_offset(header: &NiftiHeader) -> Result<()> {
+    let vox_offset = header.vox_offset;
+    if vox_offset < 0. || vox_offset.fract() != 0. {
+        return Err(NiftiError::InvalidVoxOffset(vox_offset));
+    }
+    Ok(())
+}
+
+/// Lower `dim[0]` to the number of leading positive dimensions, if it claims
+/// more than these (or more than 7).
+fn clamp_trailing_dims(header: &mut NiftiHeader) {
+    let max_rank = usize::from(header.dim[0]).min(7);
+    let rank = header.dim[1..=max_rank]
+        .iter()
+        .position(|&d| d == 0)
+        .unwrap_or(max_rank);
+    if rank > 0 {
+        header.dim[0] = rank as u16;
+    }
+}
+
+/// Skip the next `n` bytes of a file, seeking if it is not compressed.
+fn skip_bytes(stream: &mut MaybeGzDecodedFile, n: u64) -> io::Result<()> {
+    match stream {
+        Either::Left(file) => file.seek_relative(i64::try_from(n).unwrap_or(i64::MAX)),
+        Either::Right(gz) => io::copy(&mut gz.take(n), &mut io::sink()).map(|_| ()),
     }
 }
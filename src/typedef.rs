@@ -8,6 +8,8 @@ use crate::error::{NiftiError, Result};
 use crate::volume::element::{DataElement, NiftiDataRescaler};
 use byteordered::{Endian, Endianness};
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
 use std::io::Read;
 use std::ops::{Add, Mul};
 
@@ -80,6 +82,32 @@ impl NiftiType {
             Complex256 => 32,
         }
     }
+
+    /// Retrieve the data type of each component of a composite data type:
+    /// the real and imaginary parts of complex numbers, or the channels of
+    /// RGB(A) colors. Returns `None` for scalar data types.
+    pub fn component_type(self) -> Option<NiftiType> {
+        use NiftiType::*;
+        match self {
+            Complex64 => Some(Float32),
+            Complex128 => Some(Float64),
+            Complex256 => Some(Float128),
+            Rgb24 | Rgba32 => Some(Uint8),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the number of components of a value of this data type: 2 for
+    /// complex numbers, 3 for RGB, 4 for RGBA, and 1 for scalar data types.
+    pub fn num_components(self) -> usize {
+        use NiftiType::*;
+        match self {
+            Complex64 | Complex128 | Complex256 => 2,
+            Rgb24 => 3,
+            Rgba32 => 4,
+            _ => 1,
+        }
+    }
 }
 
 impl NiftiType {
@@ -184,6 +212,87 @@ impl NiftiType {
             _ => Err(NiftiError::UnsupportedDataType(self)),
         }
     }
+
+    /// Read a contiguous run of primitive voxel values from a byte slice,
+    /// applying the given scaling slope and intercept to each of them.
+    /// This is how the voxel values of an in-memory volume are decoded in
+    /// bulk, as in `InMemNiftiVolume::iter_f64`.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncompatibleLength` if the length of `bytes` is not a
+    ///   multiple of the size of this data type.
+    /// - `NiftiError::UnsupportedDataType` if values of this data type cannot
+    ///   be read as primitive values.
+    pub fn read_values<T>(
+        self,
+        bytes: &[u8],
+        endianness: Endianness,
+        slope: f32,
+        inter: f32,
+    ) -> Result<Vec<T>>
+    where
+        T: Mul<Output = T>,
+        T: Add<Output = T>,
+        T: DataElement,
+        T: NiftiDataRescaler<T>,
+    {
+        let size = self.size_of();
+        if !bytes.len().is_multiple_of(size) {
+            return Err(NiftiError::IncompatibleLength(
+                bytes.len(),
+                bytes.len() / size * size,
+            ));
+        }
+        bytes
+            .chunks_exact(size)
+            .map(|chunk| self.read_primitive_value(chunk, endianness, slope, inter))
+            .collect()
+    }
+
+    /// Read a primitive voxel value from a source as an `f64`, applying the
+    /// given scaling slope and intercept.
+    ///
+    /// Unlike `read_primitive_value`, the value and the scaling are always
+    /// computed in double precision, so 64-bit values (up to the 53 bits of
+    /// precision available in `f64`) are read without going through a
+    /// narrower type.
+    pub fn read_f64_value<S>(
+        self,
+        mut source: S,
+        endianness: Endianness,
+        slope: f32,
+        inter: f32,
+    ) -> Result<f64>
+    where
+        S: Read,
+    {
+        let raw = match self {
+            NiftiType::Uint8 => f64::from(u8::from_raw(&mut source, endianness)?),
+            NiftiType::Int8 => f64::from(i8::from_raw(&mut source, endianness)?),
+            NiftiType::Uint16 => f64::from(endianness.read_u16(&mut source)?),
+            NiftiType::Int16 => f64::from(endianness.read_i16(&mut source)?),
+            NiftiType::Uint32 => f64::from(endianness.read_u32(&mut source)?),
+            NiftiType::Int32 => f64::from(endianness.read_i32(&mut source)?),
+            NiftiType::Uint64 => endianness.read_u64(&mut source)? as f64,
+            NiftiType::Int64 => endianness.read_i64(&mut source)? as f64,
+            NiftiType::Float32 => f64::from(endianness.read_f32(&mut source)?),
+            NiftiType::Float64 => endianness.read_f64(&mut source)?,
+            _ => return Err(NiftiError::UnsupportedDataType(self)),
+        };
+        Ok(f64::nifti_rescale(raw, slope, inter))
+    }
+}
+
+impl TryFrom<i16> for NiftiType {
+    type Error = NiftiError;
+
+    /// Convert a `datatype` code to a data type.
+    ///
+    /// Fails with `NiftiError::InvalidCode` if the code is unknown.
+    fn try_from(code: i16) -> Result<Self> {
+        FromPrimitive::from_i16(code).ok_or(NiftiError::InvalidCode("datatype", code))
+    }
 }
 
 /// An enum type which represents a unit type.
@@ -215,6 +324,30 @@ pub enum Unit {
     Rads = 48,
 }
 
+impl Unit {
+    /// Retrieve the factor for converting a length in this unit to
+    /// millimeters, or `None` if this is not a spatial unit.
+    pub fn spatial_scale_to_mm(&self) -> Option<f64> {
+        match self {
+            Unit::Meter => Some(1000.),
+            Unit::Mm => Some(1.),
+            Unit::Micron => Some(0.001),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the factor for converting a duration in this unit to
+    /// seconds, or `None` if this is not a time unit.
+    pub fn time_scale_to_sec(&self) -> Option<f64> {
+        match self {
+            Unit::Sec => Some(1.),
+            Unit::Msec => Some(0.001),
+            Unit::Usec => Some(0.000_001),
+            _ => None,
+        }
+    }
+}
+
 /// An enum type for representing a NIFTI intent code.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, FromPrimitive)]
 #[repr(u16)]
@@ -424,10 +557,76 @@ pub enum Intent {
 }
 
 impl Intent {
+    /// Retrieve the intent with the given code, or `None` if the code is not
+    /// a known intent code.
+    pub fn from_code(code: i32) -> Option<Intent> {
+        FromPrimitive::from_i32(code)
+    }
+
     /// Check whether this intent code is used for statistics.
     pub fn is_statcode(self) -> bool {
         self as i16 >= 2 && self as i16 <= 24
     }
+
+    /// Retrieve the canonical name of this intent, as defined by the
+    /// `NIFTI_INTENT_*` constants of the NIfTI-1 specification.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Intent::None => "NIFTI_INTENT_NONE",
+            Intent::Correl => "NIFTI_INTENT_CORREL",
+            Intent::Ttest => "NIFTI_INTENT_TTEST",
+            Intent::Ftest => "NIFTI_INTENT_FTEST",
+            Intent::Zscore => "NIFTI_INTENT_ZSCORE",
+            Intent::Chisq => "NIFTI_INTENT_CHISQ",
+            Intent::Beta => "NIFTI_INTENT_BETA",
+            Intent::Binom => "NIFTI_INTENT_BINOM",
+            Intent::Gamma => "NIFTI_INTENT_GAMMA",
+            Intent::Poisson => "NIFTI_INTENT_POISSON",
+            Intent::Normal => "NIFTI_INTENT_NORMAL",
+            Intent::FtestNonc => "NIFTI_INTENT_FTEST_NONC",
+            Intent::ChisqNonc => "NIFTI_INTENT_CHISQ_NONC",
+            Intent::Logistic => "NIFTI_INTENT_LOGISTIC",
+            Intent::Laplace => "NIFTI_INTENT_LAPLACE",
+            Intent::Uniform => "NIFTI_INTENT_UNIFORM",
+            Intent::TtestNonc => "NIFTI_INTENT_TTEST_NONC",
+            Intent::Weibull => "NIFTI_INTENT_WEIBULL",
+            Intent::Chi => "NIFTI_INTENT_CHI",
+            Intent::Invgauss => "NIFTI_INTENT_INVGAUSS",
+            Intent::Extval => "NIFTI_INTENT_EXTVAL",
+            Intent::Pval => "NIFTI_INTENT_PVAL",
+            Intent::Logpval => "NIFTI_INTENT_LOGPVAL",
+            Intent::Log10pval => "NIFTI_INTENT_LOG10PVAL",
+            Intent::Estimate => "NIFTI_INTENT_ESTIMATE",
+            Intent::Label => "NIFTI_INTENT_LABEL",
+            Intent::Neuroname => "NIFTI_INTENT_NEURONAME",
+            Intent::Genmatrix => "NIFTI_INTENT_GENMATRIX",
+            Intent::Symmatrix => "NIFTI_INTENT_SYMMATRIX",
+            Intent::Dispvect => "NIFTI_INTENT_DISPVECT",
+            Intent::Vector => "NIFTI_INTENT_VECTOR",
+            Intent::Pointset => "NIFTI_INTENT_POINTSET",
+            Intent::Triangle => "NIFTI_INTENT_TRIANGLE",
+            Intent::Quaternion => "NIFTI_INTENT_QUATERNION",
+            Intent::Dimless => "NIFTI_INTENT_DIMLESS",
+            Intent::TimeSeries => "NIFTI_INTENT_TIME_SERIES",
+            Intent::NodeIndex => "NIFTI_INTENT_NODE_INDEX",
+            Intent::RgbVector => "NIFTI_INTENT_RGB_VECTOR",
+            Intent::RgbaVector => "NIFTI_INTENT_RGBA_VECTOR",
+            Intent::Shape => "NIFTI_INTENT_SHAPE",
+            Intent::FslFnirtDisplacementField => "NIFTI_INTENT_FSL_FNIRT_DISPLACEMENT_FIELD",
+            Intent::FslCubicSplineCoefficients => "NIFTI_INTENT_FSL_CUBIC_SPLINE_COEFFICIENTS",
+            Intent::FslDctCoefficients => "NIFTI_INTENT_FSL_DCT_COEFFICIENTS",
+            Intent::FslQuadraticSplineCoefficients => {
+                "NIFTI_INTENT_FSL_QUADRATIC_SPLINE_COEFFICIENTS"
+            }
+            Intent::FslTopupCubicSplineCoefficients => {
+                "NIFTI_INTENT_FSL_TOPUP_CUBIC_SPLINE_COEFFICIENTS"
+            }
+            Intent::FslTopupQuadraticSplineCoefficients => {
+                "NIFTI_INTENT_FSL_TOPUP_QUADRATIC_SPLINE_COEFFICIENTS"
+            }
+            Intent::FslTopupField => "NIFTI_INTENT_FSL_TOPUP_FIELD",
+        }
+    }
 }
 
 /// An enum type for representing a NIFTI XForm.
@@ -469,3 +668,119 @@ pub enum SliceOrder {
     /// NIFTI_SLICE_ALT_DEC2
     AltDec2 = 6,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Intent, NiftiType, Unit};
+    use crate::error::NiftiError;
+    use byteordered::Endianness;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn component_types() {
+        use NiftiType::*;
+        for (datatype, component_type, num_components) in [
+            (Complex64, Float32, 2),
+            (Complex128, Float64, 2),
+            (Complex256, Float128, 2),
+            (Rgb24, Uint8, 3),
+            (Rgba32, Uint8, 4),
+        ] {
+            assert_eq!(datatype.component_type(), Some(component_type));
+            assert_eq!(datatype.num_components(), num_components);
+            assert_eq!(
+                component_type.size_of() * num_components,
+                datatype.size_of()
+            );
+        }
+        for datatype in [
+            Uint8, Int8, Uint16, Int16, Uint32, Int32, Uint64, Int64, Float32, Float64, Float128,
+        ] {
+            assert_eq!(datatype.component_type(), None);
+            assert_eq!(datatype.num_components(), 1);
+        }
+    }
+
+    #[test]
+    fn nifti_type_try_from() {
+        assert_eq!(NiftiType::try_from(2).unwrap(), NiftiType::Uint8);
+        assert_eq!(NiftiType::try_from(16).unwrap(), NiftiType::Float32);
+        assert_eq!(NiftiType::try_from(2304).unwrap(), NiftiType::Rgba32);
+        assert!(matches!(
+            NiftiType::try_from(0),
+            Err(NiftiError::InvalidCode("datatype", 0))
+        ));
+        assert!(matches!(
+            NiftiType::try_from(-4),
+            Err(NiftiError::InvalidCode("datatype", -4))
+        ));
+    }
+
+    #[test]
+    fn unit_scales() {
+        assert_eq!(Unit::Unknown.spatial_scale_to_mm(), None);
+        assert_eq!(Unit::Meter.spatial_scale_to_mm(), Some(1000.));
+        assert_eq!(Unit::Mm.spatial_scale_to_mm(), Some(1.));
+        assert_eq!(Unit::Micron.spatial_scale_to_mm(), Some(0.001));
+        assert_eq!(Unit::Sec.spatial_scale_to_mm(), None);
+        assert_eq!(Unit::Hz.spatial_scale_to_mm(), None);
+
+        assert_eq!(Unit::Unknown.time_scale_to_sec(), None);
+        assert_eq!(Unit::Meter.time_scale_to_sec(), None);
+        assert_eq!(Unit::Sec.time_scale_to_sec(), Some(1.));
+        assert_eq!(Unit::Msec.time_scale_to_sec(), Some(0.001));
+        assert_eq!(Unit::Usec.time_scale_to_sec(), Some(0.000_001));
+        assert_eq!(Unit::Hz.time_scale_to_sec(), None);
+        assert_eq!(Unit::Ppm.time_scale_to_sec(), None);
+        assert_eq!(Unit::Rads.time_scale_to_sec(), None);
+    }
+
+    #[test]
+    fn read_values_int16() {
+        let values: [i16; 5] = [0, 1, -1, i16::MAX, i16::MIN];
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+
+        let got: Vec<i16> = NiftiType::Int16
+            .read_values(&bytes, Endianness::Big, 0., 0.)
+            .unwrap();
+        assert_eq!(got, values);
+
+        let got: Vec<f32> = NiftiType::Int16
+            .read_values(&bytes, Endianness::Big, 2., 1.)
+            .unwrap();
+        assert_eq!(got, [1., 3., -1., 65535., -65535.]);
+
+        assert!(matches!(
+            NiftiType::Int16.read_values::<i16>(&bytes[1..], Endianness::Big, 0., 0.),
+            Err(NiftiError::IncompatibleLength(9, 8))
+        ));
+        assert!(matches!(
+            NiftiType::Rgb24.read_values::<u8>(&bytes[..9], Endianness::Big, 0., 0.),
+            Err(NiftiError::UnsupportedDataType(NiftiType::Rgb24))
+        ));
+    }
+
+    #[test]
+    fn intent_codes_and_names() {
+        assert_eq!(Intent::from_code(0), Some(Intent::None));
+        assert_eq!(Intent::from_code(3), Some(Intent::Ttest));
+        assert_eq!(Intent::from_code(1002), Some(Intent::Label));
+        assert_eq!(Intent::from_code(2001), Some(Intent::TimeSeries));
+        assert_eq!(Intent::from_code(2005), Some(Intent::Shape));
+        assert_eq!(Intent::from_code(2018), Some(Intent::FslTopupField));
+        assert_eq!(Intent::from_code(1), None);
+        assert_eq!(Intent::from_code(2010), None);
+        assert_eq!(Intent::from_code(-1), None);
+        assert_eq!(Intent::from_code(70000), None);
+
+        assert_eq!(Intent::None.name(), "NIFTI_INTENT_NONE");
+        assert_eq!(Intent::FtestNonc.name(), "NIFTI_INTENT_FTEST_NONC");
+        assert_eq!(Intent::Log10pval.name(), "NIFTI_INTENT_LOG10PVAL");
+        assert_eq!(Intent::TimeSeries.name(), "NIFTI_INTENT_TIME_SERIES");
+        assert_eq!(Intent::RgbaVector.name(), "NIFTI_INTENT_RGBA_VECTOR");
+        assert_eq!(
+            Intent::FslDctCoefficients.name(),
+            "NIFTI_INTENT_FSL_DCT_COEFFICIENTS"
+        );
+    }
+}
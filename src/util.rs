@@ -12,6 +12,7 @@ use std::io::{BufReader, Read, Result as IoResult, Seek};
 use std::mem;
 use std::path::{Path, PathBuf};
 /// A trait that is both Read and Seek.
+#[allow(dead_code)]
 pub trait ReadSeek: Read + Seek {}
 impl<T: Read + Seek> ReadSeek for T {}
 
@@ -46,25 +47,34 @@ where
     }
 }
 
-/// Adapt a sequence of bytes for reading contiguous values of type `T`,
-/// by swapping bytes if the given endianness is not native. If no
-/// swapping is needed, the same byte slice is returned.
-#[cfg_attr(not(feature = "ndarray_volumes"), allow(dead_code))]
-pub fn adapt_bytes<T, E>(bytes: &[u8], e: E) -> Cow<[u8]>
+/// Adapt a sequence of bytes for reading or writing contiguous values of the
+/// given data type, by swapping the bytes of each value component if the given
+/// endianness is not native. If no swapping is needed, the same byte slice is
+/// returned.
+pub fn adapt_bytes<E>(bytes: &[u8], datatype: NiftiType, e: E) -> Cow<'_, [u8]>
 where
     E: Endian,
 {
-    let nb_bytes = mem::size_of::<T>();
+    let nb_bytes = component_size(datatype);
     if !e.is_native() && nb_bytes > 1 {
         // Swap endianness by block of nb_bytes
-        let mut a = bytes.to_vec();
-        adapt_bytes_inline::<T, E>(&mut a, e);
-        a.into()
+        bytes
+            .chunks(nb_bytes)
+            .flat_map(|c| c.iter().rev().copied())
+            .collect::<Vec<_>>()
+            .into()
     } else {
         bytes.into()
     }
 }
 
+/// The size in bytes of each component of a value of the given data type,
+/// which is the unit of byte swapping. Complex numbers have two components,
+/// and RGB(A) colors are made of single bytes.
+pub fn component_size(datatype: NiftiType) -> usize {
+    datatype.size_of() / datatype.num_components()
+}
+
 /// Validate a raw volume dimensions array, returning a slice of the concrete
 /// dimensions.
 ///
@@ -76,7 +86,8 @@ pub fn validate_dim(raw_dim: &[u16; 8]) -> Result<&[u16]> {
     let ndim = validate_dimensionality(raw_dim)?;
     let o = &raw_dim[1..=ndim];
     if let Some(i) = o.iter().position(|&x| x == 0) {
-        return Err(NiftiError::InconsistentDim(i as u8, raw_dim[i]));
+        // `o` starts at `raw_dim[1]`
+        return Err(NiftiError::InconsistentDim(i as u8 + 1, raw_dim[i + 1]));
     }
     Ok(o)
 }
@@ -105,7 +116,7 @@ pub fn nb_values_for_dims(dim: &[u16]) -> Option<usize> {
     dim.iter()
         .cloned()
         .map(usize::from)
-        .fold(Some(1), |acc, v| acc.and_then(|x| x.checked_mul(v)))
+        .try_fold(1usize, |acc, v| acc.checked_mul(v))
 }
 
 pub fn nb_bytes_for_dim_datatype(dim: &[u16], datatype: NiftiType) -> Option<usize> {
@@ -113,7 +124,6 @@ pub fn nb_bytes_for_dim_datatype(dim: &[u16], datatype: NiftiType) -> Option<usi
     resolution.and_then(|r| r.checked_mul(datatype.size_of()))
 }
 
-#[cfg(feature = "ndarray_volumes")]
 pub fn is_hdr_file<P>(path: P) -> bool
 where
     P: AsRef<Path>,
@@ -151,6 +161,16 @@ pub fn into_img_file_gz(mut path: PathBuf) -> PathBuf {
     path.with_extension("img.gz")
 }
 
+/// Convert a file path to a NIfTI file (.nii, .hdr, or either with .gz) to
+/// the path of its BIDS JSON sidecar (.json).
+#[cfg(feature = "json")]
+pub fn into_sidecar_file(mut path: PathBuf) -> PathBuf {
+    if is_gz_file(&path) {
+        let _ = path.set_extension("");
+    }
+    path.with_extension("json")
+}
+
 /// A reader for a GZip encoded file.
 pub type GzDecodedFile = GzDecoder<BufReader<File>>;
 
@@ -176,14 +196,140 @@ where
     }
 }
 
+/// Convert a single precision float to the bits of the nearest half precision
+/// float (IEEE 754 binary16), rounding ties to even. Values beyond the range
+/// of half precision floats become infinite.
+#[cfg(feature = "ndarray_volumes")]
+pub fn f32_to_f16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let mant = x & 0x7f_ffff;
+
+    if exp == 0xff {
+        // infinity or NaN (keeping NaNs quiet)
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (bits, shift) = if half_exp <= 0 {
+        // subnormal half float (or zero), with the implicit bit made explicit
+        if half_exp < -10 {
+            return sign;
+        }
+        (mant | 0x80_0000, (14 - half_exp) as u32)
+    } else {
+        (((half_exp as u32) << 23) | mant, 13)
+    };
+    let mut half = bits >> shift;
+    let rem = bits & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if rem > halfway || (rem == halfway && half & 1 == 1) {
+        // may carry into the exponent, which is still correct
+        half += 1;
+    }
+    sign | half as u16
+}
+
+/// Convert the bits of a half precision float (IEEE 754 binary16) to a
+/// single precision float. The conversion is exact.
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1. } else { 1. };
+    let exp = (bits >> 10) & 0x1f;
+    let mant = u32::from(bits & 0x3ff);
+    match exp {
+        0 => sign * mant as f32 * f32::powi(2., -24),
+        0x1f => f32::from_bits((u32::from(bits & 0x8000) << 16) | 0x7f80_0000 | (mant << 13)),
+        _ => f32::from_bits(
+            (u32::from(bits & 0x8000) << 16) | ((u32::from(exp) + 112) << 23) | (mant << 13),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    #[cfg(feature = "ndarray_volumes")]
     use super::is_hdr_file;
-    use super::{into_img_file_gz, is_gz_file, nb_bytes_for_dim_datatype};
+    #[cfg(feature = "ndarray_volumes")]
+    use super::{f16_bits_to_f32, f32_to_f16_bits};
+    use super::{into_img_file_gz, is_gz_file, nb_bytes_for_dim_datatype, validate_dim};
+    use crate::error::NiftiError;
     use crate::typedef::NiftiType;
     use std::path::PathBuf;
 
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_sidecar_file() {
+        use super::into_sidecar_file;
+
+        assert_eq!(
+            into_sidecar_file(PathBuf::from("/path/to/sub-01_T1w.nii.gz")),
+            PathBuf::from("/path/to/sub-01_T1w.json")
+        );
+        assert_eq!(
+            into_sidecar_file(PathBuf::from("sub-01_task-rest.1.nii")),
+            PathBuf::from("sub-01_task-rest.1.json")
+        );
+    }
+
+    #[cfg(feature = "ndarray_volumes")]
+    #[test]
+    fn test_f16_conversion() {
+        for (value, bits) in [
+            (0., 0x0000),
+            (-0., 0x8000),
+            (1., 0x3c00),
+            (-2., 0xc000),
+            (0.5, 0x3800),
+            (65504., 0x7bff),
+            (f32::powi(2., -14), 0x0400),
+            (f32::powi(2., -24), 0x0001),
+            (f32::INFINITY, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00),
+        ] {
+            assert_eq!(f32_to_f16_bits(value), bits, "{}", value);
+            assert_eq!(f16_bits_to_f32(bits), value);
+        }
+        // rounding
+        assert_eq!(f32_to_f16_bits(1. + f32::powi(2., -11)), 0x3c00);
+        assert_eq!(f32_to_f16_bits(1. + 3. * f32::powi(2., -11)), 0x3c02);
+        assert_eq!(f32_to_f16_bits(0.1), 0x2e66);
+        assert_eq!(f32_to_f16_bits(65520.), 0x7c00);
+        assert_eq!(f32_to_f16_bits(f32::powi(2., -25)), 0x0000);
+        assert_eq!(f32_to_f16_bits(1.5 * f32::powi(2., -25)), 0x0001);
+        assert_eq!(f32_to_f16_bits(1e-10), 0x0000);
+        assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+        // every finite half float survives a round-trip
+        for bits in (0..=u16::MAX).filter(|b| b & 0x7c00 != 0x7c00) {
+            assert_eq!(f32_to_f16_bits(f16_bits_to_f32(bits)), bits);
+        }
+    }
+
+    #[test]
+    fn test_validate_dim() {
+        assert_eq!(
+            validate_dim(&[7, 2, 3, 4, 5, 6, 7, 8]).unwrap(),
+            &[2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(
+            validate_dim(&[6, 2, 3, 4, 5, 6, 7, 0]).unwrap(),
+            &[2, 3, 4, 5, 6, 7]
+        );
+        assert!(matches!(
+            validate_dim(&[6, 2, 3, 4, 5, 6, 0, 1]),
+            Err(NiftiError::InconsistentDim(6, 0))
+        ));
+        assert!(matches!(
+            validate_dim(&[3, 0, 3, 4, 0, 0, 0, 0]),
+            Err(NiftiError::InconsistentDim(1, 0))
+        ));
+        assert!(matches!(
+            validate_dim(&[8, 2, 3, 4, 5, 6, 7, 8]),
+            Err(NiftiError::InconsistentDim(0, 8))
+        ));
+    }
+
     #[test]
     fn test_nbytes() {
         assert_eq!(
@@ -213,7 +359,6 @@ mod tests {
         assert!(!is_gz_file("não_é_gz.hdr"));
 
         let path = "/path/to/image.hdr";
-        #[cfg(feature = "ndarray_volumes")]
         assert!(is_hdr_file(path));
         assert!(!is_gz_file(path));
         assert_eq!(
@@ -222,7 +367,6 @@ mod tests {
         );
 
         let path = "/path/to/image.hdr.gz";
-        #[cfg(feature = "ndarray_volumes")]
         assert!(is_hdr_file(path));
         assert!(is_gz_file(path));
         assert_eq!(
@@ -231,7 +375,6 @@ mod tests {
         );
 
         let path = "my_ct_scan.1.hdr.gz";
-        #[cfg(feature = "ndarray_volumes")]
         assert!(is_hdr_file(path));
         assert!(is_gz_file(path));
         assert_eq!(
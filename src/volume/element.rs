@@ -73,25 +73,26 @@ impl NiftiDataRescaler<i16> for i16 {
 
 impl NiftiDataRescaler<u32> for u32 {
     fn nifti_rescale(value: u32, slope: f32, intercept: f32) -> u32 {
-        if slope == 0. {
+        if slope == 0. || (slope == 1. && intercept == 0.) {
             return value;
         }
-        (value as f32 * slope + intercept) as u32
+        (value as f64 * slope as f64 + intercept as f64) as u32
     }
 }
 
 impl NiftiDataRescaler<i32> for i32 {
     fn nifti_rescale(value: i32, slope: f32, intercept: f32) -> i32 {
-        if slope == 0. {
+        if slope == 0. || (slope == 1. && intercept == 0.) {
             return value;
         }
-        (value as f32 * slope + intercept) as i32
+        (value as f64 * slope as f64 + intercept as f64) as i32
     }
 }
 
 impl NiftiDataRescaler<u64> for u64 {
     fn nifti_rescale(value: u64, slope: f32, intercept: f32) -> u64 {
-        if slope == 0. {
+        // the identity scaling must not go through `f64`, which cannot hold all 64-bit values
+        if slope == 0. || (slope == 1. && intercept == 0.) {
             return value;
         }
         (value as f64 * slope as f64 + intercept as f64) as u64
@@ -100,7 +101,8 @@ impl NiftiDataRescaler<u64> for u64 {
 
 impl NiftiDataRescaler<i64> for i64 {
     fn nifti_rescale(value: i64, slope: f32, intercept: f32) -> i64 {
-        if slope == 0. {
+        // the identity scaling must not go through `f64`, which cannot hold all 64-bit values
+        if slope == 0. || (slope == 1. && intercept == 0.) {
             return value;
         }
         (value as f64 * slope as f64 + intercept as f64) as i64
@@ -148,6 +150,17 @@ impl NiftiDataRescaler<Complex64> for Complex64 {
     }
 }
 
+// Masks are rescaled as their underlying `Uint8` value, nonzero being true
+impl NiftiDataRescaler<bool> for bool {
+    fn nifti_rescale(value: bool, slope: f32, intercept: f32) -> bool {
+        if slope == 0. {
+            value
+        } else {
+            f32::from(u8::from(value)) * slope + intercept != 0.
+        }
+    }
+}
+
 // Nifti 1.1 specifies that RGB data must NOT be rescaled
 impl NiftiDataRescaler<RGB8> for RGB8 {
     fn nifti_rescale(value: RGB8, _slope: f32, _intercept: f32) -> RGB8 {
@@ -186,7 +199,7 @@ pub trait DataElement: 'static + Sized + Copy {
     type DataRescaler: NiftiDataRescaler<Self>;
 
     /// Read a single element from the given byte source.
-    fn from_raw<R: Read, E>(src: R, endianness: E) -> Result<Self>
+    fn from_raw<R, E>(src: R, endianness: E) -> Result<Self>
     where
         R: Read,
         E: Endian;
@@ -251,6 +264,21 @@ pub trait DataElement: 'static + Sized + Copy {
         unimplemented!()
     }
 
+    /// Convert this element to a scalar value.
+    ///
+    /// Complex numbers are converted to their magnitude, and colors to their
+    /// luminance (`0.299 * R + 0.587 * G + 0.114 * B`, as in ITU-R BT.601),
+    /// ignoring the alpha channel.
+    fn to_f64(self) -> f64 {
+        unimplemented!()
+    }
+
+    /// Convert this element to a scalar value, as in
+    /// [`to_f64`](#method.to_f64).
+    fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+
     /// Transform the given data vector into a vector of data elements.
     fn from_raw_vec<E>(vec: Vec<u8>, endianness: E) -> Result<Vec<Self>>
     where
@@ -274,9 +302,13 @@ pub trait DataElement: 'static + Sized + Copy {
         E: Endian;
 }
 
-/// Mass-implement primitive conversions from scalar types
+/// Mass-implement primitive conversions from and to scalar types
 macro_rules! fn_from_scalar {
     ($typ: ty) => {
+        fn to_f64(self) -> f64 {
+            self as f64
+        }
+
         fn from_u8(value: u8) -> Self {
             value as $typ
         }
@@ -365,6 +397,10 @@ macro_rules! fn_cplx_from_scalar {
 
 macro_rules! fn_from_complex {
     ($typ: ty) => {
+        fn to_f64(self) -> f64 {
+            f64::from(self.norm())
+        }
+
         fn from_complex32(value: Complex32) -> Self {
             Complex::<$typ>::new(value.re as $typ, value.im as $typ)
         }
@@ -746,8 +782,9 @@ impl DataElement for Complex32 {
     where
         E: Endian,
     {
-        Ok(convert_bytes_to::<[f32; 2], _>(vec, e)
-            .into_iter()
+        // swap bytes per component, not per pair of components
+        Ok(convert_bytes_to::<f32, _>(vec, e)
+            .chunks_exact(2)
             .map(|x| Complex32::new(x[0], x[1]))
             .collect())
     }
@@ -790,8 +827,9 @@ impl DataElement for Complex64 {
     where
         E: Endian,
     {
-        Ok(convert_bytes_to::<[f64; 2], _>(vec, e)
-            .into_iter()
+        // swap bytes per component, not per pair of components
+        Ok(convert_bytes_to::<f64, _>(vec, e)
+            .chunks_exact(2)
             .map(|x| Complex64::new(x[0], x[1]))
             .collect())
     }
@@ -866,6 +904,10 @@ impl DataElement for RGB8 {
 
         Ok(RGB8::new(r, g, b))
     }
+
+    fn to_f64(self) -> f64 {
+        0.299 * f64::from(self.r) + 0.587 * f64::from(self.g) + 0.114 * f64::from(self.b)
+    }
 }
 
 impl DataElement for RGBA8 {
@@ -909,4 +951,109 @@ impl DataElement for RGBA8 {
 
         Ok(RGBA8::new(r, g, b, a))
     }
+
+    fn to_f64(self) -> f64 {
+        0.299 * f64::from(self.r) + 0.587 * f64::from(self.g) + 0.114 * f64::from(self.b)
+    }
+}
+
+/// Mass-implement conversions from scalar types into a mask value, where
+/// nonzero is true
+macro_rules! fn_mask_from_scalar {
+    ($($fname: ident: $typ: ty),*) => {
+        $(
+            fn $fname(value: $typ) -> Self {
+                value != <$typ>::default()
+            }
+        )*
+    };
+}
+
+/// Binary masks, stored as `Uint8` voxels: nonzero values are read as `true`,
+/// and `true` is written as 1.
+impl DataElement for bool {
+    const DATA_TYPE: NiftiType = NiftiType::Uint8;
+    type DataRescaler = DataRescaler;
+
+    fn from_raw_vec<E>(vec: Vec<u8>, _: E) -> Result<Vec<Self>>
+    where
+        E: Endian,
+    {
+        Ok(vec.into_iter().map(|v| v != 0).collect())
+    }
+
+    fn from_raw_vec_validated<E>(
+        vec: Vec<u8>,
+        endianness: E,
+        datatype: NiftiType,
+    ) -> Result<Vec<Self>>
+    where
+        E: Endian,
+    {
+        if datatype == NiftiType::Uint8 {
+            Self::from_raw_vec(vec, endianness)
+        } else {
+            Err(NiftiError::InvalidTypeConversion(datatype, "bool"))
+        }
+    }
+
+    fn from_raw<R, E>(src: R, _: E) -> Result<Self>
+    where
+        R: Read,
+        E: Endian,
+    {
+        Ok(ByteOrdered::native(src).read_u8()? != 0)
+    }
+
+    fn_mask_from_scalar!(
+        from_u8: u8,
+        from_i8: i8,
+        from_u16: u16,
+        from_i16: i16,
+        from_u32: u32,
+        from_i32: i32,
+        from_u64: u64,
+        from_i64: i64,
+        from_f32: f32,
+        from_f64: f64,
+        from_complex32: Complex32,
+        from_complex64: Complex64
+    );
+
+    fn to_f64(self) -> f64 {
+        f64::from(u8::from(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataElement;
+    use num_complex::{Complex32, Complex64};
+    use rgb::{RGB8, RGBA8};
+
+    #[test]
+    fn test_to_f64() {
+        assert_eq!(200u8.to_f64(), 200.);
+        assert_eq!((-7i8).to_f64(), -7.);
+        assert_eq!(u16::MAX.to_f64(), 65535.);
+        assert_eq!(i32::MIN.to_f64(), -2147483648.);
+        assert_eq!((1u64 << 40).to_f64(), 1099511627776.);
+        assert_eq!(0.1f32.to_f64(), f64::from(0.1f32));
+        assert_eq!(0.1f64.to_f64(), 0.1);
+        assert_eq!(Complex32::new(3., -4.).to_f64(), 5.);
+        assert_eq!(Complex64::new(-6., 8.).to_f64(), 10.);
+        assert_eq!(RGB8::new(255, 255, 255).to_f64(), 255.);
+        assert_eq!(RGB8::new(0, 0, 0).to_f64(), 0.);
+        assert!((RGB8::new(100, 0, 0).to_f64() - 29.9).abs() < 1e-9);
+        assert_eq!(
+            RGBA8::new(10, 20, 30, 0).to_f64(),
+            RGB8::new(10, 20, 30).to_f64()
+        );
+
+        assert_eq!((-3i16).to_f32(), -3.);
+        assert_eq!(Complex64::new(3., 4.).to_f32(), 5.);
+
+        assert_eq!(true.to_f64(), 1.);
+        assert_eq!(false.to_f64(), 0.);
+    }
 }
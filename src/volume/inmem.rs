@@ -5,12 +5,16 @@ use super::util::coords_to_index;
 use crate::error::{NiftiError, Result};
 use crate::header::NiftiHeader;
 use crate::typedef::NiftiType;
-use crate::util::{nb_bytes_for_data, nb_bytes_for_dim_datatype};
+use crate::util::{
+    adapt_bytes, f16_bits_to_f32, nb_bytes_for_data, nb_bytes_for_dim_datatype, nb_values_for_dims,
+};
 use crate::volume::element::{DataElement, NiftiDataRescaler};
 use crate::volume::{FromSource, FromSourceOptions, NiftiVolume, RandomAccessNiftiVolume};
 use byteordered::Endianness;
 use flate2::bufread::GzDecoder;
 use num_traits::Num;
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::ops::{Add, Mul};
@@ -20,6 +24,12 @@ use std::path::Path;
 use super::ndarray::IntoNdArray;
 #[cfg(feature = "ndarray_volumes")]
 use ndarray::{Array, Ix, IxDyn, ShapeBuilder};
+#[cfg(feature = "ndarray_volumes")]
+use rgb::RGB8;
+#[cfg(feature = "sha2")]
+use sha2::{Digest, Sha256};
+#[cfg(all(feature = "nalgebra_affine", feature = "ndarray_volumes"))]
+use {nalgebra::Vector4, ndarray::Array2};
 
 macro_rules! fn_convert_and_cast {
     ($fname: ident, $typ: ty, $converter: expr) => {
@@ -28,6 +38,11 @@ macro_rules! fn_convert_and_cast {
         where
             O: DataElement,
         {
+            // fast path: the raw data buffer is reinterpreted in place
+            if O::DATA_TYPE == self.datatype {
+                return self.no_cast_convert_to_ndarray();
+            }
+
             let dim: Vec<_> = self.dim().iter().map(|d| *d as Ix).collect();
 
             // cast the raw data buffer to the DataElement
@@ -36,11 +51,13 @@ macro_rules! fn_convert_and_cast {
             // cast elements to the requested output type
             let mut data: Vec<O> = data.into_iter().map($converter).collect();
             // apply slope and inter before creating the final ndarray
-            <O as DataElement>::DataRescaler::nifti_rescale_many_inline(
-                &mut data,
-                self.scl_slope,
-                self.scl_inter,
-            );
+            if !is_identity_scaling(self.scl_slope, self.scl_inter) {
+                <O as DataElement>::DataRescaler::nifti_rescale_many_inline(
+                    &mut data,
+                    self.scl_slope,
+                    self.scl_inter,
+                );
+            }
 
             Ok(Array::from_shape_vec(IxDyn(&dim).f(), data).expect("Inconsistent raw data size"))
         }
@@ -71,6 +88,16 @@ impl InMemNiftiVolume {
     /// Build an InMemNiftiVolume from a header and a buffer. The buffer length and the dimensions
     /// declared in the header are expected to fit.
     pub fn from_raw_data(header: &NiftiHeader, raw_data: Vec<u8>) -> Result<Self> {
+        InMemNiftiVolume::from_raw_data_with_endianness(header, raw_data, header.endianness)
+    }
+
+    /// Build an InMemNiftiVolume from a header and a buffer, as in `from_raw_data`, but with the
+    /// data in the given byte order instead of the one declared in the header.
+    pub fn from_raw_data_with_endianness(
+        header: &NiftiHeader,
+        raw_data: Vec<u8>,
+        endianness: Endianness,
+    ) -> Result<Self> {
         let nbytes = nb_bytes_for_data(header)?;
         if nbytes != raw_data.len() {
             return Err(NiftiError::IncompatibleLength(raw_data.len(), nbytes));
@@ -83,7 +110,7 @@ impl InMemNiftiVolume {
             scl_slope: header.scl_slope,
             scl_inter: header.scl_inter,
             raw_data,
-            endianness: header.endianness,
+            endianness,
         })
     }
 
@@ -105,7 +132,7 @@ impl InMemNiftiVolume {
         if nbytes != Some(raw_data.len()) {
             return Err(NiftiError::IncompatibleLength(
                 raw_data.len(),
-                nbytes.unwrap_or(usize::max_value()),
+                nbytes.unwrap_or(usize::MAX),
             ));
         }
 
@@ -119,17 +146,89 @@ impl InMemNiftiVolume {
         })
     }
 
+    /// Create a volume of dimensions `dim` and the given data type, with every
+    /// voxel set to `value`. The value is rounded and clamped for integer
+    /// types. The volume is stored in native byte order, without scaling.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InconsistentDim` if `dim` does not hold between 1 and 7
+    ///   positive dimensions.
+    /// - `NiftiError::BadVolumeSize` if a dimension is beyond `u16::MAX` or the
+    ///   volume is too large.
+    /// - `NiftiError::ReserveVolume` if the memory for the volume cannot be
+    ///   allocated.
+    /// - `NiftiError::UnsupportedDataType` if `datatype` is not a real scalar
+    ///   type.
+    pub fn filled(dim: &[u64], datatype: NiftiType, value: f64) -> Result<InMemNiftiVolume> {
+        let dim = dim_from_u64(dim)?;
+        let value = match datatype {
+            NiftiType::Float32 | NiftiType::Float64 => value,
+            datatype => {
+                let (tmin, tmax) =
+                    integer_range(datatype).ok_or(NiftiError::UnsupportedDataType(datatype))?;
+                value.round().clamp(tmin, tmax)
+            }
+        };
+        let nb_bytes =
+            nb_bytes_for_dim_datatype(dim.as_ref(), datatype).ok_or(NiftiError::BadVolumeSize)?;
+
+        let mut value_bytes = Vec::with_capacity(datatype.size_of());
+        push_native_value(&mut value_bytes, datatype, value);
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(nb_bytes)
+            .map_err(|e| NiftiError::ReserveVolume(nb_bytes, e))?;
+        for _ in 0..dim.element_count() {
+            raw_data.extend_from_slice(&value_bytes);
+        }
+        Ok(InMemNiftiVolume {
+            dim,
+            datatype,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::native(),
+        })
+    }
+
     /// Read a NIFTI volume from a stream of data. The header and expected byte order
     /// of the volume's data must be known in advance. It it also expected that the
     /// following bytes represent the first voxels of the volume (and not part of the
     /// extensions).
     pub fn from_reader<R: Read>(source: R, header: &NiftiHeader) -> Result<Self> {
+        InMemNiftiVolume::from_reader_with_progress(source, header, |_, _| {})
+    }
+
+    /// Read a NIFTI volume from a stream of data, as in `from_reader`, calling
+    /// `progress` with the number of bytes read so far and the total number of
+    /// bytes expected after each chunk of data is read.
+    pub fn from_reader_with_progress<R, F>(
+        source: R,
+        header: &NiftiHeader,
+        mut progress: F,
+    ) -> Result<Self>
+    where
+        R: Read,
+        F: FnMut(usize, usize),
+    {
         let nb_bytes = nb_bytes_for_data(header)?;
         let mut raw_data = Vec::new();
         raw_data
             .try_reserve_exact(nb_bytes)
             .map_err(|e| NiftiError::ReserveVolume(nb_bytes, e))?;
-        let nb_bytes_written = source.take(nb_bytes as u64).read_to_end(&mut raw_data)?;
+        let mut source = source.take(nb_bytes as u64);
+        let mut nb_bytes_written = 0;
+        loop {
+            let n = (&mut source)
+                .take(PROGRESS_CHUNK_SIZE)
+                .read_to_end(&mut raw_data)?;
+            if n == 0 {
+                break;
+            }
+            nb_bytes_written += n;
+            progress(nb_bytes_written, nb_bytes);
+        }
 
         if nb_bytes_written != nb_bytes {
             return Err(NiftiError::IncompatibleLength(nb_bytes_written, nb_bytes));
@@ -163,6 +262,31 @@ impl InMemNiftiVolume {
         }
     }
 
+    /// Retrieve the dimensions of the volume in the raw form of the NIfTI
+    /// header's `dim` field, with the rank in `dim[0]`.
+    pub fn raw_dim(&self) -> [u64; 8] {
+        let mut dim = [0; 8];
+        for (d, &r) in dim.iter_mut().zip(self.dim.raw()) {
+            *d = u64::from(r);
+        }
+        dim
+    }
+
+    /// Retrieve the slope of the linear scaling applied to the raw values.
+    pub fn scl_slope(&self) -> f32 {
+        self.scl_slope
+    }
+
+    /// Retrieve the intercept of the linear scaling applied to the raw values.
+    pub fn scl_inter(&self) -> f32 {
+        self.scl_inter
+    }
+
+    /// Retrieve the byte order of the raw data.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
     /// Retrieve the raw data, consuming the volume.
     pub fn into_raw_data(self) -> Vec<u8> {
         self.raw_data
@@ -178,6 +302,42 @@ impl InMemNiftiVolume {
         &mut self.raw_data
     }
 
+    /// Retrieve the raw data in little endian byte order, which is only
+    /// copied if it had to be byte-swapped.
+    #[cfg(feature = "sha2")]
+    pub(crate) fn raw_data_le(&self) -> Cow<'_, [u8]> {
+        // `adapt_bytes` swaps the bytes if the given byte order is not native,
+        // so the data is swapped only when it is not already little endian
+        let order = if self.endianness == Endianness::Little {
+            Endianness::native()
+        } else {
+            Endianness::native().to_opposite()
+        };
+        adapt_bytes(&self.raw_data, self.datatype, order)
+    }
+
+    /// Compute the SHA-256 digest of the voxel data, in little endian byte
+    /// order, so that it does not depend on the byte order of the volume.
+    /// The scaling is not taken into account.
+    #[cfg(feature = "sha2")]
+    pub fn sha256(&self) -> [u8; 32] {
+        Sha256::digest(self.raw_data_le()).into()
+    }
+
+    /// Retrieve everything needed to upload the volume to a GPU buffer or
+    /// texture: the dimensions, the data type, and the raw data in the native
+    /// byte order. The data is only copied if it had to be byte-swapped.
+    ///
+    /// The voxels are laid out contiguously in Fortran (column major) order,
+    /// so the first dimension is the fastest varying one, as in a texture
+    /// with the extent `[dim[0], dim[1], dim[2]]`. The scaling (`scl_slope`
+    /// and `scl_inter`) is not applied.
+    pub fn as_bytes_for_gpu(&self) -> (Vec<u64>, NiftiType, Cow<'_, [u8]>) {
+        let dim = self.dim().iter().map(|&d| u64::from(d)).collect();
+        let data = adapt_bytes(&self.raw_data, self.datatype, self.endianness);
+        (dim, self.datatype, data)
+    }
+
     /// Retrieve the raw data, typed as specified in the volume's header, consuming the volume
     pub fn into_nifti_typed_data<T>(self) -> Result<Vec<T>>
     where
@@ -186,6 +346,963 @@ impl InMemNiftiVolume {
         T::from_raw_vec_validated(self.raw_data, self.endianness, self.datatype)
     }
 
+    /// Consume an RGB volume into an ndarray of colors.
+    ///
+    /// Both [`RGB8`] and `[u8; 3]` are element types for the `Rgb24` data
+    /// type (as are [`RGBA8`](rgb::RGBA8) and `[u8; 4]` for `Rgba32`), so
+    /// this is equivalent to `into_ndarray::<RGB8>()`, but only accepts
+    /// volumes with the `Rgb24` data type. Each voxel is stored as its three
+    /// channel bytes, so the array has the same shape as the volume.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidTypeConversion` if the volume's data type is not
+    ///   `Rgb24`.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn into_rgb_ndarray(self) -> Result<Array<RGB8, IxDyn>> {
+        if self.datatype != NiftiType::Rgb24 {
+            return Err(NiftiError::InvalidTypeConversion(self.datatype, "RGB8"));
+        }
+        self.into_ndarray()
+    }
+
+    /// Consume the volume into an ndarray, as in
+    /// [`into_ndarray`](../ndarray/trait.IntoNdArray.html#tymethod.into_ndarray),
+    /// with an explicit policy for the values which do not fit in the range
+    /// of an integer element type `T`. The scaled values are converted,
+    /// truncating them toward zero.
+    ///
+    /// The policy is ignored if `T` is not an integer type, or if the volume's
+    /// data type is not a real scalar type.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::ValueOutOfRange` with the first offending value and its
+    ///   coordinates, if the policy is `ConversionPolicy::Error`.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn into_ndarray_with<T>(self, policy: ConversionPolicy) -> Result<Array<T, IxDyn>>
+    where
+        T: DataElement,
+    {
+        let (tmin, tmax) = match integer_range(T::DATA_TYPE) {
+            Some(range) => range,
+            None => return self.into_ndarray(),
+        };
+        let values = match self.iter_f64() {
+            Ok(values) => values,
+            Err(_) => return self.into_ndarray(),
+        };
+
+        let dim = self.dim();
+        let mut data = Vec::with_capacity(self.raw_data.len() / self.datatype.size_of());
+        for (i, v) in values.enumerate() {
+            let in_range = v >= tmin && v <= tmax;
+            let value = match policy {
+                _ if in_range => T::from_f64(v),
+                ConversionPolicy::Saturate | ConversionPolicy::Wrap if v.is_nan() => {
+                    T::from_f64(0.)
+                }
+                ConversionPolicy::Saturate => T::from_f64(v.clamp(tmin, tmax)),
+                ConversionPolicy::Wrap if v.is_infinite() => T::from_f64(v.clamp(tmin, tmax)),
+                // the integer part fits in an `i128`, and its lower bits
+                // are kept by casting to the target type
+                ConversionPolicy::Wrap => T::from_i64(v as i128 as i64),
+                ConversionPolicy::Error => {
+                    return Err(NiftiError::ValueOutOfRange(v, coords_of(i, dim)))
+                }
+            };
+            data.push(value);
+        }
+        let dim: Vec<Ix> = dim.iter().map(|&d| d as Ix).collect();
+        Ok(Array::from_shape_vec(IxDyn(&dim).f(), data).expect("Inconsistent raw data size"))
+    }
+
+    /// Obtain an iterator over all voxel values in column major order,
+    /// converted to `f64` and with the volume's scaling (`scl_slope` and
+    /// `scl_inter`) applied.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type (complex, RGB(A) and 128-bit types are not supported).
+    pub fn iter_f64(&self) -> Result<VoxelsF64<'_>> {
+        use NiftiType::*;
+        match self.datatype {
+            Uint8 | Int8 | Uint16 | Int16 | Uint32 | Int32 | Uint64 | Int64 | Float32 | Float64 => {
+                let size = self.datatype.size_of();
+                let len = self.raw_data.len() / size * size;
+                Ok(VoxelsF64 {
+                    rest: &self.raw_data[..len],
+                    block: Vec::new().into_iter(),
+                    datatype: self.datatype,
+                    endianness: self.endianness,
+                    scl_slope: self.scl_slope,
+                    scl_inter: self.scl_inter,
+                })
+            }
+            _ => Err(NiftiError::UnsupportedDataType(self.datatype)),
+        }
+    }
+
+    /// Collect all voxel values in column major order into a vector,
+    /// converted to `f64` and scaled as in [`iter_f64`](#method.iter_f64).
+    /// Unlike `into_ndarray`, this does not require the `ndarray_volumes`
+    /// feature.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type (complex, RGB(A) and 128-bit types are not supported).
+    pub fn into_vec_f64(self) -> Result<Vec<f64>> {
+        Ok(self.iter_f64()?.collect())
+    }
+
+    /// Check whether this volume holds the same voxel values as another,
+    /// regardless of how they are stored.
+    ///
+    /// Unlike `==`, which compares the raw data byte for byte, the values are
+    /// decoded with each volume's byte order and scaling (`scl_slope` and
+    /// `scl_inter`) before being compared, and the data types may differ. The
+    /// dimensions must be the same. Values which cannot be converted to `f64`
+    /// (complex, RGB(A) and 128-bit types) are only equal if both volumes have
+    /// the same data type and scaling. As with floating point numbers, NaN
+    /// values are never equal.
+    pub fn values_eq(&self, other: &InMemNiftiVolume) -> bool {
+        if self.dim() != other.dim() {
+            return false;
+        }
+        match (self.iter_f64(), other.iter_f64()) {
+            (Ok(a), Ok(b)) => a.eq(b),
+            (Err(_), Err(_)) => {
+                if self.datatype != other.datatype
+                    || self.scl_slope != other.scl_slope
+                    || self.scl_inter != other.scl_inter
+                {
+                    return false;
+                }
+                let a = adapt_bytes(&self.raw_data, self.datatype, self.endianness);
+                let b = adapt_bytes(&other.raw_data, other.datatype, other.endianness);
+                // compare complex components as numbers, so that -0 == 0
+                let f32s = |bytes: &[u8]| -> Vec<f32> {
+                    bytes
+                        .chunks_exact(4)
+                        .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect()
+                };
+                let f64s = |bytes: &[u8]| -> Vec<f64> {
+                    bytes
+                        .chunks_exact(8)
+                        .map(|c| {
+                            f64::from_ne_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]])
+                        })
+                        .collect()
+                };
+                match self.datatype {
+                    NiftiType::Complex64 => f32s(&a) == f32s(&b),
+                    NiftiType::Complex128 => f64s(&a) == f64s(&b),
+                    _ => a == b,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Obtain an iterator over the coordinates and values of all voxels, in
+    /// column major order. Values are converted to `f64` and scaled, as in
+    /// [`iter_f64`](#method.iter_f64).
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use nifti::InMemNiftiVolume;
+    /// # fn get_volume() -> InMemNiftiVolume { unimplemented!() }
+    /// let volume = get_volume();
+    /// let foreground: Vec<Vec<u64>> = volume
+    ///     .enumerate_f64()?
+    ///     .filter(|(_, v)| *v != 0.)
+    ///     .map(|(coords, _)| coords)
+    ///     .collect();
+    /// # Ok::<(), nifti::NiftiError>(())
+    /// ```
+    pub fn enumerate_f64(&self) -> Result<impl Iterator<Item = (Vec<u64>, f64)> + '_> {
+        let dim: Vec<u64> = self.dim().iter().map(|&d| u64::from(d)).collect();
+        let values = self.iter_f64()?;
+        Ok(values.enumerate().map(move |(index, value)| {
+            let mut index = index as u64;
+            let coords = dim
+                .iter()
+                .map(|&d| {
+                    let c = index % d;
+                    index /= d;
+                    c
+                })
+                .collect();
+            (coords, value)
+        }))
+    }
+
+    /// Convert this volume to a volume of another data type.
+    ///
+    /// When `target` is an integer type, the scaling parameters are chosen so
+    /// that the full range of the finite (scaled) voxel values is mapped
+    /// linearly onto the range of the target type, and values are rounded to
+    /// the nearest integer. Non-finite values are saturated, as in
+    /// [`cast_to_with`](#method.cast_to_with) with `ClampBehavior::Saturate`.
+    /// When `target` is a floating point type, the values are stored as is,
+    /// with a slope of 1 and intercept of 0.
+    ///
+    /// Returns the new volume, in native byte order, along with the chosen
+    /// `scl_slope` and `scl_inter`, which are also retained in the volume.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::UnsupportedDataType` if either the volume's data type
+    ///   or the target type is not a real scalar type.
+    pub fn cast_to(&self, target: NiftiType) -> Result<(InMemNiftiVolume, f32, f32)> {
+        self.cast_to_with(target, ClampBehavior::Saturate)
+    }
+
+    /// Convert this volume to a volume of another data type, as in
+    /// [`cast_to`](#method.cast_to), handling the values which cannot be
+    /// represented in an integer `target` type (NaN and infinities) as
+    /// specified by `clamp`. This has no effect on floating point targets.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::UnsupportedDataType` if either the volume's data type
+    ///   or the target type is not a real scalar type.
+    /// - `NiftiError::ValueOutOfRange` with the first offending value and its
+    ///   coordinates, if `clamp` is `ClampBehavior::Error` and a voxel holds a
+    ///   value out of the range of the target type.
+    pub fn cast_to_with(
+        &self,
+        target: NiftiType,
+        clamp: ClampBehavior,
+    ) -> Result<(InMemNiftiVolume, f32, f32)> {
+        let values = self.iter_f64()?;
+        let range = match target {
+            NiftiType::Float32 | NiftiType::Float64 => None,
+            _ => Some(integer_range(target).ok_or(NiftiError::UnsupportedDataType(target))?),
+        };
+
+        let (slope, inter) = match range {
+            None => (1., 0.),
+            Some((tmin, tmax)) => {
+                let (min, max) = values
+                    .clone()
+                    .filter(|v| v.is_finite())
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                        (min.min(v), max.max(v))
+                    });
+                if min > max {
+                    // no finite values
+                    (1., 0.)
+                } else if min == max {
+                    (1., min as f32)
+                } else {
+                    let slope = ((max - min) / (tmax - tmin)) as f32;
+                    let inter = (min - tmin * f64::from(slope)) as f32;
+                    (slope, inter)
+                }
+            }
+        };
+
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(values.len() * target.size_of())
+            .map_err(|e| NiftiError::ReserveVolume(values.len() * target.size_of(), e))?;
+        for (index, v) in values.enumerate() {
+            let v = match range {
+                None => v,
+                Some((tmin, tmax)) => {
+                    let v = if v.is_finite() {
+                        v
+                    } else {
+                        match clamp {
+                            // casting NaN to an integer yields 0
+                            ClampBehavior::Saturate if v.is_nan() => f64::from(inter),
+                            ClampBehavior::Saturate => v,
+                            ClampBehavior::Error => {
+                                return Err(NiftiError::ValueOutOfRange(
+                                    v,
+                                    coords_of(index, self.dim()),
+                                ))
+                            }
+                            ClampBehavior::UseFill(fill) => fill,
+                        }
+                    };
+                    ((v - f64::from(inter)) / f64::from(slope))
+                        .round()
+                        .clamp(tmin, tmax)
+                }
+            };
+            push_native_value(&mut raw_data, target, v);
+        }
+
+        let volume = InMemNiftiVolume {
+            dim: self.dim,
+            datatype: target,
+            scl_slope: slope,
+            scl_inter: inter,
+            raw_data,
+            endianness: Endianness::native(),
+        };
+        Ok((volume, slope, inter))
+    }
+
+    /// Split the volume along its last axis, into one volume of rank
+    /// `dimensionality() - 1` per index along that axis. For example, a 4D
+    /// time series is split into its 3D frames.
+    ///
+    /// Since the data is in column major order, each volume holds a contiguous
+    /// block of the raw data, which is copied as is.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if the volume is 1D,
+    ///   since NIfTI volumes cannot have less than one dimension.
+    pub fn split_last_axis(&self) -> Result<Vec<InMemNiftiVolume>> {
+        let dim = self.dim();
+        let rank = dim.len();
+        if rank < 2 {
+            return Err(NiftiError::IncorrectVolumeDimensionality(2, rank as u16));
+        }
+        let sub_dim = Dim::from_slice(&dim[..rank - 1])?;
+        let nb_volumes = usize::from(dim[rank - 1]);
+        let block_len = self.raw_data.len() / nb_volumes;
+
+        Ok(self
+            .raw_data
+            .chunks_exact(block_len)
+            .map(|block| InMemNiftiVolume {
+                dim: sub_dim,
+                datatype: self.datatype,
+                scl_slope: self.scl_slope,
+                scl_inter: self.scl_inter,
+                raw_data: block.to_vec(),
+                endianness: self.endianness,
+            })
+            .collect())
+    }
+
+    /// Extract the sub-volume spanning the range `[min[i], max[i])` along
+    /// each axis `i`. The data type and scaling of the volume are retained.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if `min` or `max` do not
+    ///   have one element per axis of the volume.
+    /// - `NiftiError::AxisOutOfBounds` if the range of an axis is empty or
+    ///   goes beyond the volume's dimensions.
+    pub fn crop(&self, min: &[u64], max: &[u64]) -> Result<InMemNiftiVolume> {
+        let dim = self.dim();
+        let rank = dim.len();
+        for bounds in [min, max] {
+            if bounds.len() != rank {
+                return Err(NiftiError::IncorrectVolumeDimensionality(
+                    rank as u16,
+                    bounds.len() as u16,
+                ));
+            }
+        }
+        for (axis, ((&lo, &hi), &d)) in min.iter().zip(max).zip(dim).enumerate() {
+            if lo >= hi || hi > u64::from(d) {
+                return Err(NiftiError::AxisOutOfBounds(axis as u16));
+            }
+        }
+        let new_dim: Vec<u64> = min.iter().zip(max).map(|(lo, hi)| hi - lo).collect();
+
+        let size = self.datatype.size_of();
+        let mut strides = vec![size; rank];
+        for i in 1..rank {
+            strides[i] = strides[i - 1] * usize::from(dim[i - 1]);
+        }
+        // copy one contiguous run along the first axis at a time
+        let run_len = new_dim[0] as usize * size;
+        let nb_runs: usize = new_dim[1..].iter().map(|&d| d as usize).product();
+        let mut raw_data = Vec::with_capacity(run_len * nb_runs);
+        let mut idx: Vec<usize> = min.iter().map(|&v| v as usize).collect();
+        for _ in 0..nb_runs {
+            let start: usize = idx.iter().zip(&strides).map(|(i, s)| i * s).sum();
+            raw_data.extend_from_slice(&self.raw_data[start..start + run_len]);
+            for axis in 1..rank {
+                idx[axis] += 1;
+                if idx[axis] < max[axis] as usize {
+                    break;
+                }
+                idx[axis] = min[axis] as usize;
+            }
+        }
+
+        Ok(InMemNiftiVolume {
+            dim: Dim::from_slice(&new_dim)?,
+            datatype: self.datatype,
+            scl_slope: self.scl_slope,
+            scl_inter: self.scl_inter,
+            raw_data,
+            endianness: self.endianness,
+        })
+    }
+
+    /// Extract the block of `shape` voxels starting at voxel `starts`, as in
+    /// [`crop`](#method.crop) with the range `[starts[i], starts[i] +
+    /// shape[i])` along each axis `i`. The data type, scaling and byte order
+    /// of the volume are retained.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if `starts` or `shape`
+    ///   do not have one element per axis of the volume.
+    /// - `NiftiError::OutOfBounds` if the block goes beyond the volume's
+    ///   dimensions, with the coordinates of its last voxel.
+    /// - `NiftiError::AxisOutOfBounds` if the block is empty along an axis.
+    pub fn crop_shape(&self, starts: &[u64], shape: &[u64]) -> Result<InMemNiftiVolume> {
+        let dim = self.dim();
+        for bounds in [starts, shape] {
+            if bounds.len() != dim.len() {
+                return Err(NiftiError::IncorrectVolumeDimensionality(
+                    dim.len() as u16,
+                    bounds.len() as u16,
+                ));
+            }
+        }
+        let ends: Vec<u64> = starts
+            .iter()
+            .zip(shape)
+            .map(|(&start, &len)| start.saturating_add(len))
+            .collect();
+        if ends.iter().zip(dim).any(|(&end, &d)| end > u64::from(d)) {
+            let last = ends
+                .iter()
+                .map(|&end| end.saturating_sub(1).min(u64::from(u16::MAX)) as u16)
+                .collect();
+            return Err(NiftiError::OutOfBounds(last));
+        }
+        self.crop(starts, &ends)
+    }
+
+    /// Place the volume in a larger volume of dimensions `target_dim`, with
+    /// the remaining voxels set to `fill`. The volume is centered along each
+    /// axis if `center` is true (rounding its offset down), and placed at the
+    /// origin otherwise. The data type, scaling and byte order of the volume
+    /// are retained, so `fill` is given as a scaled value and stored as the
+    /// raw value which maps to it (rounded and clamped for integer types).
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if `target_dim` does not
+    ///   have one element per axis of the volume.
+    /// - `NiftiError::AxisOutOfBounds` if `target_dim` is smaller than the
+    ///   volume along an axis.
+    /// - `NiftiError::BadVolumeSize` if `target_dim` does not fit in a header
+    ///   (a dimension beyond `u16::MAX`) or the volume is too large.
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type.
+    pub fn pad(&self, target_dim: &[u64], fill: f64, center: bool) -> Result<InMemNiftiVolume> {
+        let dim = self.dim();
+        let rank = dim.len();
+        if target_dim.len() != rank {
+            return Err(NiftiError::IncorrectVolumeDimensionality(
+                rank as u16,
+                target_dim.len() as u16,
+            ));
+        }
+        for (axis, (&t, &d)) in target_dim.iter().zip(dim).enumerate() {
+            if t < u64::from(d) {
+                return Err(NiftiError::AxisOutOfBounds(axis as u16));
+            }
+        }
+        let new_dim = dim_from_u64(target_dim)?;
+        let fill_bytes = self.encode_scaled_value(fill)?;
+
+        let nb_values = nb_values_for_dims(new_dim.as_ref()).ok_or(NiftiError::BadVolumeSize)?;
+        let mut raw_data = fill_bytes.repeat(nb_values);
+
+        let size = self.datatype.size_of();
+        let offset: Vec<usize> = target_dim
+            .iter()
+            .zip(dim)
+            .map(|(&t, &d)| {
+                if center {
+                    (t as usize - usize::from(d)) / 2
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let mut strides = vec![size; rank];
+        for i in 1..rank {
+            strides[i] = strides[i - 1] * target_dim[i - 1] as usize;
+        }
+        // copy one contiguous run along the first axis at a time
+        let run_len = usize::from(dim[0]) * size;
+        let mut idx = offset.clone();
+        for run in self.raw_data.chunks_exact(run_len) {
+            let start: usize = idx.iter().zip(&strides).map(|(i, s)| i * s).sum();
+            raw_data[start..start + run_len].copy_from_slice(run);
+            for axis in 1..rank {
+                idx[axis] += 1;
+                if idx[axis] < offset[axis] + usize::from(dim[axis]) {
+                    break;
+                }
+                idx[axis] = offset[axis];
+            }
+        }
+
+        Ok(InMemNiftiVolume {
+            dim: new_dim,
+            datatype: self.datatype,
+            scl_slope: self.scl_slope,
+            scl_inter: self.scl_inter,
+            raw_data,
+            endianness: self.endianness,
+        })
+    }
+
+    /// Set the voxels where `mask` is zero to `outside_value`, in place. As in
+    /// [`pad`](#method.pad), `outside_value` is given as a scaled value and
+    /// stored as the raw value which maps to it (rounded and clamped for
+    /// integer types). The scaled values of the mask are considered.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::DimMismatch` if the dimensions of the volume and the
+    ///   mask differ.
+    /// - `NiftiError::UnsupportedDataType` if the data type of the volume or
+    ///   of the mask is not a real scalar type.
+    pub fn apply_mask(&mut self, mask: &InMemNiftiVolume, outside_value: f64) -> Result<()> {
+        if self.dim() != mask.dim() {
+            return Err(NiftiError::DimMismatch(
+                self.dim().to_vec(),
+                mask.dim().to_vec(),
+            ));
+        }
+        let outside_bytes = self.encode_scaled_value(outside_value)?;
+        let voxels = self.raw_data.chunks_exact_mut(self.datatype.size_of());
+        for (voxel, m) in voxels.zip(mask.iter_f64()?) {
+            if m == 0. {
+                voxel.copy_from_slice(&outside_bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode a scaled value as the raw bytes of a voxel of this volume, in
+    /// its data type and byte order (rounded and clamped for integer types).
+    fn encode_scaled_value(&self, value: f64) -> Result<Vec<u8>> {
+        let raw_value = if self.scl_slope == 0. {
+            value
+        } else {
+            (value - f64::from(self.scl_inter)) / f64::from(self.scl_slope)
+        };
+        let raw_value = match self.datatype {
+            NiftiType::Float32 | NiftiType::Float64 => raw_value,
+            datatype => {
+                let (tmin, tmax) =
+                    integer_range(datatype).ok_or(NiftiError::UnsupportedDataType(datatype))?;
+                raw_value.round().clamp(tmin, tmax)
+            }
+        };
+        let mut bytes = Vec::with_capacity(self.datatype.size_of());
+        push_native_value(&mut bytes, self.datatype, raw_value);
+        Ok(adapt_bytes(&bytes, self.datatype, self.endianness).into_owned())
+    }
+
+    /// Resample the volume to isotropic voxels of `target_mm` millimeters
+    /// along the spatial axes (the first three), by nearest neighbor
+    /// interpolation, returning the new volume and an updated copy of
+    /// `header`, which must be the header of this volume.
+    ///
+    /// The voxel sizes in `pixdim` are read in the spatial units of the header
+    /// (millimeters if unknown). Each spatial dimension is scaled by the ratio
+    /// of its voxel size to the target size, rounded to the nearest integer,
+    /// and the first voxel keeps its position. In the returned header, `dim`
+    /// and `pixdim` are updated, and so are the columns of the 'sform'
+    /// transformation, whereas the 'qform' transformation follows from
+    /// `pixdim`. The data type, scaling and byte order of the volume are
+    /// retained.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::BadVolumeSize` if the dimensions of the header and the
+    ///   volume do not match, or if a new dimension is too large.
+    /// - `NiftiError::InvalidVoxelSize` if `target_mm` or a spatial voxel size
+    ///   is not a positive finite number.
+    /// - `NiftiError::InvalidCode` if the header has an invalid space unit.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn resample_isotropic(
+        &self,
+        header: &NiftiHeader,
+        target_mm: f64,
+    ) -> Result<(InMemNiftiVolume, NiftiHeader)> {
+        let dim = self.dim();
+        if header.dim()? != dim {
+            return Err(NiftiError::BadVolumeSize);
+        }
+        let mm_per_unit = header.xyzt_to_space()?.spatial_scale_to_mm().unwrap_or(1.);
+        let target = target_mm / mm_per_unit;
+        if !(target.is_finite() && target > 0.) {
+            return Err(NiftiError::InvalidVoxelSize(target_mm));
+        }
+
+        let rank = dim.len();
+        let nb_spatial = rank.min(3);
+        // ratio between the old and new voxel sizes of each axis
+        let mut ratios = vec![1.; rank];
+        let mut new_dim: Vec<u64> = dim.iter().map(|&d| u64::from(d)).collect();
+        for axis in 0..nb_spatial {
+            let size = f64::from(header.pixdim[axis + 1]);
+            if !(size.is_finite() && size > 0.) {
+                return Err(NiftiError::InvalidVoxelSize(size));
+            }
+            ratios[axis] = size / target;
+            let len = (f64::from(dim[axis]) * ratios[axis]).round().max(1.);
+            if len > f64::from(u16::MAX) {
+                return Err(NiftiError::BadVolumeSize);
+            }
+            new_dim[axis] = len as u64;
+        }
+        let new_dim = Dim::from_slice(&new_dim)?;
+
+        // the source index along each axis, for each index of the new volume
+        let source_idx: Vec<Vec<usize>> = new_dim
+            .as_ref()
+            .iter()
+            .zip(dim)
+            .zip(&ratios)
+            .map(|((&n, &d), &ratio)| {
+                (0..usize::from(n))
+                    .map(|i| ((i as f64 / ratio).round() as usize).min(usize::from(d) - 1))
+                    .collect()
+            })
+            .collect();
+        let size = self.datatype.size_of();
+        let mut strides = vec![size; rank];
+        for i in 1..rank {
+            strides[i] = strides[i - 1] * usize::from(dim[i - 1]);
+        }
+
+        let nb_values = nb_values_for_dims(new_dim.as_ref()).ok_or(NiftiError::BadVolumeSize)?;
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(nb_values * size)
+            .map_err(|e| NiftiError::ReserveVolume(nb_values * size, e))?;
+        let mut idx = vec![0; rank];
+        for _ in 0..nb_values {
+            let start: usize = idx
+                .iter()
+                .zip(&source_idx)
+                .zip(&strides)
+                .map(|((&i, src), s)| src[i] * s)
+                .sum();
+            raw_data.extend_from_slice(&self.raw_data[start..start + size]);
+            for axis in 0..rank {
+                idx[axis] += 1;
+                if idx[axis] < source_idx[axis].len() {
+                    break;
+                }
+                idx[axis] = 0;
+            }
+        }
+
+        let mut header = header.clone();
+        header.dim = *new_dim.raw();
+        for axis in 0..nb_spatial {
+            header.pixdim[axis + 1] = target as f32;
+            let scale = (1. / ratios[axis]) as f32;
+            for row in [&mut header.srow_x, &mut header.srow_y, &mut header.srow_z] {
+                row[axis] *= scale;
+            }
+        }
+
+        let volume = InMemNiftiVolume {
+            dim: new_dim,
+            datatype: self.datatype,
+            scl_slope: self.scl_slope,
+            scl_inter: self.scl_inter,
+            raw_data,
+            endianness: self.endianness,
+        };
+        Ok((volume, header))
+    }
+
+    /// Convert an RGB or RGBA volume to a scalar `Float32` volume of the
+    /// luminance of each voxel, `0.299 * R + 0.587 * G + 0.114 * B` (as in
+    /// ITU-R BT.601). The alpha channel is ignored.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InvalidTypeConversion` if the volume's data type is
+    ///   neither `Rgb24` nor `Rgba32`.
+    pub fn rgb_to_luminance(&self) -> Result<InMemNiftiVolume> {
+        let nb_channels = match self.datatype {
+            NiftiType::Rgb24 => 3,
+            NiftiType::Rgba32 => 4,
+            _ => {
+                return Err(NiftiError::InvalidTypeConversion(
+                    self.datatype,
+                    "luminance",
+                ))
+            }
+        };
+        let raw_data = self
+            .raw_data
+            .chunks_exact(nb_channels)
+            .flat_map(|c| {
+                let [r, g, b] = [c[0], c[1], c[2]].map(f64::from);
+                ((0.299 * r + 0.587 * g + 0.114 * b) as f32).to_ne_bytes()
+            })
+            .collect();
+        Ok(InMemNiftiVolume {
+            dim: self.dim,
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::native(),
+        })
+    }
+
+    /// Reinterpret an `Int16` volume holding the bits of half precision floats
+    /// as a `Float32` volume in native byte order.
+    pub(crate) fn decode_f16(&self) -> Result<InMemNiftiVolume> {
+        if self.datatype != NiftiType::Int16 {
+            return Err(NiftiError::InvalidTypeConversion(self.datatype, "float16"));
+        }
+        let mut raw_data = Vec::with_capacity(self.raw_data.len() * 2);
+        for chunk in self.raw_data.chunks_exact(2) {
+            let bits = match self.endianness {
+                Endianness::Little => u16::from_le_bytes([chunk[0], chunk[1]]),
+                Endianness::Big => u16::from_be_bytes([chunk[0], chunk[1]]),
+            };
+            raw_data.extend_from_slice(&f16_bits_to_f32(bits).to_ne_bytes());
+        }
+        Ok(InMemNiftiVolume {
+            dim: self.dim,
+            datatype: NiftiType::Float32,
+            scl_slope: self.scl_slope,
+            scl_inter: self.scl_inter,
+            raw_data,
+            endianness: Endianness::native(),
+        })
+    }
+
+    /// Rescale each slice along the last axis independently, on top of the
+    /// volume's global scaling: the value `v` of a voxel in slice `k`
+    /// becomes `v * slopes[k] + inters[k]`.
+    ///
+    /// This is useful for applying per-slice rescaling metadata, such as the
+    /// one obtained from DICOM sources. Since the resulting values are generally
+    /// not integers, the volume is converted in place to `Float64` if its data
+    /// type is a 64-bit type, or to `Float32` otherwise, in native byte order
+    /// and with a slope of 1 and intercept of 0.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncompatibleLength` if the number of slopes or
+    ///   intercepts is not the number of slices (the last dimension).
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type.
+    pub fn apply_per_slice_scaling(&mut self, slopes: &[f32], inters: &[f32]) -> Result<()> {
+        let nb_slices = usize::from(*self.dim().last().expect("at least one dimension"));
+        if slopes.len() != nb_slices {
+            return Err(NiftiError::IncompatibleLength(slopes.len(), nb_slices));
+        }
+        if inters.len() != nb_slices {
+            return Err(NiftiError::IncompatibleLength(inters.len(), nb_slices));
+        }
+
+        let target = match self.datatype {
+            NiftiType::Uint64 | NiftiType::Int64 | NiftiType::Float64 => NiftiType::Float64,
+            _ => NiftiType::Float32,
+        };
+        let values = self.iter_f64()?;
+        let slice_len = values.len() / nb_slices;
+        let nb_bytes = values.len() * target.size_of();
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(nb_bytes)
+            .map_err(|e| NiftiError::ReserveVolume(nb_bytes, e))?;
+        for (i, v) in values.enumerate() {
+            let k = i / slice_len;
+            let v = v * f64::from(slopes[k]) + f64::from(inters[k]);
+            push_native_value(&mut raw_data, target, v);
+        }
+
+        self.datatype = target;
+        self.scl_slope = 1.;
+        self.scl_inter = 0.;
+        self.raw_data = raw_data;
+        self.endianness = Endianness::native();
+        Ok(())
+    }
+
+    /// Sample an arbitrary (possibly oblique) plane in world space, as in a
+    /// multiplanar reformatting viewer. The sample at `[i, j]` of the output
+    /// is taken at the world position `origin + i * u + j * v`, mapped to
+    /// voxel coordinates through the best affine of `header` (see
+    /// [`NiftiHeader::affine`]), which must be the header of this volume.
+    ///
+    /// Values are scaled and interpolated trilinearly from the first three
+    /// axes (the first 3D frame of volumes with more dimensions). Samples
+    /// outside of the volume are set to `outside`, such as 0 or NaN.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncorrectVolumeDimensionality` if the volume has less
+    ///   than 3 dimensions.
+    /// - `NiftiError::InvalidCode` if the header contains an invalid 'sform'
+    ///   or 'qform' code.
+    /// - `NiftiError::InvalidVoxelSize` if a spacing (`pixdim[1..4]`) is
+    ///   negative, and `NiftiError::InvalidQfac` if `pixdim[0]` is neither 1
+    ///   nor -1, when the 'qform' is used.
+    /// - `NiftiError::SingularAffine` if the affine cannot be inverted.
+    /// - `NiftiError::UnsupportedDataType` if the volume's data type is not a
+    ///   real scalar type.
+    ///
+    /// [`NiftiHeader::affine`]: ../../header/struct.NiftiHeader.html#method.affine
+    #[cfg(all(feature = "nalgebra_affine", feature = "ndarray_volumes"))]
+    pub fn sample_plane(
+        &self,
+        header: &NiftiHeader,
+        origin: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        size: [usize; 2],
+        outside: f64,
+    ) -> Result<Array2<f64>> {
+        let dim = self.dim();
+        if dim.len() < 3 {
+            return Err(NiftiError::IncorrectVolumeDimensionality(
+                3,
+                dim.len() as u16,
+            ));
+        }
+        let n = [
+            usize::from(dim[0]),
+            usize::from(dim[1]),
+            usize::from(dim[2]),
+        ];
+        let inverse = header
+            .checked_affine::<f64>()?
+            .try_inverse()
+            .ok_or(NiftiError::SingularAffine)?;
+        let values: Vec<f64> = self.iter_f64()?.take(n[0] * n[1] * n[2]).collect();
+        let at = |x: usize, y: usize, z: usize| values[x + n[0] * (y + n[1] * z)];
+
+        // tolerance for positions on the border of the volume
+        const EPS: f64 = 1e-6;
+        Ok(Array2::from_shape_fn((size[0], size[1]), |(i, j)| {
+            let (i, j) = (i as f64, j as f64);
+            let world = Vector4::new(
+                origin[0] + i * u[0] + j * v[0],
+                origin[1] + i * u[1] + j * v[1],
+                origin[2] + i * u[2] + j * v[2],
+                1.,
+            );
+            let voxel = inverse * world;
+            // lower corner and interpolation weight along each axis
+            let mut lo = [0; 3];
+            let mut hi = [0; 3];
+            let mut w = [0.; 3];
+            for axis in 0..3 {
+                let max = (n[axis] - 1) as f64;
+                let x = voxel[axis];
+                if !(-EPS..=max + EPS).contains(&x) {
+                    return outside;
+                }
+                let x = x.clamp(0., max);
+                lo[axis] = (x.floor() as usize).min(n[axis].saturating_sub(2));
+                hi[axis] = (lo[axis] + 1).min(n[axis] - 1);
+                w[axis] = x - lo[axis] as f64;
+            }
+            let mut value = 0.;
+            for corner in 0..8 {
+                let mut weight = 1.;
+                let mut idx = [0; 3];
+                for axis in 0..3 {
+                    if corner & (1 << axis) == 0 {
+                        idx[axis] = lo[axis];
+                        weight *= 1. - w[axis];
+                    } else {
+                        idx[axis] = hi[axis];
+                        weight *= w[axis];
+                    }
+                }
+                if weight != 0. {
+                    value += weight * at(idx[0], idx[1], idx[2]);
+                }
+            }
+            value
+        }))
+    }
+
+    /// Rearrange the first three axes of the volume, so that the new axis `j`
+    /// is the old axis `perm[j]`, traversed backwards if `flip[j]` is true.
+    /// Any remaining axes are kept as is.
+    #[cfg(feature = "nalgebra_affine")]
+    pub(crate) fn reorient_spatial(&self, perm: [usize; 3], flip: [bool; 3]) -> Result<Self> {
+        let old_dim = self.dim();
+        let rank = old_dim.len();
+        if rank < 3 {
+            return Err(NiftiError::IncorrectVolumeDimensionality(3, rank as u16));
+        }
+        let mut new_dim = old_dim.to_vec();
+        for j in 0..3 {
+            new_dim[j] = old_dim[perm[j]];
+        }
+
+        let mut old_strides = vec![1; rank];
+        for i in 1..rank {
+            old_strides[i] = old_strides[i - 1] * usize::from(old_dim[i - 1]);
+        }
+        // stride and starting offset (in elements) of each new axis in the old data
+        let mut strides = vec![0isize; rank];
+        let mut start = 0;
+        for j in 0..rank {
+            let i = if j < 3 { perm[j] } else { j };
+            if j < 3 && flip[j] {
+                strides[j] = -(old_strides[i] as isize);
+                start += (usize::from(old_dim[i]) - 1) * old_strides[i];
+            } else {
+                strides[j] = old_strides[i] as isize;
+            }
+        }
+
+        let size = self.datatype.size_of();
+        let mut raw_data = Vec::with_capacity(self.raw_data.len());
+        let mut idx = vec![0u16; rank];
+        let mut offset = start as isize;
+        for _ in 0..self.raw_data.len() / size {
+            let o = offset as usize * size;
+            raw_data.extend_from_slice(&self.raw_data[o..o + size]);
+            // advance the multi-dimensional index, first axis fastest
+            for j in 0..rank {
+                idx[j] += 1;
+                offset += strides[j];
+                if idx[j] < new_dim[j] {
+                    break;
+                }
+                offset -= strides[j] * idx[j] as isize;
+                idx[j] = 0;
+            }
+        }
+
+        Ok(InMemNiftiVolume {
+            dim: Dim::from_slice(&new_dim)?,
+            datatype: self.datatype,
+            scl_slope: self.scl_slope,
+            scl_inter: self.scl_inter,
+            raw_data,
+            endianness: self.endianness,
+        })
+    }
+
     fn get_prim<T>(&self, coords: &[u16]) -> Result<T>
     where
         T: DataElement,
@@ -224,7 +1341,7 @@ impl InMemNiftiVolume {
         // corresponding to the declared datatype
         if self.datatype != NiftiType::Rgb24
             && self.datatype != NiftiType::Rgba32
-            && self.scl_slope != 0.0
+            && !is_identity_scaling(self.scl_slope, self.scl_inter)
         {
             <T as DataElement>::DataRescaler::nifti_rescale_many_inline(
                 &mut data,
@@ -236,10 +1353,214 @@ impl InMemNiftiVolume {
     }
 }
 
+/// How to convert the values which cannot be represented in the target type
+/// of [`InMemNiftiVolume::cast_to_with`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClampBehavior {
+    /// Saturate the values to the closest value of the target type. Infinities
+    /// become the minimum or maximum of the type, and NaN becomes the value
+    /// stored as 0.
+    Saturate,
+    /// Fail with `NiftiError::ValueOutOfRange`.
+    Error,
+    /// Replace the values with the given (scaled) value, itself saturated
+    /// to the range of the target type.
+    UseFill(f64),
+}
+
+/// How to convert the values which do not fit in the range of an integer
+/// element type, in [`InMemNiftiVolume::into_ndarray_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConversionPolicy {
+    /// Saturate the values to the minimum or maximum of the type. NaN
+    /// becomes 0.
+    #[default]
+    Saturate,
+    /// Keep the lower bits of the integer part of the values, as in a cast
+    /// between integer types. NaN becomes 0, and infinities saturate.
+    Wrap,
+    /// Fail with `NiftiError::ValueOutOfRange`.
+    Error,
+}
+
+/// Summary of the differences between the voxel values of two volumes, as
+/// computed by [`diff`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeDiff {
+    /// The maximum absolute difference between two voxel values.
+    pub max_abs: f64,
+    /// The mean absolute difference between two voxel values, over all voxels.
+    pub mean_abs: f64,
+    /// The number of voxels with different values.
+    pub nb_different: usize,
+}
+
+/// Compare the voxel values of two volumes of the same dimensions.
+///
+/// As in [`InMemNiftiVolume::values_eq`], the values are decoded with each
+/// volume's byte order and scaling before being compared, and the data types
+/// may differ. NaN values always count as different, and make the maximum and
+/// mean differences NaN.
+///
+/// # Errors
+///
+/// - `NiftiError::DimMismatch` if the dimensions of the volumes differ.
+/// - `NiftiError::UnsupportedDataType` if a volume's values cannot be
+///   converted to `f64` (complex, RGB(A) and 128-bit types).
+pub fn diff(a: &InMemNiftiVolume, b: &InMemNiftiVolume) -> Result<VolumeDiff> {
+    if a.dim() != b.dim() {
+        return Err(NiftiError::DimMismatch(a.dim().to_vec(), b.dim().to_vec()));
+    }
+    let values = a.iter_f64()?.zip(b.iter_f64()?);
+    let nb_values = values.len();
+
+    let mut max_abs: f64 = 0.;
+    let mut sum_abs = 0.;
+    let mut nb_different = 0;
+    for (a, b) in values {
+        if a != b {
+            nb_different += 1;
+        }
+        let d = (a - b).abs();
+        // `f64::max` would ignore NaN
+        if d.is_nan() || d > max_abs {
+            max_abs = d;
+        }
+        sum_abs += d;
+    }
+
+    Ok(VolumeDiff {
+        max_abs,
+        mean_abs: sum_abs / nb_values as f64,
+        nb_different,
+    })
+}
+
+/// Concatenate 3D volumes along a new fourth axis, such as the frames of a
+/// time series. Since the data is in column major order, the new axis varies
+/// the slowest, and the raw data of each volume is copied as is.
+///
+/// # Errors
+///
+/// - `NiftiError::NoVolumeData` if `vols` is empty.
+/// - `NiftiError::IncorrectVolumeDimensionality` if a volume is not 3D.
+/// - `NiftiError::DimMismatch` if the dimensions of a volume differ from
+///   those of the first one.
+/// - `NiftiError::VolumeMismatch` if the data type, byte order or scaling of
+///   a volume differ from those of the first one.
+/// - `NiftiError::BadVolumeSize` if there are more than `u16::MAX` volumes.
+pub fn concat_volumes(vols: &[InMemNiftiVolume]) -> Result<InMemNiftiVolume> {
+    let first = vols.first().ok_or(NiftiError::NoVolumeData)?;
+    for (i, vol) in vols.iter().enumerate() {
+        if vol.dimensionality() != 3 {
+            return Err(NiftiError::IncorrectVolumeDimensionality(
+                3,
+                vol.dimensionality() as u16,
+            ));
+        }
+        if vol.dim() != first.dim() {
+            return Err(NiftiError::DimMismatch(
+                first.dim().to_vec(),
+                vol.dim().to_vec(),
+            ));
+        }
+        if vol.datatype != first.datatype {
+            return Err(NiftiError::VolumeMismatch(i, "data type"));
+        }
+        if vol.endianness != first.endianness {
+            return Err(NiftiError::VolumeMismatch(i, "byte order"));
+        }
+        if vol.scl_slope != first.scl_slope || vol.scl_inter != first.scl_inter {
+            return Err(NiftiError::VolumeMismatch(i, "scaling"));
+        }
+    }
+    let nb_volumes = u16::try_from(vols.len()).map_err(|_| NiftiError::BadVolumeSize)?;
+    let mut dim = first.dim().to_vec();
+    dim.push(nb_volumes);
+
+    Ok(InMemNiftiVolume {
+        dim: Dim::from_slice(&dim)?,
+        datatype: first.datatype,
+        scl_slope: first.scl_slope,
+        scl_inter: first.scl_inter,
+        raw_data: vols.iter().flat_map(|vol| &vol.raw_data).copied().collect(),
+        endianness: first.endianness,
+    })
+}
+
+/// Whether the given slope and intercept leave values unchanged.
+/// A slope of 0 means that no scaling is applied.
+#[cfg(feature = "ndarray_volumes")]
+fn is_identity_scaling(slope: f32, inter: f32) -> bool {
+    slope == 0. || (slope == 1. && inter == 0.)
+}
+
+/// Build the dimensions of a volume, failing with `BadVolumeSize` if a
+/// dimension does not fit in the `dim` field of a header.
+fn dim_from_u64(dim: &[u64]) -> Result<Dim> {
+    let dim = dim
+        .iter()
+        .map(|&d| u16::try_from(d).map_err(|_| NiftiError::BadVolumeSize))
+        .collect::<Result<Vec<u16>>>()?;
+    Dim::from_slice(&dim)
+}
+
+/// Retrieve the coordinates of the voxel at the given index, in column major
+/// order, of a volume with the given dimensions.
+fn coords_of(index: usize, dim: &[u16]) -> Vec<u16> {
+    let mut rest = index;
+    dim.iter()
+        .map(|&d| {
+            let coord = rest % usize::from(d);
+            rest /= usize::from(d);
+            coord as u16
+        })
+        .collect()
+}
+
+/// Retrieve the range of values representable by an integer data type.
+fn integer_range(datatype: NiftiType) -> Option<(f64, f64)> {
+    match datatype {
+        NiftiType::Uint8 => Some((u8::MIN.into(), u8::MAX.into())),
+        NiftiType::Int8 => Some((i8::MIN.into(), i8::MAX.into())),
+        NiftiType::Uint16 => Some((u16::MIN.into(), u16::MAX.into())),
+        NiftiType::Int16 => Some((i16::MIN.into(), i16::MAX.into())),
+        NiftiType::Uint32 => Some((u32::MIN.into(), u32::MAX.into())),
+        NiftiType::Int32 => Some((i32::MIN.into(), i32::MAX.into())),
+        NiftiType::Uint64 => Some((u64::MIN as f64, u64::MAX as f64)),
+        NiftiType::Int64 => Some((i64::MIN as f64, i64::MAX as f64)),
+        _ => None,
+    }
+}
+
+/// Append a value to a raw data buffer in native byte order,
+/// casting it to the given real scalar data type.
+fn push_native_value(buf: &mut Vec<u8>, datatype: NiftiType, value: f64) {
+    match datatype {
+        NiftiType::Uint8 => buf.push(value as u8),
+        NiftiType::Int8 => buf.extend_from_slice(&(value as i8).to_ne_bytes()),
+        NiftiType::Uint16 => buf.extend_from_slice(&(value as u16).to_ne_bytes()),
+        NiftiType::Int16 => buf.extend_from_slice(&(value as i16).to_ne_bytes()),
+        NiftiType::Uint32 => buf.extend_from_slice(&(value as u32).to_ne_bytes()),
+        NiftiType::Int32 => buf.extend_from_slice(&(value as i32).to_ne_bytes()),
+        NiftiType::Uint64 => buf.extend_from_slice(&(value as u64).to_ne_bytes()),
+        NiftiType::Int64 => buf.extend_from_slice(&(value as i64).to_ne_bytes()),
+        NiftiType::Float32 => buf.extend_from_slice(&(value as f32).to_ne_bytes()),
+        NiftiType::Float64 => buf.extend_from_slice(&value.to_ne_bytes()),
+        _ => unreachable!("not a real scalar data type"),
+    }
+}
+
 impl FromSourceOptions for InMemNiftiVolume {
     type Options = ();
 }
 
+/// Number of bytes read between two progress reports.
+const PROGRESS_CHUNK_SIZE: u64 = 1 << 16;
+
+/// Number of voxel values decoded at once by `VoxelsF64`.
+const VOXELS_PER_BLOCK: usize = 4096;
+
 impl<R> FromSource<R> for InMemNiftiVolume
 where
     R: Read,
@@ -247,6 +1568,15 @@ where
     fn from_reader(reader: R, header: &NiftiHeader, (): Self::Options) -> Result<Self> {
         InMemNiftiVolume::from_reader(reader, header)
     }
+
+    fn from_reader_with_progress(
+        reader: R,
+        header: &NiftiHeader,
+        (): Self::Options,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Self> {
+        InMemNiftiVolume::from_reader_with_progress(reader, header, progress)
+    }
 }
 
 #[cfg(feature = "ndarray_volumes")]
@@ -279,7 +1609,7 @@ impl IntoNdArray for InMemNiftiVolume {
 }
 
 #[cfg(feature = "ndarray_volumes")]
-impl<'a> IntoNdArray for &'a InMemNiftiVolume {
+impl IntoNdArray for &InMemNiftiVolume {
     /// Create an ndarray from the given volume.
     fn into_ndarray<T>(self) -> Result<Array<T, IxDyn>>
     where
@@ -289,7 +1619,7 @@ impl<'a> IntoNdArray for &'a InMemNiftiVolume {
     }
 }
 
-impl<'a> NiftiVolume for &'a InMemNiftiVolume {
+impl NiftiVolume for &InMemNiftiVolume {
     fn dim(&self) -> &[u16] {
         (**self).dim()
     }
@@ -323,7 +1653,10 @@ impl RandomAccessNiftiVolume for InMemNiftiVolume {
     }
 
     fn get_f64(&self, coords: &[u16]) -> Result<f64> {
-        self.get_prim(coords)
+        let index = coords_to_index(coords, self.dim())?;
+        let range = &self.raw_data[index * self.datatype.size_of()..];
+        self.datatype
+            .read_f64_value(range, self.endianness, self.scl_slope, self.scl_inter)
     }
 
     fn get_u8(&self, coords: &[u16]) -> Result<u8> {
@@ -357,15 +1690,99 @@ impl RandomAccessNiftiVolume for InMemNiftiVolume {
     fn get_i64(&self, coords: &[u16]) -> Result<i64> {
         self.get_prim(coords)
     }
-}
-
-impl<'a> RandomAccessNiftiVolume for &'a InMemNiftiVolume {
-    fn get_f32(&self, coords: &[u16]) -> Result<f32> {
-        (**self).get_f32(coords)
-    }
 
-    fn get_f64(&self, coords: &[u16]) -> Result<f64> {
-        (**self).get_f64(coords)
+    fn collect_slice(&self, axis: u16, index: u16) -> Result<InMemNiftiVolume> {
+        let slice_dim = crate::volume::slice_dim(self.dim(), axis, index)?;
+        if slice_dim.is_empty() {
+            return Err(NiftiError::IncorrectVolumeDimensionality(2, 1));
+        }
+        let mut min = vec![0; self.dim.rank()];
+        let mut max: Vec<u64> = self.dim().iter().map(|&d| u64::from(d)).collect();
+        min[usize::from(axis)] = u64::from(index);
+        max[usize::from(axis)] = u64::from(index) + 1;
+        let slice = self.crop(&min, &max)?;
+        Ok(InMemNiftiVolume {
+            dim: Dim::from_slice(&slice_dim.iter().map(|&d| u64::from(d)).collect::<Vec<_>>())?,
+            ..slice
+        })
+    }
+}
+
+/// An iterator over the voxel values of an in-memory volume, converted to
+/// `f64` and scaled. See [`InMemNiftiVolume::iter_f64`].
+#[derive(Debug, Clone)]
+pub struct VoxelsF64<'a> {
+    /// The raw data of the values which are yet to be decoded
+    rest: &'a [u8],
+    /// The decoded values of the current block
+    block: std::vec::IntoIter<f64>,
+    datatype: NiftiType,
+    endianness: Endianness,
+    scl_slope: f32,
+    scl_inter: f32,
+}
+
+impl Iterator for VoxelsF64<'_> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        loop {
+            if let Some(value) = self.block.next() {
+                return Some(value);
+            }
+            if self.rest.is_empty() {
+                return None;
+            }
+            let len = self
+                .rest
+                .len()
+                .min(VOXELS_PER_BLOCK * self.datatype.size_of());
+            let (bytes, rest) = self.rest.split_at(len);
+            self.rest = rest;
+            // the data type was validated on construction
+            // and the data was truncated to whole values
+            self.block = self
+                .datatype
+                .read_values(bytes, self.endianness, self.scl_slope, self.scl_inter)
+                .expect("scalar voxel values should be readable")
+                .into_iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.block.len() + self.rest.len() / self.datatype.size_of();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for VoxelsF64<'_> {}
+
+/// Iterating over a reference to a volume yields all of its voxel values in
+/// column major order, converted to `f64` and scaled.
+///
+/// # Panics
+///
+/// Panics if the volume's data type is not a real scalar type. Use
+/// [`InMemNiftiVolume::iter_f64`] to handle this case gracefully.
+impl<'a> IntoIterator for &'a InMemNiftiVolume {
+    type Item = f64;
+    type IntoIter = VoxelsF64<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.iter_f64() {
+            Ok(it) => it,
+            Err(e) => panic!("cannot iterate over volume as f64: {}", e),
+        }
+    }
+}
+
+impl RandomAccessNiftiVolume for &InMemNiftiVolume {
+    fn get_f32(&self, coords: &[u16]) -> Result<f32> {
+        (**self).get_f32(coords)
+    }
+
+    fn get_f64(&self, coords: &[u16]) -> Result<f64> {
+        (**self).get_f64(coords)
     }
 
     fn get_u8(&self, coords: &[u16]) -> Result<u8> {
@@ -399,6 +1816,142 @@ impl<'a> RandomAccessNiftiVolume for &'a InMemNiftiVolume {
     fn get_i64(&self, coords: &[u16]) -> Result<i64> {
         (**self).get_i64(coords)
     }
+
+    fn collect_slice(&self, axis: u16, index: u16) -> Result<InMemNiftiVolume> {
+        (**self).collect_slice(axis, index)
+    }
+}
+
+/// An in-memory volume bundled with the NIfTI header that describes it, so
+/// that a single value carries the volume's dimensions, scaling and spatial
+/// orientation.
+///
+/// It can be built from its parts with [`new`](#method.new), or from a
+/// complete NIfTI object via `From<InMemNiftiObject>`, discarding the
+/// extensions.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VolumeWithHeader {
+    header: NiftiHeader,
+    volume: InMemNiftiVolume,
+}
+
+impl VolumeWithHeader {
+    /// Bundle a volume with its header, which must describe the volume (see
+    /// [`NiftiHeader::matches_volume`]) and hold the same scaling.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::InconsistentDim` if the header's `dim` field is invalid.
+    /// - `NiftiError::NoDataType` or `NiftiError::InvalidCode` if the header's
+    ///   data type is invalid.
+    /// - `NiftiError::DimMismatch` if the dimensions differ.
+    /// - `NiftiError::DataTypeMismatch` if the data types differ.
+    /// - `NiftiError::ScalingMismatch` if `scl_slope` or `scl_inter` differ.
+    ///
+    /// [`NiftiHeader::matches_volume`]: ../../header/struct.NiftiHeader.html#method.matches_volume
+    pub fn new(header: NiftiHeader, volume: InMemNiftiVolume) -> Result<Self> {
+        header.matches_volume(&volume)?;
+        let same = |a: f32, b: f32| a == b || (a.is_nan() && b.is_nan());
+        if !same(header.scl_slope, volume.scl_slope) || !same(header.scl_inter, volume.scl_inter) {
+            return Err(NiftiError::ScalingMismatch(
+                (header.scl_slope, header.scl_inter),
+                (volume.scl_slope, volume.scl_inter),
+            ));
+        }
+        Ok(VolumeWithHeader { header, volume })
+    }
+
+    /// Bundle a volume with the header it was created from.
+    pub(crate) fn from_parts_unchecked(header: NiftiHeader, volume: InMemNiftiVolume) -> Self {
+        VolumeWithHeader { header, volume }
+    }
+
+    /// Obtain a reference to the header.
+    pub fn header(&self) -> &NiftiHeader {
+        &self.header
+    }
+
+    /// Obtain a reference to the volume.
+    pub fn volume(&self) -> &InMemNiftiVolume {
+        &self.volume
+    }
+
+    /// Split the bundle back into the header and the volume.
+    pub fn into_parts(self) -> (NiftiHeader, InMemNiftiVolume) {
+        (self.header, self.volume)
+    }
+}
+
+impl AsRef<NiftiHeader> for VolumeWithHeader {
+    fn as_ref(&self) -> &NiftiHeader {
+        &self.header
+    }
+}
+
+impl AsRef<InMemNiftiVolume> for VolumeWithHeader {
+    fn as_ref(&self) -> &InMemNiftiVolume {
+        &self.volume
+    }
+}
+
+impl NiftiVolume for VolumeWithHeader {
+    fn dim(&self) -> &[u16] {
+        self.volume.dim()
+    }
+
+    fn dimensionality(&self) -> usize {
+        self.volume.dimensionality()
+    }
+
+    fn data_type(&self) -> NiftiType {
+        self.volume.data_type()
+    }
+}
+
+impl RandomAccessNiftiVolume for VolumeWithHeader {
+    fn get_f32(&self, coords: &[u16]) -> Result<f32> {
+        self.volume.get_f32(coords)
+    }
+
+    fn get_f64(&self, coords: &[u16]) -> Result<f64> {
+        self.volume.get_f64(coords)
+    }
+
+    fn get_u8(&self, coords: &[u16]) -> Result<u8> {
+        self.volume.get_u8(coords)
+    }
+
+    fn get_i8(&self, coords: &[u16]) -> Result<i8> {
+        self.volume.get_i8(coords)
+    }
+
+    fn get_u16(&self, coords: &[u16]) -> Result<u16> {
+        self.volume.get_u16(coords)
+    }
+
+    fn get_i16(&self, coords: &[u16]) -> Result<i16> {
+        self.volume.get_i16(coords)
+    }
+
+    fn get_u32(&self, coords: &[u16]) -> Result<u32> {
+        self.volume.get_u32(coords)
+    }
+
+    fn get_i32(&self, coords: &[u16]) -> Result<i32> {
+        self.volume.get_i32(coords)
+    }
+
+    fn get_u64(&self, coords: &[u16]) -> Result<u64> {
+        self.volume.get_u64(coords)
+    }
+
+    fn get_i64(&self, coords: &[u16]) -> Result<i64> {
+        self.volume.get_i64(coords)
+    }
+
+    fn collect_slice(&self, axis: u16, index: u16) -> Result<InMemNiftiVolume> {
+        self.volume.collect_slice(axis, index)
+    }
 }
 
 #[cfg(test)]
@@ -461,6 +2014,978 @@ mod tests {
         assert_eq!(v, 39.);
     }
 
+    #[cfg(all(feature = "nalgebra_affine", feature = "ndarray_volumes"))]
+    #[test]
+    fn test_sample_plane() {
+        let values: Vec<f32> = (0..24)
+            .map(|x| (x % 4 + 10 * (x / 4 % 3) + 100 * (x / 12)) as f32)
+            .collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 4, 3, 2, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: values.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+            endianness: Endianness::native(),
+        };
+        let header = NiftiHeader {
+            dim: [3, 4, 3, 2, 1, 1, 1, 1],
+            sform_code: 1,
+            srow_x: [2., 0., 0., -1.],
+            srow_y: [0., 2., 0., 0.],
+            srow_z: [0., 0., 2., 5.],
+            ..NiftiHeader::default()
+        };
+
+        // the axis-aligned plane at voxel z = 1 (world z = 7)
+        let plane = vol
+            .sample_plane(
+                &header,
+                [-1., 0., 7.],
+                [2., 0., 0.],
+                [0., 2., 0.],
+                [4, 3],
+                0.,
+            )
+            .unwrap();
+        let slice = (&vol).get_slice(2, 1).unwrap();
+        for ((i, j), &value) in plane.indexed_iter() {
+            assert_eq!(value, slice.get_f64(&[i as u16, j as u16]).unwrap());
+        }
+
+        // half a voxel along x and z, going outside of the volume
+        let plane = vol
+            .sample_plane(
+                &header,
+                [0., 0., 6.],
+                [2., 0., 0.],
+                [0., 2., 0.],
+                [4, 1],
+                f64::NAN,
+            )
+            .unwrap();
+        assert_eq!(plane[[0, 0]], 50.5);
+        assert_eq!(plane[[2, 0]], 52.5);
+        assert!(plane[[3, 0]].is_nan());
+
+        let sample = |header: &NiftiHeader| {
+            vol.sample_plane(header, [0.; 3], [1., 0., 0.], [0., 1., 0.], [1, 1], 0.)
+        };
+        let singular = NiftiHeader {
+            srow_z: [0.; 4],
+            ..header.clone()
+        };
+        assert!(matches!(sample(&singular), Err(NiftiError::SingularAffine)));
+        let invalid_qform = NiftiHeader {
+            sform_code: 0,
+            qform_code: 1,
+            pixdim: [1., -1., 1., 1., 1., 1., 1., 1.],
+            ..header
+        };
+        assert!(matches!(
+            sample(&invalid_qform),
+            Err(NiftiError::InvalidVoxelSize(d)) if d == -1.
+        ));
+    }
+
+    #[cfg(feature = "ndarray_volumes")]
+    #[test]
+    fn test_into_ndarray_with() {
+        let values: [f32; 6] = [-1.5, 0., 100.7, 255., 300., f32::NAN];
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([2, 3, 2, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            endianness: Endianness::Little,
+        };
+
+        let a = vol
+            .clone()
+            .into_ndarray_with::<u8>(ConversionPolicy::Saturate)
+            .unwrap();
+        assert_eq!(
+            a.as_slice_memory_order().unwrap(),
+            &[0, 0, 100, 255, 255, 0]
+        );
+        assert_eq!(a, vol.clone().into_ndarray::<u8>().unwrap());
+
+        let a = vol
+            .clone()
+            .into_ndarray_with::<u8>(ConversionPolicy::Wrap)
+            .unwrap();
+        assert_eq!(
+            a.as_slice_memory_order().unwrap(),
+            &[255, 0, 100, 255, 44, 0]
+        );
+
+        match vol.clone().into_ndarray_with::<u8>(ConversionPolicy::Error) {
+            Err(NiftiError::ValueOutOfRange(v, coords)) => {
+                assert_eq!(v, -1.5);
+                assert_eq!(coords, vec![0, 0]);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        // the first value out of the range of `i8`
+        match vol.clone().into_ndarray_with::<i8>(ConversionPolicy::Error) {
+            Err(NiftiError::ValueOutOfRange(v, coords)) => {
+                assert_eq!(v, 255.);
+                assert_eq!(coords, vec![0, 1]);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // the policy does not apply to floating point types
+        let a = vol
+            .into_ndarray_with::<f32>(ConversionPolicy::Error)
+            .unwrap();
+        assert!(a[[2, 1]].is_nan());
+    }
+
+    #[test]
+    fn test_apply_mask() {
+        let values: [i16; 8] = [10, -20, 30, -40, 50, -60, 70, -80];
+        let mut vol = InMemNiftiVolume {
+            dim: Dim::new([2, 4, 2, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Int16,
+            scl_slope: 2.,
+            scl_inter: 1.,
+            raw_data: values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+        // the second half (last column) is zero
+        let mask = InMemNiftiVolume {
+            dim: vol.dim,
+            datatype: NiftiType::Uint8,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: vec![1, 1, 1, 1, 0, 0, 0, 0],
+            endianness: Endianness::native(),
+        };
+        vol.apply_mask(&mask, -1.).unwrap();
+        assert_eq!(
+            vol.iter_f64().unwrap().collect::<Vec<_>>(),
+            vec![21., -39., 61., -79., -1., -1., -1., -1.]
+        );
+        // stored as the raw value -1 (`-1 * 2 + 1`)
+        assert_eq!(&vol.raw_data()[8..10], &(-1i16).to_be_bytes());
+
+        let small = mask.crop(&[0, 0], &[2, 2]).unwrap();
+        assert!(matches!(
+            vol.apply_mask(&small, 0.),
+            Err(NiftiError::DimMismatch(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_as_bytes_for_gpu() {
+        let values: [u16; 6] = [1, 2, 300, 4, 5, 600];
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([2, 3, 2, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint16,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+        let (dim, datatype, data) = vol.as_bytes_for_gpu();
+        assert_eq!(dim, vec![3, 2]);
+        assert_eq!(datatype, NiftiType::Uint16);
+        assert_eq!(data.len(), 6 * 2);
+        let expected: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        assert_eq!(&data[..], &expected[..]);
+
+        // no copy is made if the data is already native
+        let vol = InMemNiftiVolume {
+            raw_data: expected.clone(),
+            endianness: Endianness::native(),
+            ..vol
+        };
+        let (_, _, data) = vol.as_bytes_for_gpu();
+        assert!(matches!(data, Cow::Borrowed(_)));
+        assert_eq!(&data[..], &expected[..]);
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = InMemNiftiVolume {
+            dim: Dim::new([2, 4, 2, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 2.,
+            scl_inter: 0.,
+            raw_data: (0..8).collect(),
+            endianness: Endianness::Little,
+        };
+        let d = diff(&a, &a).unwrap();
+        assert_eq!(
+            d,
+            VolumeDiff {
+                max_abs: 0.,
+                mean_abs: 0.,
+                nb_different: 0
+            }
+        );
+
+        // the same values, perturbed in two voxels
+        let mut values: Vec<f32> = (0..8).map(|v| v as f32 * 2.).collect();
+        values[1] += 0.5;
+        values[6] -= 3.;
+        let b = InMemNiftiVolume {
+            dim: a.dim,
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+        let d = diff(&a, &b).unwrap();
+        assert_eq!(d.max_abs, 3.);
+        assert_eq!(d.mean_abs, 3.5 / 8.);
+        assert_eq!(d.nb_different, 2);
+
+        let c = a.crop(&[0, 0], &[2, 2]).unwrap();
+        assert!(matches!(
+            diff(&a, &c),
+            Err(NiftiError::DimMismatch(l, r)) if l == [4, 2] && r == [2, 2]
+        ));
+    }
+
+    #[test]
+    fn test_collect_nested_slice() {
+        let data: Vec<u8> = (0..64).map(|x| x * 2).collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 4, 4, 4, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 1.,
+            scl_inter: -5.,
+            raw_data: data,
+            endianness: Endianness::Little,
+        };
+
+        let slice = (&vol).get_slice(2, 1).unwrap();
+        let nested = (&slice).get_slice(0, 3).unwrap();
+        assert_eq!(nested.dim(), &[4]);
+        assert_eq!(nested.get_f32(&[1]).unwrap(), 41.);
+
+        // generic path, through the slice views
+        let collected = slice.collect_slice(0, 3).unwrap();
+        assert_eq!(collected.dim(), &[4]);
+        assert_eq!(collected.data_type(), NiftiType::Float64);
+        for i in 0..4 {
+            assert_eq!(
+                collected.get_f64(&[i]).unwrap(),
+                nested.get_f64(&[i]).unwrap()
+            );
+        }
+
+        // byte path, retaining the data type and scaling
+        let owned = vol
+            .collect_slice(2, 1)
+            .unwrap()
+            .collect_slice(0, 3)
+            .unwrap();
+        assert_eq!(owned.dim(), &[4]);
+        assert_eq!(owned.data_type(), NiftiType::Uint8);
+        assert!(owned.values_eq(&collected));
+
+        assert!(nested.collect_slice(0, 0).is_err());
+        assert!(vol.collect_slice(3, 0).is_err());
+        assert!(vol.collect_slice(0, 4).is_err());
+    }
+
+    #[test]
+    fn test_iter_f64_blocks() {
+        // more values than are decoded at once
+        let values: Vec<i16> = (0..10_000).map(|v| (v * 7 % 1000) as i16 - 500).collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([2, 100, 100, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Int16,
+            scl_slope: 0.5,
+            scl_inter: 2.,
+            raw_data: values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+
+        let mut iter = vol.iter_f64().unwrap();
+        assert_eq!(iter.len(), 10_000);
+        let _ = iter.nth(VOXELS_PER_BLOCK);
+        assert_eq!(iter.len(), 10_000 - VOXELS_PER_BLOCK - 1);
+        let got: Vec<f64> = vol.iter_f64().unwrap().collect();
+        let expected: Vec<f64> = values.iter().map(|&v| f64::from(v) * 0.5 + 2.).collect();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_iter_f64_sum() {
+        let data: Vec<u8> = (0..8).collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 2, 2, 2, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 2.,
+            scl_inter: 1.,
+            raw_data: data,
+            endianness: Endianness::Little,
+        };
+
+        assert_eq!(vol.iter_f64().unwrap().len(), 8);
+        let mut sum = 0.;
+        for v in &vol {
+            sum += v;
+        }
+        // sum(2 * x + 1) for x in 0..8
+        assert_eq!(sum, 64.);
+    }
+
+    #[test]
+    fn test_get_f64_large_i64() {
+        let values: [i64; 2] = [(1 << 53) - 1, -(1 << 53) + 3];
+        let raw_data = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([1, 2, 0, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Int64,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::Big,
+        };
+
+        assert_eq!(vol.get_f64(&[0]).unwrap(), 9_007_199_254_740_991.);
+        assert_eq!(vol.get_f64(&[1]).unwrap(), -9_007_199_254_740_989.);
+        assert_eq!(
+            vol.iter_f64().unwrap().collect::<Vec<_>>(),
+            vec![9_007_199_254_740_991., -9_007_199_254_740_989.]
+        );
+    }
+
+    #[cfg(feature = "ndarray_volumes")]
+    #[test]
+    fn test_into_ndarray_fast_path() {
+        let values: Vec<i16> = (0..60).map(|x| x * 37 - 1000).collect();
+        for &(slope, inter) in &[(0., 0.), (1., 0.), (2., -3.)] {
+            let vol = InMemNiftiVolume {
+                dim: Dim::new([3, 5, 4, 3, 0, 0, 0, 0]).unwrap(),
+                datatype: NiftiType::Int16,
+                scl_slope: slope,
+                scl_inter: inter,
+                raw_data: values.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+                endianness: Endianness::native(),
+            };
+            let swapped = InMemNiftiVolume {
+                raw_data: values
+                    .iter()
+                    .flat_map(|v| v.swap_bytes().to_ne_bytes())
+                    .collect(),
+                endianness: Endianness::native().to_opposite(),
+                ..vol.clone()
+            };
+
+            // same type, native byte order
+            let fast = vol.clone().into_ndarray::<i16>().unwrap();
+            // same type, swapped byte order
+            let slow = swapped.clone().into_ndarray::<i16>().unwrap();
+            assert_eq!(fast, slow);
+            // casting to another type
+            let cast = vol.into_ndarray::<i32>().unwrap();
+            assert_eq!(cast, slow.mapv(i32::from));
+            for (i, v) in fast.t().iter().enumerate() {
+                assert_eq!(*v, swapped.get_i16(&idx_of(i, &[5, 4, 3])).unwrap());
+            }
+        }
+    }
+
+    #[cfg(feature = "ndarray_volumes")]
+    fn idx_of(mut i: usize, dim: &[u16]) -> Vec<u16> {
+        dim.iter()
+            .map(|&d| {
+                let c = i % usize::from(d);
+                i /= usize::from(d);
+                c as u16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_from_raw_data_with_endianness() {
+        let header = NiftiHeader {
+            dim: [2, 2, 2, 1, 1, 1, 1, 1],
+            datatype: NiftiType::Int32 as i16,
+            bitpix: 32,
+            endianness: Endianness::Big,
+            ..NiftiHeader::default()
+        };
+        let values = [1i32, -2, 300_000, i32::MIN];
+        let raw_data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+        let vol = InMemNiftiVolume::from_raw_data_with_endianness(
+            &header,
+            raw_data.clone(),
+            Endianness::native(),
+        )
+        .unwrap();
+        assert_eq!(vol.endianness(), Endianness::native());
+        assert_eq!(vol.get_i32(&[0, 0]).unwrap(), 1);
+        assert_eq!(vol.get_i32(&[1, 0]).unwrap(), -2);
+        assert_eq!(vol.get_i32(&[0, 1]).unwrap(), 300_000);
+        assert_eq!(vol.get_i32(&[1, 1]).unwrap(), i32::MIN);
+
+        assert!(matches!(
+            InMemNiftiVolume::from_raw_data_with_endianness(
+                &header,
+                raw_data[1..].to_vec(),
+                Endianness::native()
+            ),
+            Err(NiftiError::IncompatibleLength(15, 16))
+        ));
+    }
+
+    #[test]
+    fn test_split_last_axis() {
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([4, 2, 2, 2, 3, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint16,
+            scl_slope: 1.,
+            scl_inter: 0.5,
+            raw_data: (0..24u16).flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+
+        let volumes = vol.split_last_axis().unwrap();
+        assert_eq!(volumes.len(), 3);
+        for (t, v) in volumes.iter().enumerate() {
+            assert_eq!(v.dim(), &[2, 2, 2]);
+            assert_eq!(v.data_type(), NiftiType::Uint16);
+            for k in 0..2 {
+                for j in 0..2 {
+                    for i in 0..2 {
+                        assert_eq!(
+                            v.get_f32(&[i, j, k]).unwrap(),
+                            vol.get_f32(&[i, j, k, t as u16]).unwrap()
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(volumes[2].get_f32(&[1, 1, 1]).unwrap(), 23.5);
+
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([1, 4, 0, 0, 0, 0, 0, 0]).unwrap(),
+            raw_data: vec![0; 8],
+            ..vol
+        };
+        assert!(matches!(
+            vol.split_last_axis(),
+            Err(NiftiError::IncorrectVolumeDimensionality(2, 1))
+        ));
+    }
+
+    #[test]
+    fn test_concat_volumes() {
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([4, 2, 2, 2, 3, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint16,
+            scl_slope: 1.,
+            scl_inter: 0.5,
+            raw_data: (0..24u16).flat_map(|v| v.to_be_bytes()).collect(),
+            endianness: Endianness::Big,
+        };
+        let volumes = vol.split_last_axis().unwrap();
+        let joined = concat_volumes(&volumes).unwrap();
+        assert_eq!(joined.dim(), &[2, 2, 2, 3]);
+        assert_eq!(joined.raw_data(), vol.raw_data());
+        assert_eq!(joined.scl_inter(), 0.5);
+        assert_eq!(joined.endianness(), Endianness::Big);
+
+        assert!(matches!(concat_volumes(&[]), Err(NiftiError::NoVolumeData)));
+        assert!(matches!(
+            concat_volumes(std::slice::from_ref(&vol)),
+            Err(NiftiError::IncorrectVolumeDimensionality(3, 4))
+        ));
+        let small = volumes[0].crop(&[0, 0, 0], &[2, 2, 1]).unwrap();
+        assert!(matches!(
+            concat_volumes(&[volumes[0].clone(), small]),
+            Err(NiftiError::DimMismatch(_, _))
+        ));
+        let mut other = volumes.clone();
+        other[2].endianness = Endianness::Little;
+        assert!(matches!(
+            concat_volumes(&other),
+            Err(NiftiError::VolumeMismatch(2, "byte order"))
+        ));
+        let mut other = volumes.clone();
+        other[1].datatype = NiftiType::Int16;
+        assert!(matches!(
+            concat_volumes(&other),
+            Err(NiftiError::VolumeMismatch(1, "data type"))
+        ));
+        let mut other = volumes.clone();
+        other[1].scl_slope = 2.;
+        assert!(matches!(
+            concat_volumes(&other),
+            Err(NiftiError::VolumeMismatch(1, "scaling"))
+        ));
+        let many = vec![volumes[0].clone(); usize::from(u16::MAX) + 1];
+        assert!(matches!(
+            concat_volumes(&many),
+            Err(NiftiError::BadVolumeSize)
+        ));
+    }
+
+    #[test]
+    fn test_enumerate_f64() {
+        let mut raw_data = vec![0u8; 24];
+        raw_data[1] = 3;
+        raw_data[10] = 7;
+        raw_data[23] = 1;
+        let vol = InMemNiftiVolume::from_raw_fields(
+            [3, 2, 3, 4, 0, 0, 0, 0],
+            NiftiType::Uint8,
+            2.,
+            0.,
+            raw_data,
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!(vol.enumerate_f64().unwrap().count(), 24);
+        let foreground: Vec<(Vec<u64>, f64)> = vol
+            .enumerate_f64()
+            .unwrap()
+            .filter(|(_, v)| *v != 0.)
+            .collect();
+        assert_eq!(
+            foreground,
+            vec![
+                (vec![1, 0, 0], 6.),
+                (vec![0, 2, 1], 14.),
+                (vec![1, 2, 3], 2.),
+            ]
+        );
+        for (coords, value) in foreground {
+            let coords: Vec<u16> = coords.iter().map(|&c| c as u16).collect();
+            assert_eq!(vol.get_f64(&coords).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_pad() {
+        let vol = InMemNiftiVolume::from_raw_fields(
+            [3, 2, 2, 2, 0, 0, 0, 0],
+            NiftiType::Int16,
+            2.,
+            1.,
+            (1..=8i16).flat_map(|v| v.to_be_bytes()).collect(),
+            Endianness::Big,
+        )
+        .unwrap();
+
+        for (center, offset) in [(true, 1), (false, 0)] {
+            let padded = vol.pad(&[4, 4, 4], -1., center).unwrap();
+            assert_eq!(padded.dim(), &[4, 4, 4]);
+            assert_eq!(padded.data_type(), NiftiType::Int16);
+            assert_eq!(padded.scl_slope(), 2.);
+            assert_eq!(padded.endianness(), Endianness::Big);
+            for x in 0..4u16 {
+                for y in 0..4u16 {
+                    for z in 0..4u16 {
+                        let coords = [x, y, z];
+                        let inner: Option<Vec<u16>> = coords
+                            .iter()
+                            .map(|&c| c.checked_sub(offset).filter(|&c| c < 2))
+                            .collect();
+                        let expected = match inner {
+                            Some(c) => vol.get_f64(&c).unwrap(),
+                            None => -1.,
+                        };
+                        assert_eq!(padded.get_f64(&coords).unwrap(), expected);
+                    }
+                }
+            }
+        }
+
+        // the raw fill value is rounded for integer types
+        let padded = vol.pad(&[2, 3, 2], 4., false).unwrap();
+        assert_eq!(padded.get_f64(&[1, 2, 1]).unwrap(), 5.);
+
+        assert!(matches!(
+            vol.pad(&[4, 1, 4], 0., true),
+            Err(NiftiError::AxisOutOfBounds(1))
+        ));
+        assert!(matches!(
+            vol.pad(&[4, 4], 0., true),
+            Err(NiftiError::IncorrectVolumeDimensionality(3, 2))
+        ));
+
+        assert!(matches!(
+            vol.pad(&[65537, 2, 2], 0., false),
+            Err(NiftiError::BadVolumeSize)
+        ));
+    }
+
+    #[test]
+    fn test_rgb_to_luminance() {
+        let rgb = InMemNiftiVolume::from_raw_fields(
+            [2, 2, 2, 0, 0, 0, 0, 0],
+            NiftiType::Rgb24,
+            1.,
+            0.,
+            vec![0, 0, 0, 255, 0, 0, 0, 255, 0, 100, 100, 100],
+            Endianness::Big,
+        )
+        .unwrap();
+        let luminance = rgb.rgb_to_luminance().unwrap();
+        assert_eq!(luminance.data_type(), NiftiType::Float32);
+        assert_eq!(luminance.dim(), &[2, 2]);
+        let values: Vec<f64> = luminance.iter_f64().unwrap().collect();
+        let expected = [0., 0.299 * 255., 0.587 * 255., 100.];
+        for (v, e) in values.iter().zip(&expected) {
+            assert!((v - e).abs() < 1e-4, "{} != {}", v, e);
+        }
+
+        // the alpha channel is ignored
+        let rgba = InMemNiftiVolume::from_raw_fields(
+            [1, 2, 0, 0, 0, 0, 0, 0],
+            NiftiType::Rgba32,
+            1.,
+            0.,
+            vec![0, 0, 255, 0, 0, 0, 255, 255],
+            Endianness::Little,
+        )
+        .unwrap();
+        let values: Vec<f64> = rgba
+            .rgb_to_luminance()
+            .unwrap()
+            .iter_f64()
+            .unwrap()
+            .collect();
+        assert!((values[0] - 0.114 * 255.).abs() < 1e-4);
+        assert!((values[1] - 0.114 * 255.).abs() < 1e-4);
+
+        let err = luminance.rgb_to_luminance().unwrap_err();
+        assert!(matches!(
+            err,
+            NiftiError::InvalidTypeConversion(NiftiType::Float32, _)
+        ));
+    }
+
+    #[test]
+    fn test_values_eq() {
+        let values: Vec<i16> = (0..24).map(|x| x * 100 - 1000).collect();
+        let bytes = |f: fn(i16) -> [u8; 2]| values.iter().flat_map(|&v| f(v)).collect();
+        let raw_dim = [3, 2, 3, 4, 0, 0, 0, 0];
+        let le = InMemNiftiVolume::from_raw_fields(
+            raw_dim,
+            NiftiType::Int16,
+            1.,
+            0.,
+            bytes(i16::to_le_bytes),
+            Endianness::Little,
+        )
+        .unwrap();
+        let be = InMemNiftiVolume::from_raw_fields(
+            raw_dim,
+            NiftiType::Int16,
+            1.,
+            0.,
+            bytes(i16::to_be_bytes),
+            Endianness::Big,
+        )
+        .unwrap();
+        assert_ne!(le, be);
+        assert!(le.values_eq(&be));
+        assert!(be.values_eq(&le));
+
+        // same values, stored with a different data type and scaling
+        let halves: Vec<u8> = values
+            .iter()
+            .flat_map(|&v| (v as f32 * 2.).to_be_bytes())
+            .collect();
+        let scaled = InMemNiftiVolume::from_raw_fields(
+            raw_dim,
+            NiftiType::Float32,
+            0.5,
+            0.,
+            halves,
+            Endianness::Big,
+        )
+        .unwrap();
+        assert!(le.values_eq(&scaled));
+
+        let mut other = le.clone();
+        other.scl_inter = 1.;
+        assert!(!le.values_eq(&other));
+        let reshaped = InMemNiftiVolume::from_raw_fields(
+            [3, 4, 3, 2, 0, 0, 0, 0],
+            NiftiType::Int16,
+            1.,
+            0.,
+            bytes(i16::to_le_bytes),
+            Endianness::Little,
+        )
+        .unwrap();
+        assert!(!le.values_eq(&reshaped));
+
+        // complex values are compared component-wise
+        let complex = |e: Endianness| {
+            let raw_data = values
+                .iter()
+                .flat_map(|&v| match e {
+                    Endianness::Little => (v as f32).to_le_bytes(),
+                    Endianness::Big => (v as f32).to_be_bytes(),
+                })
+                .collect();
+            InMemNiftiVolume::from_raw_fields(
+                [2, 3, 4, 0, 0, 0, 0, 0],
+                NiftiType::Complex64,
+                1.,
+                0.,
+                raw_data,
+                e,
+            )
+            .unwrap()
+        };
+        assert!(complex(Endianness::Little).values_eq(&complex(Endianness::Big)));
+        assert!(!complex(Endianness::Little).values_eq(&le));
+    }
+
+    #[test]
+    fn test_crop() {
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 4, 4, 4, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 2.,
+            scl_inter: 0.,
+            raw_data: (0..64).collect(),
+            endianness: Endianness::Little,
+        };
+
+        let cropped = vol.crop(&[1, 1, 1], &[3, 3, 3]).unwrap();
+        assert_eq!(cropped.dim(), &[2, 2, 2]);
+        assert_eq!(cropped.raw_data(), &[21, 22, 25, 26, 37, 38, 41, 42]);
+        for k in 0..2 {
+            for j in 0..2 {
+                for i in 0..2 {
+                    assert_eq!(
+                        cropped.get_f32(&[i, j, k]).unwrap(),
+                        vol.get_f32(&[i + 1, j + 1, k + 1]).unwrap()
+                    );
+                }
+            }
+        }
+
+        let cropped = vol.crop(&[0, 3, 0], &[4, 4, 2]).unwrap();
+        assert_eq!(cropped.dim(), &[4, 1, 2]);
+        assert_eq!(cropped.raw_data(), &[12, 13, 14, 15, 28, 29, 30, 31]);
+
+        assert!(matches!(
+            vol.crop(&[0, 0], &[2, 2]),
+            Err(NiftiError::IncorrectVolumeDimensionality(3, 2))
+        ));
+        assert!(matches!(
+            vol.crop(&[0, 0, 0], &[2, 5, 2]),
+            Err(NiftiError::AxisOutOfBounds(1))
+        ));
+        assert!(matches!(
+            vol.crop(&[0, 0, 2], &[2, 2, 2]),
+            Err(NiftiError::AxisOutOfBounds(2))
+        ));
+    }
+
+    #[test]
+    fn test_crop_shape() {
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 4, 4, 4, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 2.,
+            scl_inter: 0.,
+            raw_data: (0..64).collect(),
+            endianness: Endianness::Little,
+        };
+
+        let cropped = vol.crop_shape(&[1, 1, 1], &[2, 2, 2]).unwrap();
+        assert_eq!(cropped, vol.crop(&[1, 1, 1], &[3, 3, 3]).unwrap());
+
+        assert!(matches!(
+            vol.crop_shape(&[0, 0], &[2, 2, 2]),
+            Err(NiftiError::IncorrectVolumeDimensionality(3, 2))
+        ));
+        assert!(matches!(
+            vol.crop_shape(&[1, 1, 0], &[4, 2, 2]),
+            Err(NiftiError::OutOfBounds(c)) if c == [4, 2, 1]
+        ));
+        assert!(matches!(
+            vol.crop_shape(&[0, 0, 0], &[2, 0, 2]),
+            Err(NiftiError::AxisOutOfBounds(1))
+        ));
+    }
+
+    #[test]
+    fn test_per_slice_scaling() {
+        let mut vol = InMemNiftiVolume {
+            dim: Dim::new([3, 2, 2, 2, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Uint8,
+            scl_slope: 2.,
+            scl_inter: 0.,
+            raw_data: (0..8).collect(),
+            endianness: Endianness::Little,
+        };
+
+        assert!(matches!(
+            vol.apply_per_slice_scaling(&[1.], &[0.]),
+            Err(NiftiError::IncompatibleLength(1, 2))
+        ));
+        assert!(matches!(
+            vol.apply_per_slice_scaling(&[1., 1.], &[0.]),
+            Err(NiftiError::IncompatibleLength(1, 2))
+        ));
+
+        vol.apply_per_slice_scaling(&[0.5, 3.], &[1., -1.]).unwrap();
+        assert_eq!(vol.data_type(), NiftiType::Float32);
+        assert_eq!(vol.get_f32(&[0, 0, 0]).unwrap(), 1.);
+        assert_eq!(vol.get_f32(&[1, 1, 0]).unwrap(), 4.);
+        assert_eq!(vol.get_f32(&[0, 0, 1]).unwrap(), 23.);
+        assert_eq!(vol.get_f32(&[1, 1, 1]).unwrap(), 41.);
+    }
+
+    #[test]
+    fn test_get_64bit_exact() {
+        let raw_data = (i64::MAX - 1).to_le_bytes().to_vec();
+        let mut vol = InMemNiftiVolume {
+            dim: Dim::new([1, 1, 0, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Int64,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::Little,
+        };
+        assert_eq!(vol.get_i64(&[0]).unwrap(), i64::MAX - 1);
+        vol.scl_slope = 0.;
+        assert_eq!(vol.get_i64(&[0]).unwrap(), i64::MAX - 1);
+
+        vol.datatype = NiftiType::Uint64;
+        vol.raw_data = (u64::MAX - 1).to_le_bytes().to_vec();
+        assert_eq!(vol.get_u64(&[0]).unwrap(), u64::MAX - 1);
+
+        vol.datatype = NiftiType::Int32;
+        vol.scl_slope = 1.;
+        vol.raw_data = vec![0; 4];
+        vol.raw_data.copy_from_slice(&(i32::MAX - 1).to_le_bytes());
+        assert_eq!(vol.get_i32(&[0]).unwrap(), i32::MAX - 1);
+        vol.scl_inter = 1.;
+        assert_eq!(vol.get_i32(&[0]).unwrap(), i32::MAX);
+    }
+
+    #[test]
+    fn test_get_f64_float64_precision() {
+        let raw_data = 0.1f64.to_le_bytes().to_vec();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([1, 1, 0, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Float64,
+            scl_slope: 0.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::Little,
+        };
+        assert_eq!(vol.get_f64(&[0]).unwrap(), 0.1);
+    }
+
+    #[test]
+    fn test_iter_f64_unsupported() {
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([1, 2, 0, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Rgb24,
+            scl_slope: 0.,
+            scl_inter: 0.,
+            raw_data: vec![0; 6],
+            endianness: Endianness::Little,
+        };
+        assert!(matches!(
+            vol.iter_f64(),
+            Err(NiftiError::UnsupportedDataType(NiftiType::Rgb24))
+        ));
+    }
+
+    #[test]
+    fn test_cast_f32_to_i16() {
+        let values: Vec<f32> = (0..60).map(|x| (x as f32 - 20.) * 0.37).collect();
+        let raw_data = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([3, 3, 4, 5, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data,
+            endianness: Endianness::Little,
+        };
+
+        let (cast, slope, inter) = vol.cast_to(NiftiType::Int16).unwrap();
+        assert_eq!(cast.data_type(), NiftiType::Int16);
+        assert_eq!(cast.dim(), vol.dim());
+        assert_eq!(cast.raw_data().len(), 60 * 2);
+        assert!(slope > 0.);
+
+        for (original, recovered) in values.iter().zip(&cast) {
+            let error = (f64::from(*original) - recovered).abs();
+            assert!(
+                error <= f64::from(slope) * 0.5 + 1e-6,
+                "error {} too large (slope {}, inter {})",
+                error,
+                slope,
+                inter
+            );
+        }
+
+        let (cast, slope, inter) = vol.cast_to(NiftiType::Float64).unwrap();
+        assert_eq!((slope, inter), (1., 0.));
+        assert_eq!(cast.get_f64(&[1, 0, 0]).unwrap(), f64::from(values[1]));
+
+        assert!(vol.cast_to(NiftiType::Rgb24).is_err());
+    }
+
+    #[test]
+    fn test_cast_clamp_behavior() {
+        let values = [0., 1., 2., f32::INFINITY, f32::NAN, f32::NEG_INFINITY];
+        let vol = InMemNiftiVolume {
+            dim: Dim::new([2, 2, 3, 0, 0, 0, 0, 0]).unwrap(),
+            datatype: NiftiType::Float32,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            raw_data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            endianness: Endianness::Little,
+        };
+
+        // the scaling only depends on the finite values
+        // (1 is stored as 127, due to the rounding of the slope)
+        let (cast, slope, inter) = vol.cast_to(NiftiType::Uint8).unwrap();
+        assert_eq!((slope, inter), (2. / 255., 0.));
+        assert_eq!(cast.raw_data(), &[0, 127, 255, 255, 0, 0]);
+        let (saturated, _, _) = vol
+            .cast_to_with(NiftiType::Uint8, ClampBehavior::Saturate)
+            .unwrap();
+        assert_eq!(saturated, cast);
+
+        assert!(matches!(
+            vol.cast_to_with(NiftiType::Uint8, ClampBehavior::Error),
+            Err(NiftiError::ValueOutOfRange(v, coords))
+                if v == f64::INFINITY && coords == [1, 1]
+        ));
+
+        let (filled, _, _) = vol
+            .cast_to_with(NiftiType::Uint8, ClampBehavior::UseFill(1.))
+            .unwrap();
+        assert_eq!(filled.raw_data(), &[0, 127, 255, 127, 127, 127]);
+        let (filled, _, _) = vol
+            .cast_to_with(NiftiType::Uint8, ClampBehavior::UseFill(-10.))
+            .unwrap();
+        assert_eq!(filled.raw_data(), &[0, 127, 255, 0, 0, 0]);
+
+        // floating point targets keep non-finite values
+        let (cast, _, _) = vol
+            .cast_to_with(NiftiType::Float64, ClampBehavior::Error)
+            .unwrap();
+        assert_eq!(cast.get_f64(&[1, 1]).unwrap(), f64::INFINITY);
+        assert!(cast.get_f64(&[0, 2]).unwrap().is_nan());
+    }
+
     #[test]
     fn test_false_4d() {
         let (w, h, d) = (5, 5, 5);
@@ -474,10 +2999,10 @@ mod tests {
         let mut volume = InMemNiftiVolume::from_raw_data(&header, raw_data).unwrap();
         assert_eq!(header.dim[0], 4);
         assert_eq!(volume.dimensionality(), 4);
-        if header.dim[header.dim[0] as usize] == 1 {
-            header.dim[0] -= 1;
-            volume = InMemNiftiVolume::from_raw_data(&header, volume.into_raw_data()).unwrap();
-        }
+        let rank = header.effective_dimensionality().unwrap();
+        assert_eq!(rank, 3);
+        header.dim[0] = rank as u16;
+        volume = InMemNiftiVolume::from_raw_data(&header, volume.into_raw_data()).unwrap();
         assert_eq!(volume.dimensionality(), 3);
 
         #[cfg(feature = "ndarray_volumes")]
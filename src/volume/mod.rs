@@ -16,6 +16,7 @@ mod util;
 use crate::error::{NiftiError, Result};
 use crate::header::NiftiHeader;
 use crate::typedef::NiftiType;
+use byteordered::Endianness;
 use std::io::Read;
 
 #[cfg(feature = "ndarray_volumes")]
@@ -57,7 +58,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     fn get_f64(&self, coords: &[u16]) -> Result<f64>;
 
     /// Fetch a single voxel's value in the given voxel index coordinates
@@ -70,7 +71,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_f32(&self, coords: &[u16]) -> Result<f32> {
         self.get_f64(coords).map(|v| v as f32)
@@ -86,7 +87,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_u8(&self, coords: &[u16]) -> Result<u8> {
         self.get_f64(coords).map(|v| v as u8)
@@ -102,7 +103,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_i8(&self, coords: &[u16]) -> Result<i8> {
         self.get_f64(coords).map(|v| v as i8)
@@ -118,7 +119,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_u16(&self, coords: &[u16]) -> Result<u16> {
         self.get_f64(coords).map(|v| v as u16)
@@ -134,7 +135,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_i16(&self, coords: &[u16]) -> Result<i16> {
         self.get_f64(coords).map(|v| v as i16)
@@ -150,7 +151,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_u32(&self, coords: &[u16]) -> Result<u32> {
         self.get_f64(coords).map(|v| v as u32)
@@ -166,7 +167,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_i32(&self, coords: &[u16]) -> Result<i32> {
         self.get_f64(coords).map(|v| v as i32)
@@ -182,7 +183,7 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_u64(&self, coords: &[u16]) -> Result<u64> {
         self.get_f64(coords).map(|v| v as u64)
@@ -198,11 +199,78 @@ pub trait RandomAccessNiftiVolume: NiftiVolume {
     /// # Errors
     ///
     /// - `NiftiError::OutOfBounds` if the given coordinates surpass this
-    /// volume's boundaries.
+    ///   volume's boundaries.
     #[inline]
     fn get_i64(&self, coords: &[u16]) -> Result<i64> {
         self.get_f64(coords).map(|v| v as i64)
     }
+
+    /// Extract the slice at position `index` of the given `axis` into an
+    /// owned in-memory volume of N-1 dimensions.
+    ///
+    /// The default implementation reads every voxel of the slice with
+    /// `get_f64`, so the resulting volume holds the scaled values as
+    /// `Float64`. In-memory volumes copy the raw bytes instead, retaining
+    /// their data type and scaling.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::AxisOutOfBounds` if `axis` is not an axis of the volume.
+    /// - `NiftiError::OutOfBounds` if `index` surpasses the axis' length.
+    /// - `NiftiError::IncorrectVolumeDimensionality` if the volume is 1D,
+    ///   since NIfTI volumes cannot have less than one dimension.
+    fn collect_slice(&self, axis: u16, index: u16) -> Result<InMemNiftiVolume> {
+        let slice_dim = slice_dim(self.dim(), axis, index)?;
+        if slice_dim.is_empty() {
+            return Err(NiftiError::IncorrectVolumeDimensionality(2, 1));
+        }
+
+        let len: usize = slice_dim.iter().map(|&d| usize::from(d)).product();
+        let mut raw_data = Vec::with_capacity(len * 8);
+        let mut coords = vec![0; slice_dim.len()];
+        for _ in 0..len {
+            let mut full_coords = coords.clone();
+            full_coords.insert(usize::from(axis), index);
+            raw_data.extend_from_slice(&self.get_f64(&full_coords)?.to_ne_bytes());
+            for (c, &d) in coords.iter_mut().zip(&slice_dim) {
+                *c += 1;
+                if *c < d {
+                    break;
+                }
+                *c = 0;
+            }
+        }
+
+        let mut raw_dim = [1; 8];
+        raw_dim[0] = slice_dim.len() as u16;
+        raw_dim[1..=slice_dim.len()].copy_from_slice(&slice_dim);
+        InMemNiftiVolume::from_raw_fields(
+            raw_dim,
+            NiftiType::Float64,
+            1.,
+            0.,
+            raw_data,
+            Endianness::native(),
+        )
+    }
+}
+
+/// Compute the shape of the slice at position `index` of the given `axis`.
+pub(crate) fn slice_dim(dim: &[u16], axis: u16, index: u16) -> Result<Vec<u16>> {
+    let mut coords: Vec<_> = dim.into();
+    if let Some(d) = coords.get(axis as usize) {
+        if *d <= index {
+            return Err(NiftiError::OutOfBounds(util::hot_vector(
+                dim.len(),
+                axis as usize,
+                index,
+            )));
+        }
+    } else {
+        return Err(NiftiError::AxisOutOfBounds(axis));
+    }
+    let _ = coords.remove(axis as usize);
+    Ok(coords)
 }
 
 /// Interface for a volume that can be sliced at an arbitrary position.
@@ -233,6 +301,24 @@ pub trait FromSource<R>: FromSourceOptions + Sized {
     fn from_reader(reader: R, header: &NiftiHeader, options: Self::Options) -> Result<Self>
     where
         R: Read;
+
+    /// Read a NIfTI volume from a stream of raw voxel data, as in `from_reader`,
+    /// while reporting the number of bytes read so far and the total number of
+    /// bytes expected to the given callback.
+    ///
+    /// The default implementation does not report any progress.
+    fn from_reader_with_progress(
+        reader: R,
+        header: &NiftiHeader,
+        options: Self::Options,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        let _ = progress;
+        Self::from_reader(reader, header, options)
+    }
 }
 
 /// A view over a single slice of another volume.
@@ -254,26 +340,12 @@ where
     type Slice = SliceView<&'a T>;
 
     fn get_slice(&self, axis: u16, index: u16) -> Result<Self::Slice> {
-        let mut coords: Vec<_> = self.dim().into();
-        if let Some(d) = coords.get(axis as usize) {
-            if *d <= index {
-                return Err(NiftiError::OutOfBounds(util::hot_vector(
-                    self.dimensionality(),
-                    axis as usize,
-                    index,
-                )));
-            }
-        } else {
-            return Err(NiftiError::AxisOutOfBounds(axis));
-        }
-
-        let _ = coords.remove(axis as usize);
-
+        let dim = slice_dim(self.dim(), axis, index)?;
         Ok(SliceView {
             volume: *self,
             axis,
             index,
-            dim: coords,
+            dim,
         })
     }
 }
@@ -362,3 +434,68 @@ where
         self.volume.get_i64(&coords)
     }
 }
+
+impl<V> NiftiVolume for &SliceView<V>
+where
+    V: NiftiVolume,
+{
+    #[inline]
+    fn dim(&self) -> &[u16] {
+        (**self).dim()
+    }
+
+    #[inline]
+    fn dimensionality(&self) -> usize {
+        (**self).dimensionality()
+    }
+
+    #[inline]
+    fn data_type(&self) -> NiftiType {
+        (**self).data_type()
+    }
+}
+
+impl<V> RandomAccessNiftiVolume for &SliceView<V>
+where
+    V: RandomAccessNiftiVolume,
+{
+    fn get_f32(&self, coords: &[u16]) -> Result<f32> {
+        (**self).get_f32(coords)
+    }
+
+    fn get_f64(&self, coords: &[u16]) -> Result<f64> {
+        (**self).get_f64(coords)
+    }
+
+    fn get_u8(&self, coords: &[u16]) -> Result<u8> {
+        (**self).get_u8(coords)
+    }
+
+    fn get_i8(&self, coords: &[u16]) -> Result<i8> {
+        (**self).get_i8(coords)
+    }
+
+    fn get_u16(&self, coords: &[u16]) -> Result<u16> {
+        (**self).get_u16(coords)
+    }
+
+    fn get_i16(&self, coords: &[u16]) -> Result<i16> {
+        (**self).get_i16(coords)
+    }
+
+    fn get_u32(&self, coords: &[u16]) -> Result<u32> {
+        (**self).get_u32(coords)
+    }
+
+    fn get_i32(&self, coords: &[u16]) -> Result<i32> {
+        (**self).get_i32(coords)
+    }
+
+    fn get_u64(&self, coords: &[u16]) -> Result<u64> {
+        (**self).get_u64(coords)
+    }
+
+    fn get_i64(&self, coords: &[u16]) -> Result<i64> {
+        (**self).get_i64(coords)
+    }
+}
@@ -104,6 +104,10 @@ pub struct Dim(Idx);
 impl Dim {
     /// Validate and create a new volume shape.
     ///
+    /// The rank in `dim[0]` must be between 1 and 7, the maximum
+    /// dimensionality supported by NIfTI-1, and each of the first `dim[0]`
+    /// dimensions after it must be positive.
+    ///
     /// # Example
     ///
     /// ```
@@ -290,8 +294,7 @@ mod tests {
                 Idx::from_slice(&[0, 3]).unwrap(),
                 Idx::from_slice(&[1, 3]).unwrap(),
                 Idx::from_slice(&[2, 3]).unwrap(),
-            ]
-            .into_iter(),
+            ],
         )
         .enumerate()
         {
@@ -58,13 +58,14 @@
 use super::inmem::InMemNiftiVolume;
 use super::shape::{Dim, Idx};
 use super::{FromSource, FromSourceOptions, NiftiVolume};
-use crate::error::Result;
+use crate::error::{NiftiError, Result};
 use crate::header::NiftiHeader;
 use crate::typedef::NiftiType;
-use crate::util::nb_bytes_for_dim_datatype;
+use crate::util::{nb_bytes_for_dim_datatype, nb_values_for_dims};
 use byteordered::Endianness;
+use std::convert::TryFrom;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 /// A NIfTI-1 volume instance that is read slice by slice from a byte stream.
@@ -121,11 +122,20 @@ where
     /// extensions).
     ///
     /// The slice rank defines how many dimensions each slice should have.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::AxisOutOfBounds` if the slice rank is zero or not lower
+    ///   than the rank of the volume. In particular, 1D volumes cannot be
+    ///   streamed.
     pub fn from_reader_rank(source: R, header: &NiftiHeader, slice_rank: u16) -> Result<Self> {
         let dim = Dim::new(header.dim)?; // check dim consistency
         let datatype = header.data_type()?;
+        if slice_rank == 0 || usize::from(slice_rank) >= dim.rank() {
+            return Err(NiftiError::AxisOutOfBounds(slice_rank));
+        }
         let slice_dim = calculate_slice_dims(&dim, slice_rank);
-        let slices_left = calculate_total_slices(&dim, slice_rank);
+        let slices_left = calculate_total_slices(&dim, slice_rank)?;
         Ok(StreamedNiftiVolume {
             source,
             dim,
@@ -190,6 +200,34 @@ where
         )
     }
 
+    /// Skip the next `n` slices, reading and discarding their bytes from the
+    /// data source. Skipped slices count as read. No seeking is done, see
+    /// [`skip_slices_seek`](#method.skip_slices_seek) for seekable sources.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::Io` with `io::ErrorKind::UnexpectedEof` if fewer than
+    ///   `n` slices are left, in which case nothing is skipped.
+    pub fn skip_slices(&mut self, n: usize) -> Result<()> {
+        if n > self.slices_left {
+            return Err(NiftiError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("cannot skip {} slices, {} left", n, self.slices_left),
+            )));
+        }
+        let slice_len = nb_bytes_for_dim_datatype(self.slice_dim(), self.datatype)
+            .ok_or(NiftiError::BadVolumeSize)?;
+        let len = (slice_len as u64).saturating_mul(n as u64);
+        let skipped = io::copy(&mut (&mut self.source).take(len), &mut io::sink())?;
+        if skipped != len {
+            return Err(NiftiError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        self.slices_read += n;
+        self.slices_left -= n;
+        Ok(())
+    }
+
     /// Fetch the next slice while reusing a raw data buffer. This is the
     /// streaming iterator equivalent of `Iterator::next`. Once the output
     /// volume has been used, the method [`into_raw_data`] can be used to
@@ -203,6 +241,42 @@ where
         Some(self.read_slice_inline(buffer))
     }
 
+    /// Read all remaining slices into a single in-memory volume.
+    ///
+    /// If no slice was read yet, the resulting volume has the dimensions of
+    /// the full volume. Otherwise, it contains the remaining slices stacked
+    /// along one extra axis, with dimensions `[slice_dim.., slices_left]`.
+    pub fn collect_in_memory(mut self) -> Result<InMemNiftiVolume> {
+        let mut raw_dim = *self.dim.raw();
+        if self.slices_read > 0 {
+            let rank = self.slice_dim.rank();
+            raw_dim = *self.slice_dim.raw();
+            raw_dim[0] = rank as u16 + 1;
+            raw_dim[rank + 1] =
+                u16::try_from(self.slices_left).map_err(|_| NiftiError::BadVolumeSize)?;
+        }
+        let slice_len = nb_bytes_for_dim_datatype(self.slice_dim(), self.datatype)
+            .ok_or(NiftiError::BadVolumeSize)?;
+        let len = slice_len
+            .checked_mul(self.slices_left)
+            .ok_or(NiftiError::BadVolumeSize)?;
+        let mut raw_data = Vec::new();
+        raw_data
+            .try_reserve_exact(len)
+            .map_err(|e| NiftiError::ReserveVolume(len, e))?;
+        raw_data.resize(len, 0);
+        self.source.read_exact(&mut raw_data)?;
+
+        InMemNiftiVolume::from_raw_fields(
+            raw_dim,
+            self.datatype,
+            self.scl_slope,
+            self.scl_inter,
+            raw_data,
+            self.endianness,
+        )
+    }
+
     /// Adapt the streamed volume to produce slice indices alongside the produced
     /// slices.
     ///
@@ -226,6 +300,62 @@ where
     }
 }
 
+impl<R> StreamedNiftiVolume<R>
+where
+    R: Read + Seek,
+{
+    /// Read the slice at the given index, in any order, by seeking to its
+    /// position in the data source. The slice position is relative to the
+    /// current one, so the source's cursor must not have been moved
+    /// externally. Streaming then resumes from the slice after `index`.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::OutOfBounds` if `index` is not lower than the total
+    ///   number of slices.
+    pub fn get_slice_at(&mut self, index: usize) -> Result<InMemNiftiVolume> {
+        let total = self.slices_read + self.slices_left;
+        if index >= total {
+            return Err(NiftiError::OutOfBounds(vec![
+                u16::try_from(index).unwrap_or(u16::MAX)
+            ]));
+        }
+        let slice_len = nb_bytes_for_dim_datatype(self.slice_dim(), self.datatype)
+            .ok_or(NiftiError::BadVolumeSize)? as i64;
+        let offset = (index as i64 - self.slices_read as i64) * slice_len;
+        let _ = self.source.seek(SeekFrom::Current(offset))?;
+        self.slices_read = index;
+        self.slices_left = total - index;
+        self.read_slice()
+    }
+
+    /// Skip the next `n` slices, as in [`skip_slices`](#method.skip_slices),
+    /// by seeking past them in the data source instead of reading them. The
+    /// source's cursor must not have been moved externally.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::Io` with `io::ErrorKind::UnexpectedEof` if fewer than
+    ///   `n` slices are left, in which case nothing is skipped.
+    pub fn skip_slices_seek(&mut self, n: usize) -> Result<()> {
+        if n > self.slices_left {
+            return Err(NiftiError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("cannot skip {} slices, {} left", n, self.slices_left),
+            )));
+        }
+        let slice_len = nb_bytes_for_dim_datatype(self.slice_dim(), self.datatype)
+            .ok_or(NiftiError::BadVolumeSize)?;
+        let offset =
+            i64::try_from(slice_len as u64 * n as u64).map_err(|_| NiftiError::BadVolumeSize)?;
+        let _ = self.source.seek(SeekFrom::Current(offset))?;
+
+        self.slices_read += n;
+        self.slices_left -= n;
+        Ok(())
+    }
+}
+
 impl<R> FromSourceOptions for StreamedNiftiVolume<R> {
     type Options = Option<u16>;
 }
@@ -243,7 +373,7 @@ where
     }
 }
 
-impl<'a, R> NiftiVolume for &'a StreamedNiftiVolume<R> {
+impl<R> NiftiVolume for &StreamedNiftiVolume<R> {
     fn dim(&self) -> &[u16] {
         (**self).dim()
     }
@@ -300,10 +430,10 @@ fn calculate_slice_dims(dim: &Dim, slice_rank: u16) -> Dim {
     Dim::new(raw_dim).unwrap()
 }
 
-fn calculate_total_slices(dim: &Dim, slice_rank: u16) -> usize {
+fn calculate_total_slices(dim: &Dim, slice_rank: u16) -> Result<usize> {
     assert!(usize::from(slice_rank) < dim.rank());
     let (_, r) = dim.split(slice_rank);
-    r.element_count()
+    nb_values_for_dims(r.as_ref()).ok_or(NiftiError::BadVolumeSize)
 }
 
 #[cfg(test)]
@@ -314,6 +444,7 @@ mod tests {
     use crate::typedef::NiftiType;
     use crate::NiftiHeader;
     use byteordered::Endianness;
+    use std::io::Cursor;
 
     #[test]
     fn test_streamed_base() {
@@ -484,6 +615,69 @@ mod tests {
         assert!(volume.next().is_none());
     }
 
+    #[test]
+    fn test_streamed_get_slice_at() {
+        let volume_data = [1, 3, 5, 7, 9, 11, 13, 15, 17, 19, 21, 23];
+        let header = NiftiHeader {
+            dim: [3, 2, 3, 2, 0, 0, 0, 0],
+            datatype: NiftiType::Uint8 as i16,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            endianness: Endianness::native(),
+            ..NiftiHeader::default()
+        };
+
+        let mut volume =
+            StreamedNiftiVolume::from_reader(Cursor::new(&volume_data[..]), &header).unwrap();
+
+        let slice = volume.get_slice_at(1).unwrap();
+        assert_eq!(slice.raw_data(), &[13, 15, 17, 19, 21, 23]);
+        assert_eq!(volume.slices_left(), 0);
+        let slice = volume.get_slice_at(0).unwrap();
+        assert_eq!(slice.dim(), &[2, 3]);
+        assert_eq!(slice.raw_data(), &[1, 3, 5, 7, 9, 11]);
+        assert_eq!(slice.get_u8(&[1, 2]).unwrap(), 11);
+        assert_eq!(volume.slices_read(), 1);
+        assert_eq!(volume.slices_left(), 1);
+
+        assert!(volume.get_slice_at(2).is_err());
+
+        // streaming resumes after the last accessed slice
+        let slice = volume.next().unwrap().unwrap();
+        assert_eq!(slice.raw_data(), &[13, 15, 17, 19, 21, 23]);
+        assert!(volume.next().is_none());
+    }
+
+    #[test]
+    fn test_streamed_skip_slices_seek() {
+        let volume_data: Vec<u8> = (0..18).collect();
+        let header = NiftiHeader {
+            dim: [3, 2, 3, 3, 0, 0, 0, 0],
+            datatype: NiftiType::Uint8 as i16,
+            scl_slope: 1.,
+            scl_inter: 0.,
+            endianness: Endianness::native(),
+            ..NiftiHeader::default()
+        };
+
+        let mut volume =
+            StreamedNiftiVolume::from_reader(Cursor::new(&volume_data[..]), &header).unwrap();
+        volume.skip_slices_seek(1).unwrap();
+        assert_eq!(volume.slices_read(), 1);
+        assert_eq!(volume.slices_left(), 2);
+        assert_eq!(volume.source.position(), 6);
+        let slice = volume.read_slice().unwrap();
+        assert_eq!(slice.raw_data(), &[6, 7, 8, 9, 10, 11]);
+
+        assert!(matches!(
+            volume.skip_slices_seek(2),
+            Err(crate::NiftiError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+        assert_eq!(volume.source.position(), 12);
+        volume.skip_slices_seek(1).unwrap();
+        assert!(volume.next().is_none());
+    }
+
     #[test]
     fn test_streamed_lesser_rank() {
         let volume_data = &[
@@ -1,22 +1,29 @@
 //! Utility functions to write nifti images.
 
-use std::fs::File;
+use std::borrow::Cow;
+use std::fs::{self, File};
 use std::io::{BufWriter, Write};
+#[cfg(feature = "ndarray_volumes")]
 use std::mem::size_of;
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "ndarray_volumes")]
 use bytemuck::{cast_slice, Pod};
-use byteordered::{ByteOrdered, Endian};
+use byteordered::{ByteOrdered, Endian, Endianness};
 use flate2::write::GzEncoder;
 use flate2::Compression;
+#[cfg(feature = "ndarray_volumes")]
 use ndarray::{ArrayBase, Axis, Data, Dimension, RemoveAxis};
 
 use crate::{
-    header::{MAGIC_CODE_NI1, MAGIC_CODE_NIP1},
-    util::{adapt_bytes, is_gz_file, is_hdr_file},
+    header::SIZEOF_HDR_NI1,
+    util::{adapt_bytes, is_gz_file, is_hdr_file, nb_bytes_for_data, nb_values_for_dims},
     volume::shape::Dim,
-    DataElement, ExtensionSequence, NiftiHeader, NiftiType, Result,
+    ExtensionSequence, InMemNiftiVolume, NiftiError, NiftiHeader, NiftiType, NiftiVolume, Result,
+    SliceOrder, XForm,
 };
+#[cfg(feature = "ndarray_volumes")]
+use crate::{util::f32_to_f16_bits, DataElement, Extender, Extension};
 
 #[derive(Debug, Clone, PartialEq)]
 enum HeaderReference<'a> {
@@ -58,9 +65,34 @@ pub struct WriterOptions<'a> {
     /// The header file will only be compressed if the caller specifically asked for a path ending
     /// with "hdr.gz". Otherwise, only the volume will be compressed (if requested).
     force_header_compression: bool,
+    /// The compression level of the header file, when writing a compressed header file separately
+    /// from the volume. Defaults to the level of the volume.
+    header_compression: Option<Compression>,
 
     /// Optional ExtensionSequence
     extension_sequence: Option<ExtensionSequence>,
+
+    /// If given, the data type to write, regardless of the element type of the data.
+    datatype: Option<NiftiType>,
+
+    /// Whether the reference header should be written as is, for an exact round-trip.
+    exact_roundtrip: bool,
+
+    /// If given, the slice timing fields to write: `slice_code`, `slice_start`, `slice_end` and
+    /// `slice_duration`.
+    slice_timing: Option<(SliceOrder, i16, i16, f32)>,
+
+    /// If given, the `sform_code` and `qform_code` to write.
+    xform_codes: Option<(XForm, XForm)>,
+
+    /// If given, the `aux_file` to write.
+    aux_file: Option<String>,
+
+    /// If given, the `intent_name` to write.
+    intent_name: Option<String>,
+
+    /// Whether to write to temporary files, renamed into place on success.
+    atomic: bool,
 }
 
 impl<'a> WriterOptions<'a> {
@@ -85,7 +117,15 @@ impl<'a> WriterOptions<'a> {
             write_header_file,
             compression,
             force_header_compression: write_header_file && compression.is_some(),
+            header_compression: None,
             extension_sequence: None,
+            datatype: None,
+            exact_roundtrip: false,
+            slice_timing: None,
+            xform_codes: None,
+            aux_file: None,
+            intent_name: None,
+            atomic: false,
         }
     }
 
@@ -96,9 +136,9 @@ impl<'a> WriterOptions<'a> {
     }
 
     /// Loads a reference header from a Nifti file.
-    pub fn reference_file<P: 'a>(mut self, path: &'a P) -> Self
+    pub fn reference_file<P>(mut self, path: &'a P) -> Self
     where
-        P: AsRef<Path>,
+        P: 'a + AsRef<Path>,
     {
         self.header_reference = HeaderReference::FromFile(path.as_ref());
         self
@@ -132,13 +172,115 @@ impl<'a> WriterOptions<'a> {
         self
     }
 
+    /// Sets the compression level to use for the header file, independently of the volume's.
+    ///
+    /// This only applies when writing a compressed file pair (header and volume in distinct
+    /// files), and makes the header file compressed as well ("hdr.gz"), even if it wasn't
+    /// requested in the output path. A single "nii.gz" file is always compressed as a whole,
+    /// using the level given by [`compression_level`](#method.compression_level).
+    pub fn header_compression(mut self, compression_level: Compression) -> Self {
+        self.header_compression = Some(compression_level);
+        self.force_header_compression = true;
+        self
+    }
+
     /// Sets an extension sequence for the writer
+    ///
+    /// The sequence is checked with
+    /// [`ExtensionSequence::validate`](../extension/struct.ExtensionSequence.html#method.validate)
+    /// before writing, so that no corrupt file is produced.
     pub fn with_extensions(mut self, extension_sequence: ExtensionSequence) -> Self {
         self.extension_sequence = Some(extension_sequence);
         self
     }
 
+    /// Sets the slice timing information of the output header: the slice
+    /// acquisition order (`slice_code`), the first and last slices acquired
+    /// (`slice_start` and `slice_end`), and the time taken to acquire each
+    /// slice (`slice_duration`).
+    ///
+    /// These fields override those of the reference header, unless
+    /// [`exact_roundtrip`](#method.exact_roundtrip) is enabled. The slice axis
+    /// itself is given by the `dim_info` field of the reference header.
+    pub fn slice_timing(mut self, code: SliceOrder, start: i16, end: i16, duration: f32) -> Self {
+        self.slice_timing = Some((code, start, end, duration));
+        self
+    }
+
+    /// Sets the codes of the 'sform' and 'qform' transformations of the output
+    /// header (`sform_code` and `qform_code`).
+    ///
+    /// By default, the codes are those of the reference header. Without a
+    /// reference header, `sform_code` is `XForm::AlignedAnat` (2) and
+    /// `qform_code` is `XForm::ScannerAnat` (1). These codes override those of
+    /// the reference header, unless [`exact_roundtrip`](#method.exact_roundtrip)
+    /// is enabled.
+    pub fn xform_codes(mut self, sform: XForm, qform: XForm) -> Self {
+        self.xform_codes = Some((sform, qform));
+        self
+    }
+
+    /// Sets the name of an auxiliary file of the output header (`aux_file`),
+    /// such as a label table.
+    ///
+    /// The name overrides that of the reference header, unless
+    /// [`exact_roundtrip`](#method.exact_roundtrip) is enabled. Writing fails
+    /// with `IncorrectFieldLength` if it is longer than 24 bytes.
+    pub fn aux_file(mut self, aux_file: &str) -> Self {
+        self.aux_file = Some(aux_file.to_owned());
+        self
+    }
+
+    /// Sets the name of the intent of the output header (`intent_name`).
+    ///
+    /// The name overrides that of the reference header, unless
+    /// [`exact_roundtrip`](#method.exact_roundtrip) is enabled. Writing fails
+    /// with `IncorrectFieldLength` if it is longer than 16 bytes.
+    pub fn intent_name(mut self, intent_name: &str) -> Self {
+        self.intent_name = Some(intent_name.to_owned());
+        self
+    }
+
+    /// Whether to write each output file to a temporary sibling file first,
+    /// renaming it into place only once everything was written. This way,
+    /// a failed write does not leave partial files behind.
+    ///
+    /// In a file pair, the volume file is renamed before the header file.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Sets the data type of the output volume.
+    ///
+    /// By default, the data type is derived from the element type of the array
+    /// (`A::DATA_TYPE`). When set, this data type is written instead, and `bitpix`
+    /// is updated accordingly. The element type must have the same size as a
+    /// voxel of the given data type, otherwise writing fails with
+    /// `UnsupportedDataType`. For instance, arrays of `[u8; 3]` can be written
+    /// as `NiftiType::Rgb24` with [`write_nifti_pod`](#method.write_nifti_pod).
+    pub fn datatype(mut self, datatype: NiftiType) -> Self {
+        self.datatype = Some(datatype);
+        self
+    }
+
+    /// Writes the given header exactly as it is, for a byte-for-byte round-trip
+    /// of a previously read NIfTI file.
+    ///
+    /// Unlike [`reference_header`](#method.reference_header), no field of the header
+    /// is recomputed: `scl_slope`, `scl_inter`, `vox_offset`, `magic` and the byte
+    /// order are all preserved, and the space between the extensions and
+    /// `vox_offset` is filled with zeros. The dimensions and data type of the data
+    /// to write must match the ones in the header. This is best combined with
+    /// [`write_raw_volume`](#method.write_raw_volume) and the original extensions.
+    pub fn exact_roundtrip(mut self, header: &'a NiftiHeader) -> Self {
+        self.header_reference = HeaderReference::FromHeader(header);
+        self.exact_roundtrip = true;
+        self
+    }
+
     /// Write a nifti file (.nii or .nii.gz) from an NdArray of any Pod type
+    #[cfg(feature = "ndarray_volumes")]
     pub fn write_nifti_with_type<A, S, D>(
         &self,
         data: &ArrayBase<S, D>,
@@ -151,75 +293,134 @@ impl<'a> WriterOptions<'a> {
     {
         // do a basic size check
         if size_of::<A>() != datatype.size_of() {
-            return Err(crate::error::NiftiError::UnsupportedDataType(datatype));
+            return Err(NiftiError::UnsupportedDataType(datatype));
         }
 
-        let header = self.prepare_header(data, datatype)?;
-        let (header_path, data_path) = self.output_paths();
+        let header = self.prepare_header(*Dim::from_slice(data.shape())?.raw(), datatype)?;
 
         // Need the transpose for fortran ordering used in nifti file format.
         let data = data.t();
 
-        let header_file = File::create(header_path)?;
-        if header.vox_offset > 0.0 {
-            if let Some(compression_level) = self.compression {
-                let mut writer = ByteOrdered::runtime(
-                    GzEncoder::new(header_file, compression_level),
-                    header.endianness,
-                );
-                write_header(writer.as_mut(), &header)?;
-                write_extensions(writer.as_mut(), self.extension_sequence.as_ref())?;
-                write_data::<_, A, _, _, _, _>(writer.as_mut(), data)?;
-                let _ = writer.into_inner().finish()?;
-            } else {
-                let mut writer =
-                    ByteOrdered::runtime(BufWriter::new(header_file), header.endianness);
-                write_header(writer.as_mut(), &header)?;
-                write_extensions(writer.as_mut(), self.extension_sequence.as_ref())?;
-                write_data::<_, A, _, _, _, _>(writer, data)?;
-            }
-        } else {
-            let data_file = File::create(data_path)?;
-            if let Some(compression_level) = self.compression {
-                let mut writer = ByteOrdered::runtime(
-                    GzEncoder::new(header_file, compression_level),
-                    header.endianness,
-                );
-                write_header(writer.as_mut(), &header)?;
-                write_extensions(writer.as_mut(), self.extension_sequence.as_ref())?;
-                let _ = writer.into_inner().finish()?;
-
-                let mut writer = ByteOrdered::runtime(
-                    GzEncoder::new(data_file, compression_level),
-                    header.endianness,
-                );
-                write_data::<_, A, _, _, _, _>(writer.as_mut(), data)?;
-                let _ = writer.into_inner().finish()?;
+        self.write_files(|header_path, data_path| {
+            let header_file = File::create(header_path)?;
+            if header.vox_offset > 0.0 {
+                if let Some(compression_level) = self.compression {
+                    let mut writer = ByteOrdered::runtime(
+                        GzEncoder::new(header_file, compression_level),
+                        header.endianness,
+                    );
+                    write_header(writer.as_mut(), &header)?;
+                    write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), &header)?;
+                    write_data(writer.as_mut(), data, datatype)?;
+                    let _ = writer.into_inner().finish()?;
+                } else {
+                    let mut writer =
+                        ByteOrdered::runtime(BufWriter::new(header_file), header.endianness);
+                    write_header(writer.as_mut(), &header)?;
+                    write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), &header)?;
+                    write_data(writer.as_mut(), data, datatype)?;
+                    writer.into_inner().flush()?;
+                }
             } else {
-                let mut header_writer =
-                    ByteOrdered::runtime(BufWriter::new(header_file), header.endianness);
-                write_header(header_writer.as_mut(), &header)?;
-                write_extensions(header_writer.as_mut(), self.extension_sequence.as_ref())?;
-                let data_writer =
-                    ByteOrdered::runtime(BufWriter::new(data_file), header.endianness);
-                write_data::<_, A, _, _, _, _>(data_writer, data)?;
+                self.write_separate_header(header_file, &header)?;
+
+                let data_file = File::create(data_path)?;
+                if let Some(compression_level) = self.compression {
+                    let mut writer = ByteOrdered::runtime(
+                        GzEncoder::new(data_file, compression_level),
+                        header.endianness,
+                    );
+                    write_data(writer.as_mut(), data, datatype)?;
+                    let _ = writer.into_inner().finish()?;
+                } else {
+                    let mut data_writer =
+                        ByteOrdered::runtime(BufWriter::new(data_file), header.endianness);
+                    write_data(data_writer.as_mut(), data, datatype)?;
+                    data_writer.into_inner().flush()?;
+                }
             }
-        }
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Write a nifti file (.nii or .nii.gz) from an NdArray of DataElements
+    #[cfg(feature = "ndarray_volumes")]
     pub fn write_nifti<A, S, D>(&self, data: &ArrayBase<S, D>) -> Result<()>
     where
         S: Data<Elem = A>,
         A: DataElement + Pod,
         D: Dimension + RemoveAxis,
     {
-        self.write_nifti_with_type(data, A::DATA_TYPE)
+        self.write_nifti_with_type(data, self.datatype.unwrap_or(A::DATA_TYPE))
+    }
+
+    /// Write a GZip compressed nifti file (.nii.gz) from an NdArray of
+    /// DataElements into the given sink, such as an upload stream to object
+    /// storage. The sink is flushed once the compressed stream is finished.
+    ///
+    /// The output always has the single file layout: the path of these options
+    /// is not used, and neither is [`write_header_file`](#method.write_header_file).
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn write_nifti_to_compressed<W, A, S, D>(
+        &self,
+        writer: W,
+        data: &ArrayBase<S, D>,
+        compression: Compression,
+    ) -> Result<()>
+    where
+        W: Write,
+        S: Data<Elem = A>,
+        A: DataElement + Pod,
+        D: Dimension + RemoveAxis,
+    {
+        let datatype = self.datatype.unwrap_or(A::DATA_TYPE);
+        if size_of::<A>() != datatype.size_of() {
+            return Err(NiftiError::UnsupportedDataType(datatype));
+        }
+
+        let dim = *Dim::from_slice(data.shape())?.raw();
+        let header = if self.write_header_file {
+            WriterOptions {
+                write_header_file: false,
+                ..self.clone()
+            }
+            .prepare_header(dim, datatype)?
+        } else {
+            self.prepare_header(dim, datatype)?
+        };
+
+        let mut writer =
+            ByteOrdered::runtime(GzEncoder::new(writer, compression), header.endianness);
+        write_header(writer.as_mut(), &header)?;
+        write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), &header)?;
+        write_data(writer.as_mut(), data.t(), datatype)?;
+        writer.into_inner().finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Write a nifti file (.nii or .nii.gz) from an NdArray of any Pod type,
+    /// using the data type set with [`datatype`](#method.datatype).
+    ///
+    /// The elements are written as raw voxels, so this also passes through
+    /// data types which have no `DataElement` counterpart. For instance,
+    /// arrays of `[u8; 32]` can be written as `NiftiType::Complex256`, with
+    /// each 128-bit component swapped if the byte order is not native.
+    ///
+    /// Fails with `MissingDataType` if no data type was set.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn write_nifti_pod<A, S, D>(&self, data: &ArrayBase<S, D>) -> Result<()>
+    where
+        S: Data<Elem = A>,
+        A: Pod,
+        D: Dimension + RemoveAxis,
+    {
+        let datatype = self.datatype.ok_or(NiftiError::MissingDataType)?;
+        self.write_nifti_with_type(data, datatype)
     }
 
     /// Write a RGB nifti file (.nii or .nii.gz).
+    #[cfg(feature = "ndarray_volumes")]
     pub fn write_rgb_nifti<S, D>(&self, data: &ArrayBase<S, D>) -> Result<()>
     where
         S: Data<Elem = [u8; 3]>,
@@ -228,38 +429,213 @@ impl<'a> WriterOptions<'a> {
         self.write_nifti_with_type(data, NiftiType::Rgb24)
     }
 
-    fn prepare_header<T, D>(
-        &self,
-        data: &ArrayBase<T, D>,
-        datatype: NiftiType,
-    ) -> Result<NiftiHeader>
+    /// Write a binary mask nifti file (.nii or .nii.gz), as `Uint8` voxels
+    /// where `true` is written as 1 and `false` as 0.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn write_nifti_mask<S, D>(&self, data: &ArrayBase<S, D>) -> Result<()>
+    where
+        S: Data<Elem = bool>,
+        D: Dimension + RemoveAxis,
+    {
+        self.write_nifti_with_type(&data.mapv(u8::from), NiftiType::Uint8)
+    }
+
+    /// Write a nifti file (.nii or .nii.gz) of half precision floats.
+    ///
+    /// NIfTI-1 has no half precision data type, so the values are rounded to
+    /// IEEE 754 binary16 and their bits are stored as `Int16`, along with an
+    /// extension marking the volume as such (see
+    /// [`ECODE_FLOAT16`](../extension/constant.ECODE_FLOAT16.html)). Use
+    /// [`ReaderOptions::interpret_f16_extension`](../object/struct.ReaderOptions.html#method.interpret_f16_extension)
+    /// to read the values back as `f32`. Other tools will see plain integers.
+    #[cfg(feature = "ndarray_volumes")]
+    pub fn write_nifti_f16<S, D>(&self, data: &ArrayBase<S, D>) -> Result<()>
     where
-        T: Data,
-        D: Dimension,
+        S: Data<Elem = f32>,
+        D: Dimension + RemoveAxis,
     {
-        let mut vox_offset: f32 = 352.0;
+        let data = data.mapv(|v| f32_to_f16_bits(v) as i16);
+        let mut extensions: Vec<Extension> = self
+            .extension_sequence
+            .iter()
+            .flat_map(|seq| seq.iter().cloned())
+            .collect();
+        if !extensions.iter().any(Extension::is_float16) {
+            extensions.push(Extension::float16());
+        }
+        let extender = self
+            .extension_sequence
+            .as_ref()
+            .map_or_else(|| Extender::from([1, 0, 0, 0]), |seq| seq.extender());
+        let options = self
+            .clone()
+            .with_extensions(ExtensionSequence::new(extender, extensions));
+        options.write_nifti_with_type(&data, NiftiType::Int16)
+    }
+
+    /// Write a nifti file (.nii or .nii.gz) from the raw data of an in-memory volume.
+    ///
+    /// The voxel data is written as is, without any conversion, and `scl_slope`
+    /// and `scl_inter` are taken from the volume. The output byte order is the
+    /// one of the volume, unless [`exact_roundtrip`](#method.exact_roundtrip) is
+    /// enabled, in which case the data is converted to the byte order of the
+    /// reference header if needed.
+    pub fn write_raw_volume(&self, volume: &InMemNiftiVolume) -> Result<()> {
+        let datatype = volume.data_type();
+        let mut header = self.prepare_header(*Dim::from_slice(volume.dim())?.raw(), datatype)?;
+        if !self.exact_roundtrip {
+            header.scl_slope = volume.scl_slope();
+            header.scl_inter = volume.scl_inter();
+            header.endianness = volume.endianness();
+        }
+        let data = if header.endianness == volume.endianness() {
+            Cow::Borrowed(volume.raw_data())
+        } else {
+            adapt_bytes(
+                volume.raw_data(),
+                datatype,
+                Endianness::native().to_opposite(),
+            )
+        };
+        self.write_header_and_data(&header, &data)
+    }
 
+    /// Write a nifti file (.nii or .nii.gz) from a header and the raw bytes of
+    /// its volume, without decoding them.
+    ///
+    /// The data must be in column major order and in the byte order of the
+    /// header. The header takes the place of the reference header, and is
+    /// written with its own dimensions, data type and scaling. As with the other
+    /// methods, the fields which depend on the output layout, such as
+    /// `vox_offset` and `magic`, are recomputed, and the other options of the
+    /// writer (extensions, slice timing, ...) are applied, unless
+    /// [`exact_roundtrip`](#method.exact_roundtrip) is enabled.
+    ///
+    /// # Errors
+    ///
+    /// - `NiftiError::IncompatibleLength` if the length of `raw_data` does not
+    ///   match the dimensions and data type of the header.
+    /// - `NiftiError::InvalidCode` if the header's data type is invalid.
+    pub fn write_raw(&self, header: &NiftiHeader, raw_data: &[u8]) -> Result<()> {
+        let nbytes = nb_bytes_for_data(header)?;
+        if raw_data.len() != nbytes {
+            return Err(NiftiError::IncompatibleLength(raw_data.len(), nbytes));
+        }
+        let datatype = header.data_type()?;
+        let options = WriterOptions {
+            header_reference: HeaderReference::FromHeader(header),
+            ..self.clone()
+        };
+        let mut output_header = options.prepare_header(header.dim, datatype)?;
+        if !self.exact_roundtrip {
+            output_header.scl_slope = header.scl_slope;
+            output_header.scl_inter = header.scl_inter;
+        }
+        self.write_header_and_data(&output_header, raw_data)
+    }
+
+    /// Write the header, extensions and raw data to the output files.
+    fn write_header_and_data(&self, header: &NiftiHeader, data: &[u8]) -> Result<()> {
+        self.write_files(|header_path, data_path| {
+            let header_file = File::create(header_path)?;
+            if header.vox_offset > 0.0 {
+                if let Some(compression_level) = self.compression {
+                    let mut writer = ByteOrdered::runtime(
+                        GzEncoder::new(header_file, compression_level),
+                        header.endianness,
+                    );
+                    write_header(writer.as_mut(), header)?;
+                    write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), header)?;
+                    writer.write_all(data)?;
+                    let _ = writer.into_inner().finish()?;
+                } else {
+                    let mut writer =
+                        ByteOrdered::runtime(BufWriter::new(header_file), header.endianness);
+                    write_header(writer.as_mut(), header)?;
+                    write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), header)?;
+                    writer.write_all(data)?;
+                    writer.into_inner().flush()?;
+                }
+            } else {
+                self.write_separate_header(header_file, header)?;
+
+                let data_file = File::create(data_path)?;
+                if let Some(compression_level) = self.compression {
+                    let mut writer = GzEncoder::new(data_file, compression_level);
+                    writer.write_all(data)?;
+                    let _ = writer.finish()?;
+                } else {
+                    let mut data_writer = BufWriter::new(data_file);
+                    data_writer.write_all(data)?;
+                    data_writer.flush()?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    fn prepare_header(&self, dim: [u16; 8], datatype: NiftiType) -> Result<NiftiHeader> {
         if let Some(extension_sequence) = self.extension_sequence.as_ref() {
-            vox_offset += extension_sequence.bytes_on_disk() as f32;
+            extension_sequence.validate()?;
+        }
+
+        if self.exact_roundtrip {
+            let header = self.header_reference.to_header()?;
+            if header.datatype != datatype as i16 {
+                return Err(NiftiError::UnsupportedDataType(datatype));
+            }
+            let dim = Dim::new(dim)?;
+            if header.dim()? != dim.as_ref() {
+                let got = nb_values_for_dims(dim.as_ref()).unwrap_or(usize::MAX);
+                let expected = nb_values_for_dims(header.dim()?).unwrap_or(usize::MAX);
+                return Err(NiftiError::IncompatibleLength(got, expected));
+            }
+            // only a NIfTI-1 layout is written, so the magic code must agree with it
+            let magic = header.canonical_magic();
+            return Ok(NiftiHeader { magic, ..header });
         }
 
         let mut header = NiftiHeader {
-            dim: *Dim::from_slice(data.shape())?.raw(),
-            sizeof_hdr: 348,
+            dim,
+            sizeof_hdr: SIZEOF_HDR_NI1,
             datatype: datatype as i16,
             bitpix: (datatype.size_of() * 8) as i16,
-            vox_offset,
             scl_inter: 0.0,
             scl_slope: 1.0,
-            magic: *MAGIC_CODE_NIP1,
             // All other fields are copied from the requested reference header
             ..self.header_reference.to_header()?
         };
 
+        header.vox_offset = match self.extension_sequence.as_ref() {
+            Some(extension_sequence) => header.compute_vox_offset(extension_sequence),
+            None => header.compute_vox_offset(&ExtensionSequence::default()),
+        } as f32;
+
+        if let Some((code, start, end, duration)) = self.slice_timing {
+            header.slice_code = code as u8;
+            header.slice_start = start;
+            header.slice_end = end;
+            header.slice_duration = duration;
+        }
+
+        if let Some((sform, qform)) = self.xform_codes {
+            header.sform_code = sform as i16;
+            header.qform_code = qform as i16;
+        }
+
+        if let Some(aux_file) = &self.aux_file {
+            header.set_aux_file(aux_file)?;
+        }
+
+        if let Some(intent_name) = &self.intent_name {
+            header.set_intent_name(intent_name)?;
+        }
+
         if self.write_header_file {
             header.vox_offset = 0.0;
-            header.magic = *MAGIC_CODE_NI1;
         }
+        header.magic = header.canonical_magic();
 
         // The only acceptable length is 80. If different, try to set it.
         header.validate_description()?;
@@ -267,6 +643,61 @@ impl<'a> WriterOptions<'a> {
         Ok(header)
     }
 
+    /// Write the header and extensions of a file pair, compressed only if the header file is
+    /// meant to be compressed (see `output_paths`).
+    fn write_separate_header(&self, header_file: File, header: &NiftiHeader) -> Result<()> {
+        let compression = match self.compression {
+            Some(level) if self.force_header_compression => {
+                Some(self.header_compression.unwrap_or(level))
+            }
+            _ => None,
+        };
+        if let Some(compression_level) = compression {
+            let mut writer = ByteOrdered::runtime(
+                GzEncoder::new(header_file, compression_level),
+                header.endianness,
+            );
+            write_header(writer.as_mut(), header)?;
+            write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), header)?;
+            let _ = writer.into_inner().finish()?;
+        } else {
+            let mut writer = ByteOrdered::runtime(BufWriter::new(header_file), header.endianness);
+            write_header(writer.as_mut(), header)?;
+            write_extensions(writer.as_mut(), self.extension_sequence.as_ref(), header)?;
+            writer.into_inner().flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the output files with `write`, given the header and data paths (which are the
+    /// same for a single file). If `atomic` is set, temporary paths are given instead, and the
+    /// files are renamed into place on success, or removed on failure.
+    fn write_files<F>(&self, write: F) -> Result<()>
+    where
+        F: FnOnce(&Path, &Path) -> Result<()>,
+    {
+        let (header_path, data_path) = self.output_paths();
+        if !self.atomic {
+            return write(&header_path, &data_path);
+        }
+
+        let tmp_header_path = temporary_path(&header_path);
+        let tmp_data_path = temporary_path(&data_path);
+        let result = write(&tmp_header_path, &tmp_data_path).and_then(|()| {
+            if data_path != header_path {
+                fs::rename(&tmp_data_path, &data_path)?;
+            }
+            fs::rename(&tmp_header_path, &header_path)?;
+            Ok(())
+        });
+        if result.is_err() {
+            // the files may not have been created, so errors are ignored
+            let _ = fs::remove_file(&tmp_data_path);
+            let _ = fs::remove_file(&tmp_header_path);
+        }
+        result
+    }
+
     /// Fix the header path extension in case a change in `write_header_file` or `compression`
     /// broke it.
     fn output_paths(&self) -> (PathBuf, PathBuf) {
@@ -287,38 +718,50 @@ impl<'a> WriterOptions<'a> {
     }
 }
 
+/// The path of the temporary sibling file to write before renaming it to `path`.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+/// Write the extender code and the extensions, followed by zeros up until the
+/// header's `vox_offset`, if there is any gap left.
 fn write_extensions<W, E>(
     mut writer: ByteOrdered<W, E>,
     extensions: Option<&ExtensionSequence>,
+    header: &NiftiHeader,
 ) -> Result<()>
 where
     W: Write,
     E: Endian,
 {
-    let extensions = match extensions {
-        Some(extensions) => extensions,
-        None => {
+    let mut written = 352;
+    match extensions {
+        Some(extensions) if !extensions.is_empty() => {
+            writer.write_all(extensions.extender().as_bytes())?;
+            for extension in extensions.iter() {
+                writer.write_i32(extension.size())?;
+                writer.write_i32(extension.code())?;
+                writer.write_all(extension.data())?;
+            }
+            written += extensions.bytes_on_disk();
+        }
+        _ => {
+            // Write an extender code of 4 zeros, which for NIFTI means that there are no extensions
             writer.write_u32(0)?;
-            return Ok(());
         }
-    };
-
-    if extensions.is_empty() {
-        // Write an extender code of 4 zeros, which for NIFTI means that there are no extensions
-        writer.write_u32(0)?;
-        return Ok(());
     }
 
-    writer.write_all(extensions.extender().as_bytes())?;
-    for extension in extensions.iter() {
-        writer.write_i32(extension.size())?;
-        writer.write_i32(extension.code())?;
-        writer.write_all(extension.data())?;
+    let vox_offset = header.vox_offset as usize;
+    if vox_offset > written {
+        writer.write_all(&vec![0; vox_offset - written])?;
     }
     Ok(())
 }
 
-fn write_header<W, E>(mut writer: ByteOrdered<W, E>, header: &NiftiHeader) -> Result<()>
+pub(crate) fn write_header<W, E>(mut writer: ByteOrdered<W, E>, header: &NiftiHeader) -> Result<()>
 where
     W: Write,
     E: Endian,
@@ -384,10 +827,15 @@ where
     Ok(())
 }
 
+#[cfg(feature = "ndarray_volumes")]
 /// Write the data in 'f' order.
 ///
 /// Like NiBabel, we iterate by "slice" to improve speed and use less memory.
-fn write_data<A, B, S, D, W, E>(mut writer: ByteOrdered<W, E>, data: ArrayBase<S, D>) -> Result<()>
+fn write_data<A, S, D, W, E>(
+    mut writer: ByteOrdered<W, E>,
+    data: ArrayBase<S, D>,
+    datatype: NiftiType,
+) -> Result<()>
 where
     S: Data<Elem = A>,
     A: Pod,
@@ -395,7 +843,13 @@ where
     W: Write,
     E: Endian + Copy,
 {
-    // We always write the image by iterating on the first axis, thus
+    // Data which was already in Fortran order is now contiguous in memory, in
+    // the order expected by the file, so it can be written all at once.
+    if data.is_standard_layout() {
+        return write_slice(writer.as_mut(), data.view(), datatype);
+    }
+
+    // Otherwise, we write the image by iterating on the first axis, thus
     //   3D (depth, height, width) => per axial slice
     //   4D (time, depth, height, width) => per volume
     // However, there's a problem when dim == (1, ...). We must iterate by the first non-one length
@@ -405,18 +859,20 @@ where
     if let Some(arr_data) = iter.next() {
         // Keep slice voxels in a separate array to ensure `C` ordering even after `into_shape`.
         let mut slice = arr_data.to_owned();
-        write_slice::<_, B, _, _, _, _>(writer.as_mut(), slice.view())?;
+        write_slice(writer.as_mut(), slice.view(), datatype)?;
         for arr_data in iter {
             slice.assign(&arr_data);
-            write_slice::<_, B, _, _, _, _>(writer.as_mut(), slice.view())?;
+            write_slice(writer.as_mut(), slice.view(), datatype)?;
         }
     }
     Ok(())
 }
 
-fn write_slice<A, B, S, D, W, E>(
+#[cfg(feature = "ndarray_volumes")]
+fn write_slice<A, S, D, W, E>(
     writer: ByteOrdered<&mut W, E>,
     data: ArrayBase<S, D>,
+    datatype: NiftiType,
 ) -> Result<()>
 where
     S: Data<Elem = A>,
@@ -430,7 +886,7 @@ where
     let slice = arr_data.as_slice().unwrap();
     let bytes = cast_slice(slice);
     let (writer, endianness) = writer.into_parts();
-    let bytes = adapt_bytes::<B, _>(bytes, endianness);
+    let bytes = adapt_bytes(bytes, datatype, endianness);
     writer.write_all(&bytes)?;
     Ok(())
 }
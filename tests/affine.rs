@@ -4,9 +4,10 @@ extern crate nalgebra;
 extern crate nifti;
 
 #[cfg(feature = "nalgebra_affine")]
+#[allow(clippy::field_reassign_with_default)]
 mod nalgebra_affine {
     use nalgebra::Vector4;
-    use nifti::{affine::Affine4, NiftiHeader};
+    use nifti::{affine::Affine4, NiftiError, NiftiHeader};
 
     #[test]
     fn affine() {
@@ -37,6 +38,29 @@ mod nalgebra_affine {
         assert_eq!(header.affine(), real_affine);
     }
 
+    #[test]
+    fn affines_qform_only() {
+        let mut header = NiftiHeader::default();
+        header.sform_code = 0;
+        header.qform_code = 1;
+        header.pixdim = [1.0, 2.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.0];
+        header.quatern_x = 10.0;
+
+        let (sform, qform) = header.affines::<f64>().unwrap();
+        assert_eq!(sform, None);
+        assert_eq!(qform, Some(header.qform_affine::<f64>()));
+        assert_eq!(qform.unwrap()[(0, 0)], 2.0);
+        assert_eq!(qform.unwrap()[(0, 3)], 10.0);
+
+        header.pixdim[0] = 0.0;
+        assert!(matches!(
+            header.affines::<f64>(),
+            Err(NiftiError::InvalidQfac(_))
+        ));
+        header.qform_code = 0;
+        assert_eq!(header.affines::<f64>().unwrap(), (None, None));
+    }
+
     #[test]
     #[rustfmt::skip]
     fn qform() {
@@ -116,4 +140,53 @@ mod nalgebra_affine {
         );
         assert_eq!(header.affine(), real_affine);
     }
+
+    #[test]
+    #[rustfmt::skip]
+    fn is_oblique() {
+        let mut header = NiftiHeader::default();
+        header.sform_code = 1;
+
+        // axis-aligned, with flipped and permuted axes
+        header.srow_x = [0.0, -2.0, 0.0, 90.0];
+        header.srow_y = [0.0, 0.0, 2.5, -126.0];
+        header.srow_z = [3.0, 0.0, 0.0, -72.0];
+        assert!(!header.is_oblique(1e-6).unwrap());
+
+        // rotated by 30 degrees around the z axis
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        header.srow_x = [2.0 * cos, -2.0 * sin, 0.0, 0.0];
+        header.srow_y = [2.0 * sin, 2.0 * cos, 0.0, 0.0];
+        header.srow_z = [0.0, 0.0, 2.0, 0.0];
+        assert!(header.is_oblique(1e-6).unwrap());
+        assert!(!header.is_oblique(0.5).unwrap());
+
+        // slightly off, within tolerance
+        header.srow_x = [2.4, -0.0008, -0.0411765, -114.766396];
+        header.srow_y = [0.1, 2.4995277, 0.0485984, -97.420204];
+        header.srow_z = [0.4, -0.0485, 2.4991884, -89.12282];
+        assert!(header.is_oblique(1e-6).unwrap());
+        assert!(!header.is_oblique(0.02).unwrap());
+    }
+
+    #[test]
+    fn is_oblique_invalid_qform() {
+        let mut header = NiftiHeader::default();
+        header.qform_code = 1;
+        header.pixdim[0] = 0.;
+        assert!(matches!(
+            header.is_oblique(1e-6),
+            Err(NiftiError::InvalidQfac(q)) if q == 0.
+        ));
+
+        header.pixdim[0] = 1.;
+        header.pixdim[1] = -1.;
+        assert!(matches!(
+            header.is_oblique(1e-6),
+            Err(NiftiError::InvalidVoxelSize(d)) if d == -1.
+        ));
+
+        header.pixdim[1] = 1.;
+        assert!(!header.is_oblique(1e-6).unwrap());
+    }
 }
@@ -2,8 +2,12 @@ extern crate nifti;
 #[macro_use]
 extern crate pretty_assertions;
 
-use nifti::{Endianness, Intent, NiftiHeader, NiftiType, SliceOrder, Unit, XForm};
+use nifti::{
+    Endianness, Extender, Extension, ExtensionSequence, Intent, NiftiError, NiftiHeader,
+    NiftiObject, NiftiType, ReaderOptions, SliceOrder, Unit, XForm,
+};
 use std::fs::File;
+use std::io::Cursor;
 
 mod util;
 
@@ -62,6 +66,22 @@ fn minimal_nii() {
     assert_eq!(header.sform().unwrap(), XForm::Unknown);
 }
 
+#[test]
+fn parse_from_slice_both_endiannesses() {
+    let bytes = std::fs::read("resources/minimal.nii").unwrap();
+    let header = NiftiHeader::parse_from_slice(&bytes[..348], Endianness::Big).unwrap();
+    assert_eq!(header, minimal_header_nii_gt());
+    assert!(NiftiHeader::parse_from_slice(&bytes[..348], Endianness::Little).is_err());
+
+    let bytes = std::fs::read("resources/rgb/3D.nii").unwrap();
+    let header = NiftiHeader::parse_from_slice(&bytes, Endianness::Little).unwrap();
+    assert_eq!(header, NiftiHeader::from_reader(&bytes[..]).unwrap());
+    assert_eq!(header.endianness, Endianness::Little);
+    assert!(NiftiHeader::parse_from_slice(&bytes, Endianness::Big).is_err());
+
+    assert!(NiftiHeader::parse_from_slice(&bytes[..100], Endianness::Little).is_err());
+}
+
 #[test]
 #[allow(non_snake_case)]
 fn avg152T1_LR_hdr_gz() {
@@ -195,3 +215,393 @@ fn zstat1_nii_gz() {
     assert_eq!(header.qform().unwrap(), XForm::ScannerAnat);
     assert_eq!(header.sform().unwrap(), XForm::Unknown);
 }
+
+#[test]
+fn zero_datatype() {
+    let mut header = NiftiHeader::default();
+    assert_eq!(header.datatype, 0);
+    assert!(matches!(header.data_type(), Err(NiftiError::NoDataType)));
+
+    header.datatype = 3;
+    assert!(matches!(
+        header.data_type(),
+        Err(NiftiError::InvalidCode("datatype", 3))
+    ));
+}
+
+#[test]
+fn new_3d_and_4d() {
+    let header = NiftiHeader::new_3d([64, 32, 10], NiftiType::Float32).unwrap();
+    assert_eq!(header.dim, [3, 64, 32, 10, 1, 1, 1, 1]);
+    assert_eq!(header.dimensionality().unwrap(), 3);
+    assert_eq!(header.dim().unwrap(), &[64, 32, 10]);
+    assert_eq!(header.data_type().unwrap(), NiftiType::Float32);
+    assert_eq!(header.bitpix, 32);
+    assert_eq!(header.pixdim, [1.; 8]);
+
+    let header = NiftiHeader::new_4d([5, 4, 3, 2], NiftiType::Rgb24).unwrap();
+    assert_eq!(header.dim, [4, 5, 4, 3, 2, 1, 1, 1]);
+    assert_eq!(header.dimensionality().unwrap(), 4);
+    assert_eq!(header.bitpix, 24);
+
+    assert!(matches!(
+        NiftiHeader::new_3d([64, 0, 10], NiftiType::Uint8),
+        Err(NiftiError::InconsistentDim(2, 0))
+    ));
+    assert!(matches!(
+        NiftiHeader::new_4d([1, 1, 1, 70_000], NiftiType::Uint8),
+        Err(NiftiError::BadVolumeSize)
+    ));
+}
+
+#[test]
+fn push_dim() {
+    let mut header = NiftiHeader::new_3d([64, 32, 10], NiftiType::Float32).unwrap();
+    header.push_dim(20).unwrap();
+    assert_eq!(header.dim, [4, 64, 32, 10, 20, 1, 1, 1]);
+    assert_eq!(header.dim().unwrap(), &[64, 32, 10, 20]);
+
+    assert!(matches!(
+        header.push_dim(0),
+        Err(NiftiError::InconsistentDim(5, 0))
+    ));
+    assert!(matches!(
+        header.push_dim(70_000),
+        Err(NiftiError::BadVolumeSize)
+    ));
+    assert_eq!(header.dimensionality().unwrap(), 4);
+
+    for _ in 0..3 {
+        header.push_dim(1).unwrap();
+    }
+    assert_eq!(header.dim, [7, 64, 32, 10, 20, 1, 1, 1]);
+    assert!(matches!(
+        header.push_dim(1),
+        Err(NiftiError::InconsistentDim(0, 8))
+    ));
+}
+
+#[test]
+fn from_reader_at_offset() {
+    let mut bytes = vec![0xAB; 100];
+    bytes.extend(std::fs::read("resources/minimal.nii").unwrap());
+    let mut cursor = Cursor::new(bytes);
+
+    let header = NiftiHeader::from_reader_at(&mut cursor, 100).unwrap();
+    assert_eq!(header, minimal_header_nii_gt());
+    assert_eq!(cursor.position(), 100 + 348);
+
+    assert!(NiftiHeader::from_reader_at(&mut cursor, 0).is_err());
+}
+
+#[test]
+fn frame_times() {
+    let mut header = NiftiHeader::new_4d([8, 8, 4, 4], NiftiType::Int16).unwrap();
+    header.pixdim[4] = 2.;
+    header.toffset = 1.;
+    header.xyzt_units = Unit::Mm as u8 | Unit::Sec as u8;
+    assert_eq!(header.frame_times().unwrap(), vec![1., 3., 5., 7.]);
+
+    // in milliseconds
+    header.pixdim[4] = 2000.;
+    header.toffset = 500.;
+    header.xyzt_units = Unit::Mm as u8 | Unit::Msec as u8;
+    assert_eq!(header.frame_times().unwrap(), vec![0.5, 2.5, 4.5, 6.5]);
+
+    header.pixdim[4] = 0.;
+    assert!(matches!(
+        header.frame_times(),
+        Err(NiftiError::InvalidVoxelSize(_))
+    ));
+
+    header.xyzt_units = Unit::Hz as u8;
+    assert!(matches!(
+        header.frame_times(),
+        Err(NiftiError::InvalidCode(_, 32))
+    ));
+
+    // a single frame without a time axis
+    let header = NiftiHeader::new_3d([8, 8, 4], NiftiType::Int16).unwrap();
+    assert_eq!(header.frame_times().unwrap(), vec![0.]);
+}
+
+#[test]
+fn invalid_format_leading_bytes() {
+    // arbitrary bytes, with a valid `dim[0]` but no magic code
+    let mut bytes: Vec<u8> = (0..348u32).map(|i| (i * 37 % 251) as u8).collect();
+    bytes[40..42].copy_from_slice(&[0, 0]);
+    let err = NiftiHeader::from_reader(&bytes[..]).unwrap_err();
+    match err {
+        NiftiError::InvalidFormat(leading) => assert_eq!(&leading[..], &bytes[..16]),
+        e => panic!("unexpected error {:?}", e),
+    }
+
+    // a GZip stream which was not decoded
+    let bytes = std::fs::read("resources/minimal.nii.gz").unwrap();
+    let err = NiftiHeader::from_reader(&bytes[..]).unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidFormat(leading) if leading[..2] == [0x1f, 0x8b]));
+    assert!(err
+        .to_string()
+        .starts_with("Invalid NIfTI-1 file (leading bytes: 1f 8b "));
+
+    let err = NiftiHeader::parse_from_slice(&bytes, Endianness::Little).unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidFormat(leading) if leading[..] == bytes[..16]));
+}
+
+#[test]
+fn matches_volume() {
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    let header = obj.header();
+    let volume = obj.volume();
+    assert!(header.matches_volume(volume).is_ok());
+
+    let mut other = header.clone();
+    other.dim[3] = 5;
+    assert!(matches!(
+        other.matches_volume(volume),
+        Err(NiftiError::DimMismatch(h, v)) if h == [64, 64, 5] && v == [64, 64, 10]
+    ));
+
+    let mut other = header.clone();
+    other.datatype = NiftiType::Int16 as i16;
+    other.bitpix = 16;
+    assert!(matches!(
+        other.matches_volume(volume),
+        Err(NiftiError::DataTypeMismatch(
+            NiftiType::Int16,
+            NiftiType::Uint8
+        ))
+    ));
+
+    let mut other = header.clone();
+    other.datatype = 0;
+    assert!(matches!(
+        other.matches_volume(volume),
+        Err(NiftiError::NoDataType)
+    ));
+}
+
+#[test]
+fn compute_vox_offset() {
+    let header = NiftiHeader::default();
+    assert_eq!(
+        header.compute_vox_offset(&ExtensionSequence::default()),
+        352
+    );
+
+    let extensions = ExtensionSequence::new(
+        Extender::from([1, 0, 0, 0]),
+        vec![
+            Extension::from_str(6, "a comment"),
+            Extension::new(4, vec![0; 40]),
+        ],
+    );
+    assert_eq!(extensions.bytes_on_disk(), 32 + 48);
+    assert_eq!(header.compute_vox_offset(&extensions), 352 + 32 + 48);
+
+    // the file written with these extensions agrees
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    assert_eq!(
+        obj.header().compute_vox_offset(obj.extensions()),
+        obj.header().vox_offset as u64
+    );
+}
+
+#[test]
+fn effective_dimensionality() {
+    let mut header = NiftiHeader {
+        dim: [4, 64, 64, 30, 1, 1, 1, 1],
+        ..NiftiHeader::default()
+    };
+    assert_eq!(header.dimensionality().unwrap(), 4);
+    assert_eq!(header.effective_dimensionality().unwrap(), 3);
+
+    // inner singleton dimensions are kept
+    header.dim = [5, 64, 1, 30, 1, 1, 1, 1];
+    assert_eq!(header.effective_dimensionality().unwrap(), 3);
+    header.dim = [3, 1, 1, 1, 1, 1, 1, 1];
+    assert_eq!(header.effective_dimensionality().unwrap(), 1);
+    header.dim = [3, 64, 64, 0, 1, 1, 1, 1];
+    assert!(header.effective_dimensionality().is_err());
+}
+
+#[test]
+fn normalize_qform() {
+    let mut header = NiftiHeader {
+        quatern_b: 0.8,
+        quatern_c: 0.8,
+        quatern_d: 0.,
+        ..NiftiHeader::default()
+    };
+    header.normalize_qform();
+    let half_sqrt_2 = std::f32::consts::FRAC_1_SQRT_2;
+    assert!((header.quatern_b - half_sqrt_2).abs() < 1e-6);
+    assert!((header.quatern_c - half_sqrt_2).abs() < 1e-6);
+    assert_eq!(header.quatern_d, 0.);
+
+    // unit quaternions are left untouched
+    let mut header = NiftiHeader {
+        quatern_b: 0.6,
+        quatern_c: 0.,
+        quatern_d: 0.8,
+        ..NiftiHeader::default()
+    };
+    let expected = header.clone();
+    header.normalize_qform();
+    assert_eq!(header, expected);
+
+    // also repaired by `fix`
+    let mut header = NiftiHeader {
+        quatern_b: 0.,
+        quatern_c: -1.5,
+        quatern_d: 0.,
+        ..NiftiHeader::default()
+    };
+    header.fix();
+    assert_eq!(header.quatern_c, -1.);
+    #[cfg(feature = "nalgebra_affine")]
+    {
+        let affine = header.qform_affine::<f64>();
+        assert!(affine.iter().all(|v| v.is_finite()));
+    }
+}
+
+#[test]
+fn sanitize_3d() {
+    let mut header = NiftiHeader {
+        dim: [3, 64, 64, 10, 5, 1, 1, 1],
+        pixdim: [1., 0.5, 0.5, 2., 2.5, 1., 1., 1.],
+        xyzt_units: Unit::Mm as u8 | Unit::Sec as u8,
+        toffset: 1.5,
+        slice_duration: 0.1,
+        ..NiftiHeader::default()
+    };
+    header.sanitize();
+    assert_eq!(header.pixdim, [1., 0.5, 0.5, 2., 0., 0., 0., 0.]);
+    assert_eq!(header.xyzt_units().unwrap(), (Unit::Mm, Unit::Unknown));
+    assert_eq!(header.toffset, 0.);
+    assert_eq!(header.slice_duration, 0.);
+
+    // time is kept for 4D volumes
+    let mut header = NiftiHeader {
+        dim: [4, 64, 64, 10, 5, 1, 1, 1],
+        pixdim: [1., 0.5, 0.5, 2., 2.5, 1., 1., 1.],
+        xyzt_units: Unit::Mm as u8 | Unit::Sec as u8,
+        toffset: 1.5,
+        ..NiftiHeader::default()
+    };
+    header.sanitize();
+    assert_eq!(header.pixdim, [1., 0.5, 0.5, 2., 2.5, 0., 0., 0.]);
+    assert_eq!(header.xyzt_units().unwrap(), (Unit::Mm, Unit::Sec));
+    assert_eq!(header.toffset, 1.5);
+}
+
+#[test]
+fn detect_version_sentinels() {
+    use nifti::header::{
+        detect_version_and_endianness, NiftiVersion, SIZEOF_HDR_NI1, SIZEOF_HDR_NI1_SWAPPED,
+        SIZEOF_HDR_NI2, SIZEOF_HDR_NI2_SWAPPED,
+    };
+
+    let native = Endianness::native();
+    let opposite = native.to_opposite();
+    assert_eq!(
+        detect_version_and_endianness(SIZEOF_HDR_NI1),
+        Some((NiftiVersion::Nifti1, native))
+    );
+    assert_eq!(
+        detect_version_and_endianness(SIZEOF_HDR_NI1_SWAPPED),
+        Some((NiftiVersion::Nifti1, opposite))
+    );
+    assert_eq!(
+        detect_version_and_endianness(SIZEOF_HDR_NI2),
+        Some((NiftiVersion::Nifti2, native))
+    );
+    assert_eq!(
+        detect_version_and_endianness(SIZEOF_HDR_NI2_SWAPPED),
+        Some((NiftiVersion::Nifti2, opposite))
+    );
+    assert_eq!(SIZEOF_HDR_NI1_SWAPPED, 348_i32.swap_bytes());
+    assert_eq!(SIZEOF_HDR_NI2_SWAPPED, 540_i32.swap_bytes());
+    assert_eq!(detect_version_and_endianness(0), None);
+
+    // probe the first 4 bytes of a big endian file
+    let bytes = std::fs::read("resources/minimal.nii").unwrap();
+    let sizeof_hdr = i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    assert_eq!(
+        detect_version_and_endianness(sizeof_hdr),
+        Some((NiftiVersion::Nifti1, Endianness::Big))
+    );
+}
+
+#[test]
+fn ambiguous_dim0_endianness() {
+    // minimal.nii is big endian; with `dim[0]` (at byte 40) set to 0, it reads
+    // the same in both byte orders and `sizeof_hdr` decides
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[40..42].copy_from_slice(&[0, 0]);
+    let header = NiftiHeader::from_reader(&bytes[..]).unwrap();
+    assert_eq!(header.endianness, Endianness::Big);
+    assert_eq!(header.sizeof_hdr, 348);
+    assert_eq!(header.dim[0], 0);
+    assert_eq!(&header.dim[1..4], &[64, 64, 10]);
+    assert_eq!(header.datatype, NiftiType::Uint8 as i16);
+}
+
+#[test]
+fn raw_unit_codes() {
+    // unknown space code 5, time code 56, and an unused high bit
+    let header = NiftiHeader {
+        xyzt_units: 0o275,
+        ..NiftiHeader::default()
+    };
+    assert_eq!(header.space_unit_code(), 5);
+    assert_eq!(header.time_unit_code(), 56);
+    assert!(matches!(
+        header.xyzt_to_space(),
+        Err(NiftiError::InvalidCode(_, 5))
+    ));
+    assert!(matches!(
+        header.xyzt_to_time(),
+        Err(NiftiError::InvalidCode(_, 56))
+    ));
+
+    let header = NiftiHeader {
+        xyzt_units: Unit::Micron as u8 | Unit::Msec as u8,
+        ..NiftiHeader::default()
+    };
+    assert_eq!(header.space_unit_code(), Unit::Micron as i32);
+    assert_eq!(header.time_unit_code(), Unit::Msec as i32);
+    assert_eq!(header.xyzt_units().unwrap(), (Unit::Micron, Unit::Msec));
+}
+
+#[test]
+fn set_pixdim_spatial_keeps_qfac() {
+    let mut header = NiftiHeader {
+        pixdim: [-1., 1., 1., 1., 2.5, 0., 0., 0.],
+        ..NiftiHeader::default()
+    };
+    header.set_pixdim_spatial([0.5, 0.75, 3.]);
+    assert_eq!(header.pixdim, [-1., 0.5, 0.75, 3., 2.5, 0., 0., 0.]);
+}
+
+#[test]
+fn text_field_strings() {
+    let mut header = NiftiHeader::default();
+    header.set_description_str("русский").unwrap();
+    header.set_aux_file("aux.txt").unwrap();
+    header.set_intent_name("t-stat").unwrap();
+    assert_eq!(header.descrip_str().unwrap(), "русский");
+    assert_eq!(header.aux_file_str().unwrap(), "aux.txt");
+    assert_eq!(header.intent_name_str().unwrap(), "t-stat");
+
+    header.intent_name = [0; 16];
+    assert_eq!(header.intent_name_str().unwrap(), "");
+
+    header.aux_file[0] = 0xFF;
+    assert!(matches!(header.aux_file_str(), Err(NiftiError::Utf8(_))));
+}
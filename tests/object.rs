@@ -6,10 +6,13 @@ extern crate nifti;
 extern crate pretty_assertions;
 
 use nifti::{
-    Endianness, NiftiHeader, NiftiObject, NiftiType, NiftiVolume, RandomAccessNiftiVolume,
-    ReaderOptions, ReaderStreamedOptions, XForm,
+    Endianness, HeaderWarning, InMemNiftiObject, NiftiError, NiftiHeader, NiftiObject, NiftiType,
+    NiftiVolume, RandomAccessNiftiVolume, ReaderOptions, ReaderStreamedOptions,
+    StreamedNiftiObject, XForm,
 };
 
+use std::io::Cursor;
+
 mod util;
 
 use util::{minimal_header_hdr_gt, minimal_header_nii_gt};
@@ -26,6 +29,41 @@ fn minimal_nii_gz() {
     assert_eq!(volume.dim(), [64, 64, 10].as_ref());
 }
 
+#[test]
+fn minimal_nii_gz_with_progress() {
+    let mut calls = Vec::new();
+    const FILE_NAME: &str = "resources/minimal.nii.gz";
+    let obj = ReaderOptions::new()
+        .read_file_with_progress(FILE_NAME, |read, total| calls.push((read, total)))
+        .unwrap();
+    assert_eq!(obj.header(), &minimal_header_nii_gt());
+    assert_eq!(calls.last(), Some(&(64 * 64 * 10, 64 * 64 * 10)));
+}
+
+#[test]
+fn zstat1_nii_gz_with_progress() {
+    let mut calls: Vec<(usize, usize)> = Vec::new();
+    const FILE_NAME: &str = "resources/zstat1.nii.gz";
+    let obj = ReaderOptions::new()
+        .read_file_with_progress(FILE_NAME, |read, total| calls.push((read, total)))
+        .unwrap();
+    let expected = obj.volume().raw_data().len();
+    assert!(calls.len() > 1);
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(calls.iter().all(|&(_, total)| total == expected));
+    assert_eq!(calls.last(), Some(&(expected, expected)));
+}
+
+#[test]
+fn minimal_by_hdr_with_progress() {
+    let mut last = None;
+    const FILE_NAME: &str = "resources/minimal.hdr";
+    ReaderOptions::new()
+        .read_file_with_progress(FILE_NAME, |read, total| last = Some((read, total)))
+        .unwrap();
+    assert_eq!(last, Some((64 * 64 * 10, 64 * 64 * 10)));
+}
+
 #[test]
 fn streamed_minimal_nii_gz() {
     let minimal_hdr = minimal_header_nii_gt();
@@ -238,3 +276,718 @@ fn bad_file_4() {
         .read_file("resources/fuzz_artifacts/crash-08123ef33416bd6f0c5fa63d44b681b8581d62a0");
     // must not panic or abort
 }
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn f32_nii_gz_ndarray() {
+    const FILE_NAME: &str = "resources/f32.nii.gz";
+    let (header, volume) = ReaderOptions::new()
+        .read_file_ndarray::<f32, _>(FILE_NAME)
+        .unwrap();
+    assert_eq!(header.data_type().unwrap(), NiftiType::Float32);
+    assert_eq!(volume.shape(), &[11, 11, 11]);
+    assert_eq!(volume[[5, 5, 5]], 1.);
+    assert_eq!(volume[[5, 0, 4]], 0.4);
+    assert_eq!(volume[[0, 8, 5]], 0.8);
+}
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn into_ndarray_f64() {
+    use nifti::IntoNdArray;
+
+    // minimal.nii is big endian, `scl_slope` and `scl_inter` are at bytes 112 and 116
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[112..116].copy_from_slice(&2f32.to_be_bytes());
+    bytes[116..120].copy_from_slice(&(-0.5f32).to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scaled.nii");
+    std::fs::write(&path, bytes).unwrap();
+
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    let expected = obj.clone().into_volume().into_ndarray::<f64>().unwrap();
+    let volume = obj.into_ndarray_f64().unwrap();
+    assert_eq!(volume.shape(), &[64, 64, 10]);
+    assert_eq!(volume, expected);
+    assert!(volume.iter().all(|&v| v == -0.5 || v >= 1.5));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn read_with_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sub-01_T1w.nii.gz");
+    std::fs::copy("resources/minimal.nii.gz", &path).unwrap();
+
+    let (obj, sidecar) = ReaderOptions::new().read_with_sidecar(&path).unwrap();
+    assert_eq!(obj.header(), &minimal_header_nii_gt());
+    assert_eq!(sidecar, None);
+
+    let json = r#"{"RepetitionTime": 2.0, "EchoTime": 0.03}"#;
+    std::fs::write(dir.path().join("sub-01_T1w.json"), json).unwrap();
+    let (obj, sidecar) = ReaderOptions::new().read_with_sidecar(&path).unwrap();
+    assert_eq!(obj.header(), &minimal_header_nii_gt());
+    let sidecar = sidecar.unwrap();
+    assert_eq!(sidecar["RepetitionTime"], 2.0);
+    assert_eq!(sidecar["EchoTime"], 0.03);
+
+    std::fs::write(dir.path().join("sub-01_T1w.json"), "{").unwrap();
+    let err = ReaderOptions::new().read_with_sidecar(&path).unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidSidecar(_)));
+}
+
+#[test]
+fn lenient_trailing_dims() {
+    // minimal.nii is big endian, `dim` is at byte 40:
+    // claim a 4th dimension, left as zero
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[40..42].copy_from_slice(&4u16.to_be_bytes());
+    bytes[48..50].copy_from_slice(&0u16.to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trailing_dims.nii");
+    std::fs::write(&path, bytes).unwrap();
+
+    let err = ReaderOptions::new().read_file(&path).unwrap_err();
+    assert!(matches!(err, NiftiError::InconsistentDim(4, 0)));
+
+    let obj = ReaderOptions::new().lenient(true).read_file(&path).unwrap();
+    assert_eq!(obj.header().dim().unwrap(), &[64, 64, 10]);
+    assert_eq!(obj.volume().dim(), &[64, 64, 10]);
+    // `fix_header` is also enabled
+    assert_eq!(obj.header().pixdim[0], 1.);
+    let expected = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    assert_eq!(obj.volume(), expected.volume());
+
+    // the same applies when reading a single timepoint
+    let err = ReaderOptions::new().read_timepoint(&path, 0).unwrap_err();
+    assert!(matches!(err, NiftiError::InconsistentDim(4, 0)));
+    let (header, volume) = ReaderOptions::new()
+        .lenient(true)
+        .read_timepoint(&path, 9)
+        .unwrap();
+    assert_eq!(&header, obj.header());
+    let slice = &expected.volume().split_last_axis().unwrap()[9];
+    assert_eq!(volume.dim(), slice.dim());
+    assert_eq!(volume.raw_data(), slice.raw_data());
+}
+
+#[test]
+fn fix_nan_scaling() {
+    // minimal.nii is big endian, `scl_slope` and `scl_inter` are at bytes 112 and 116
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[112..116].copy_from_slice(&f32::NAN.to_be_bytes());
+    bytes[116..120].copy_from_slice(&f32::NAN.to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nan_scaling.nii");
+    std::fs::write(&path, bytes).unwrap();
+
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    assert!(obj.header().scl_slope.is_nan());
+    assert!(obj.volume().get_f64(&[0, 0, 0]).unwrap().is_nan());
+
+    let expected = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    let obj = ReaderOptions::new()
+        .fix_header(true)
+        .read_file(&path)
+        .unwrap();
+    assert_eq!(obj.header().scl_slope, 1.);
+    assert_eq!(obj.header().scl_inter, 0.);
+    assert!(obj.volume().values_eq(expected.volume()));
+
+    let obj = ReaderStreamedOptions::new()
+        .fix_header(true)
+        .read_file(&path)
+        .unwrap();
+    assert_eq!(obj.header().scl_slope, 1.);
+    assert_eq!(obj.header().scl_inter, 0.);
+    let slice = obj.into_volume().next().unwrap().unwrap();
+    let expected_slice = expected.volume().collect_slice(2, 0).unwrap();
+    assert!(slice.values_eq(&expected_slice));
+}
+
+#[test]
+fn from_bytes() {
+    let expected = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+
+    let data = std::fs::read("resources/minimal.nii").unwrap();
+    let obj = InMemNiftiObject::from_bytes(data).unwrap();
+    assert_eq!(obj.header(), expected.header());
+    assert_eq!(obj.volume(), expected.volume());
+
+    let data = std::fs::read("resources/minimal.nii.gz").unwrap();
+    let obj = InMemNiftiObject::from_bytes(&data[..]).unwrap();
+    assert_eq!(obj.header(), expected.header());
+    assert_eq!(obj.volume(), expected.volume());
+
+    let data = std::fs::read("resources/minimal.hdr").unwrap();
+    assert!(matches!(
+        InMemNiftiObject::from_bytes(data),
+        Err(NiftiError::NoVolumeData)
+    ));
+}
+
+#[test]
+fn read_with_warnings() {
+    // minimal.nii already has an invalid qfac (`pixdim[0]` is 0),
+    // `scl_slope` is at byte 112
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[112..116].copy_from_slice(&f32::NAN.to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("two_issues.nii");
+    std::fs::write(&path, bytes).unwrap();
+
+    let (obj, warnings) = ReaderOptions::new()
+        .fix_header(true)
+        .read_file_with_warnings(&path)
+        .unwrap();
+    assert_eq!(
+        warnings,
+        vec![HeaderWarning::InvalidQfac(0.), HeaderWarning::NanSlope]
+    );
+    assert_eq!(obj.header().pixdim[0], 1.);
+    assert_eq!(obj.header().scl_slope, 1.);
+    assert!(obj.header().warnings().is_empty());
+}
+
+#[test]
+fn fractional_vox_offset_strict() {
+    // minimal.nii is big endian, `vox_offset` is at byte 108
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[108..112].copy_from_slice(&352.5f32.to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fractional.nii");
+    std::fs::write(&path, bytes).unwrap();
+
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    assert_eq!(obj.header().vox_offset, 352.5);
+
+    let err = ReaderOptions::new()
+        .strict(true)
+        .read_file(&path)
+        .unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidVoxOffset(v) if v == 352.5));
+    let err = ReaderStreamedOptions::new()
+        .strict(true)
+        .read_file(&path)
+        .unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidVoxOffset(v) if v == 352.5));
+
+    // the header is rejected before the (here missing) volume is read
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes.truncate(352);
+    std::fs::write(&path, bytes).unwrap();
+    let err = ReaderOptions::new()
+        .strict(true)
+        .read_file(&path)
+        .unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidVoxOffset(v) if v == 352.5));
+    let err = ReaderStreamedOptions::new()
+        .strict(true)
+        .read_file(&path)
+        .unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidVoxOffset(v) if v == 352.5));
+
+    // an integer offset is accepted
+    assert!(ReaderOptions::new()
+        .strict(true)
+        .read_file("resources/minimal.nii")
+        .is_ok());
+}
+
+#[cfg(all(feature = "nalgebra_affine", feature = "ndarray_volumes"))]
+#[test]
+fn apply_affine_reorient() {
+    use nalgebra::Vector4;
+    use ndarray::{Array, ShapeBuilder};
+    use nifti::writer::WriterOptions;
+
+    // voxel axis 0 points to -y, axis 1 to +x and axis 2 to -z
+    let header = NiftiHeader {
+        sform_code: 1,
+        srow_x: [0., 2., 0., 10.],
+        srow_y: [-1., 0., 0., 5.],
+        srow_z: [0., 0., -3., 1.],
+        pixdim: [1., 1., 2., 3., 1., 1., 1., 1.],
+        dim_info: 1 | 2 << 2 | 3 << 4,
+        ..NiftiHeader::default()
+    };
+    // each voxel holds its own linear index
+    let data = Array::from_shape_vec((2, 3, 4).f(), (0..24).collect::<Vec<u8>>()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("oriented.nii");
+    WriterOptions::new(&path)
+        .reference_header(&header)
+        .write_nifti(&data)
+        .unwrap();
+
+    let mut obj = ReaderOptions::new().read_file(&path).unwrap();
+    let old_affine = obj.header().affine::<f64>();
+    obj.apply_affine_reorient().unwrap();
+
+    let header = obj.header();
+    assert_eq!(&header.dim[..4], &[3, 3, 2, 4]);
+    assert_eq!(&header.pixdim[1..4], &[2., 1., 3.]);
+    assert_eq!(header.dim_info, 2 | 1 << 2 | 3 << 4);
+    assert_eq!(header.sform_code, 1);
+    let new_affine = header.affine::<f64>();
+    for w in 0..3 {
+        for v in 0..3 {
+            assert_eq!(new_affine[(w, v)] > 0., w == v);
+        }
+    }
+
+    let volume = obj.volume();
+    assert_eq!(volume.dim(), &[3, 2, 4]);
+    for z in 0..4 {
+        for y in 0..2 {
+            for x in 0..3 {
+                let value = volume.get_u8(&[x, y, z]).unwrap();
+                let old = Vector4::new(
+                    f64::from(value % 2),
+                    f64::from(value / 2 % 3),
+                    f64::from(value / 6),
+                    1.,
+                );
+                let new = Vector4::new(f64::from(x), f64::from(y), f64::from(z), 1.);
+                assert_eq!(old_affine * old, new_affine * new);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra_affine")]
+#[test]
+fn apply_affine_reorient_invalid_qform() {
+    // minimal.nii is big endian, `pixdim[0]` is at byte 76 and `qform_code`
+    // at byte 252
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[76..80].copy_from_slice(&0f32.to_be_bytes());
+    bytes[252..254].copy_from_slice(&1i16.to_be_bytes());
+    let mut obj = InMemNiftiObject::from_reader(&bytes[..]).unwrap();
+    assert!(matches!(
+        obj.apply_affine_reorient(),
+        Err(NiftiError::InvalidQfac(q)) if q == 0.
+    ));
+}
+
+#[test]
+fn negative_extension_size() {
+    // minimal.nii is big endian, `vox_offset` is at byte 108
+    let original = std::fs::read("resources/minimal.nii").unwrap();
+    let mut bytes = original[..348].to_vec();
+    bytes[108..112].copy_from_slice(&384f32.to_be_bytes());
+    bytes.extend_from_slice(&[1, 0, 0, 0]);
+    // a valid 16-byte extension, followed by one with a negative size
+    bytes.extend_from_slice(&16i32.to_be_bytes());
+    bytes.extend_from_slice(&[0; 12]);
+    bytes.extend_from_slice(&(-16i32).to_be_bytes());
+    bytes.extend_from_slice(&[0; 12]);
+    bytes.extend_from_slice(&original[352..]);
+
+    let err = InMemNiftiObject::from_reader(&bytes[..]).unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidExtensionSize(-16)));
+}
+
+#[test]
+fn mutated_headers_do_not_panic() {
+    let original = std::fs::read("resources/minimal.nii").unwrap();
+    // simple xorshift generator, for reproducible mutations
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..2000 {
+        let mut bytes = original.clone();
+        for _ in 0..1 + next() % 8 {
+            let pos = (next() % 360) as usize;
+            bytes[pos] = next() as u8;
+        }
+        let len = if next() % 4 == 0 {
+            (next() % 400) as usize
+        } else {
+            bytes.len()
+        };
+        let _ = NiftiHeader::from_reader(&bytes[..len]);
+        let _ = InMemNiftiObject::from_reader(&bytes[..len]);
+        let _ = StreamedNiftiObject::from_reader(&bytes[..len]);
+    }
+}
+
+#[test]
+fn header_only_stream_without_extender() {
+    let bytes = std::fs::read("resources/minimal.nii").unwrap();
+    let header_only = &bytes[..348];
+
+    let obj = StreamedNiftiObject::from_reader(header_only).unwrap();
+    assert_eq!(obj.header(), &minimal_header_nii_gt());
+    assert!(obj.extensions().is_empty());
+    assert!(!obj.extensions().extender().has_extensions());
+
+    // the volume itself is still missing
+    let err = InMemNiftiObject::from_reader(header_only).unwrap_err();
+    assert!(matches!(err, NiftiError::IncompatibleLength(0, 40960)));
+}
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn read_timepoint() {
+    use ndarray::{Array, ShapeBuilder};
+    use nifti::writer::WriterOptions;
+
+    let data =
+        Array::from_shape_vec((2, 2, 2, 3).f(), (0..24).map(|v| v as f32).collect()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    for file_name in &["4d.nii", "4d.nii.gz", "4d.hdr", "4d.hdr.gz"] {
+        let path = dir.path().join(file_name);
+        WriterOptions::new(&path).write_nifti(&data).unwrap();
+        let full = ReaderOptions::new().read_file(&path).unwrap();
+        let expected = full.volume().split_last_axis().unwrap();
+
+        for (k, expected) in expected.iter().enumerate() {
+            let (header, volume) = ReaderOptions::new()
+                .read_timepoint(&path, k as u64)
+                .unwrap();
+            assert_eq!(&header, full.header());
+            assert_eq!(volume.dim(), &[2, 2, 2]);
+            assert_eq!(&volume, expected);
+        }
+        assert_eq!(
+            ReaderOptions::new()
+                .read_timepoint(&path, 2)
+                .unwrap()
+                .1
+                .get_f32(&[1, 1, 1])
+                .unwrap(),
+            23.
+        );
+
+        let err = ReaderOptions::new().read_timepoint(&path, 3).unwrap_err();
+        assert!(matches!(err, NiftiError::OutOfBounds(ref c) if c == &[0, 0, 0, 3]));
+    }
+}
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn read_timepoint_f16() {
+    use ndarray::{Array, ShapeBuilder};
+    use nifti::writer::WriterOptions;
+
+    let data =
+        Array::from_shape_vec((2, 2, 2, 3).f(), (0..24).map(|v| v as f32 / 4.).collect()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    for file_name in &["f16.nii", "f16.hdr"] {
+        let path = dir.path().join(file_name);
+        WriterOptions::new(&path).write_nifti_f16(&data).unwrap();
+
+        let (header, volume) = ReaderOptions::new().read_timepoint(&path, 2).unwrap();
+        assert_eq!(header.data_type().unwrap(), NiftiType::Int16);
+        assert_eq!(volume.data_type(), NiftiType::Int16);
+
+        let (header, volume) = ReaderOptions::new()
+            .interpret_f16_extension(true)
+            .read_timepoint(&path, 2)
+            .unwrap();
+        assert_eq!(header.data_type().unwrap(), NiftiType::Float32);
+        assert_eq!(header.bitpix, 32);
+        assert_eq!(volume.data_type(), NiftiType::Float32);
+        for ((i, j, k, t), &v) in data.indexed_iter() {
+            if t == 2 {
+                let coords = [i as u16, j as u16, k as u16];
+                assert_eq!(volume.get_f32(&coords).unwrap(), v);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn extension_count_and_bytes() {
+    use nifti::{writer::WriterOptions, Extender, Extension, ExtensionSequence};
+
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    assert_eq!(obj.extension_count(), 0);
+    assert_eq!(obj.extension_bytes(), 0);
+
+    let extensions = ExtensionSequence::new(
+        Extender::from([1, 0, 0, 0]),
+        vec![
+            Extension::from_str(6, "a comment"),
+            Extension::new(4, vec![0; 40]),
+        ],
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("extended.nii");
+    let data = ndarray::Array3::<u8>::zeros((4, 5, 6));
+    WriterOptions::new(&path)
+        .with_extensions(extensions)
+        .write_nifti(&data)
+        .unwrap();
+
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    assert_eq!(obj.extension_count(), 2);
+    assert_eq!(obj.extension_bytes(), 32 + 48);
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn content_hash() {
+    let read = |path| ReaderOptions::new().read_file(path).unwrap();
+    let hash = read("resources/minimal.nii").content_hash();
+    // same content, compressed
+    assert_eq!(read("resources/minimal.nii.gz").content_hash(), hash);
+    // same content, as a file pair
+    assert_eq!(read("resources/minimal.hdr").content_hash(), hash);
+
+    // any change to the header or data changes the hash
+    let mut obj = read("resources/minimal.nii");
+    obj.header_mut().descrip = b"changed".to_vec();
+    assert_ne!(obj.content_hash(), hash);
+    assert_ne!(read("resources/f32.nii.gz").content_hash(), hash);
+}
+
+#[test]
+fn from_hdr_img_readers() {
+    let hdr = std::fs::read("resources/minimal.hdr").unwrap();
+    let img = std::fs::read("resources/minimal.img").unwrap();
+    let expected = ReaderOptions::new()
+        .read_file_pair("resources/minimal.hdr", "resources/minimal.img")
+        .unwrap();
+
+    let obj = InMemNiftiObject::from_hdr_img_readers(Cursor::new(&hdr), Cursor::new(&img)).unwrap();
+    assert_eq!(obj.header(), expected.header());
+    assert_eq!(obj.volume(), expected.volume());
+    assert!(obj.extensions().is_empty());
+
+    // append a comment extension to the header, in big endian
+    let mut hdr_ext = hdr.clone();
+    hdr_ext.extend_from_slice(&[1, 0, 0, 0]);
+    hdr_ext.extend_from_slice(&16i32.to_be_bytes());
+    hdr_ext.extend_from_slice(&6i32.to_be_bytes());
+    hdr_ext.extend_from_slice(b"comment\0");
+    let obj =
+        InMemNiftiObject::from_hdr_img_readers(Cursor::new(&hdr_ext), Cursor::new(&img)).unwrap();
+    assert_eq!(obj.volume(), expected.volume());
+    let extensions: Vec<_> = obj.extensions().iter().collect();
+    assert_eq!(extensions.len(), 1);
+    assert_eq!(extensions[0].code(), 6);
+    assert_eq!(extensions[0].data(), b"comment\0");
+
+    // the volume starts at `vox_offset` in the image
+    let mut hdr_offset = hdr;
+    hdr_offset[108..112].copy_from_slice(&16f32.to_be_bytes());
+    let mut img_offset = vec![0xFF; 16];
+    img_offset.extend_from_slice(&img);
+    let obj =
+        InMemNiftiObject::from_hdr_img_readers(Cursor::new(&hdr_offset), Cursor::new(&img_offset))
+            .unwrap();
+    assert_eq!(obj.volume(), expected.volume());
+}
+
+#[cfg(feature = "ndarray_volumes")]
+#[test]
+fn eq_ignoring_extension_order() {
+    use nifti::{writer::WriterOptions, Extender, Extension, ExtensionSequence};
+
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    let extender = Extender::from([1, 0, 0, 0]);
+    let a = Extension::from_str(6, "a comment");
+    let b = Extension::new(4, vec![1; 24]);
+    let c = Extension::from_str(6, "another comment");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("extensions.nii");
+    let with_extensions = |extensions: Vec<Extension>| {
+        WriterOptions::new(&path)
+            .reference_header(obj.header())
+            .with_extensions(ExtensionSequence::new(extender, extensions))
+            .write_raw_volume(obj.volume())
+            .unwrap();
+        ReaderOptions::new().read_file(&path).unwrap()
+    };
+
+    let obj1 = with_extensions(vec![a.clone(), b.clone(), c.clone()]);
+    let obj2 = with_extensions(vec![c.clone(), a.clone(), b.clone()]);
+    assert_ne!(obj1, obj2);
+    assert!(obj1.eq_ignoring_extension_order(&obj2));
+    assert!(obj2.eq_ignoring_extension_order(&obj1));
+
+    // the extensions must still be the same
+    let obj3 = with_extensions(vec![c.clone(), a.clone(), a.clone()]);
+    assert!(!obj1.eq_ignoring_extension_order(&obj3));
+    let obj4 = with_extensions(vec![c, a]);
+    assert!(!obj1.eq_ignoring_extension_order(&obj4));
+
+    // and so must the rest of the object
+    let mut obj5 = with_extensions(vec![b.clone()]);
+    obj5.header_mut().descrip = b"changed".to_vec();
+    assert!(!with_extensions(vec![b]).eq_ignoring_extension_order(&obj5));
+}
+
+#[test]
+fn validate_values() {
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    obj.validate_values().unwrap();
+
+    // minimal.nii is big endian, `intent_code` is at byte 68,
+    // `scl_slope` at byte 112 and `scl_inter` at byte 116
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[68..70].copy_from_slice(&(nifti::Intent::Label as i16).to_be_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("labels.nii");
+    std::fs::write(&path, &bytes).unwrap();
+    // its unsigned values are all valid labels
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    obj.validate_values().unwrap();
+
+    // shifting them makes the background label negative
+    bytes[112..116].copy_from_slice(&1f32.to_be_bytes());
+    bytes[116..120].copy_from_slice(&(-1f32).to_be_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    let err = obj.validate_values().unwrap_err();
+    assert!(matches!(err, NiftiError::InvalidVoxelValue(_, v) if v == -1.));
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn verify_checksum() {
+    use nifti::{writer::WriterOptions, Extender, Extension, ExtensionSequence};
+
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    let digest = obj.volume().sha256();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checksum.nii");
+    WriterOptions::new(&path)
+        .reference_header(obj.header())
+        .with_extensions(ExtensionSequence::new(
+            Extender::from([1, 0, 0, 0]),
+            vec![Extension::sha256(&digest)],
+        ))
+        .write_raw_volume(obj.volume())
+        .unwrap();
+
+    let read = ReaderOptions::new()
+        .verify_checksum(true)
+        .read_file(&path)
+        .unwrap();
+    assert_eq!(read.extensions().sha256_digest(), Some(digest));
+    assert_eq!(read.volume().raw_data(), obj.volume().raw_data());
+
+    // tamper with the last voxel
+    let mut bytes = std::fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, bytes).unwrap();
+    assert!(matches!(
+        ReaderOptions::new().verify_checksum(true).read_file(&path),
+        Err(NiftiError::ChecksumMismatch)
+    ));
+    assert!(ReaderOptions::new().read_file(&path).is_ok());
+
+    // files without the extension are not checked
+    assert!(ReaderOptions::new()
+        .verify_checksum(true)
+        .read_file("resources/minimal.nii")
+        .is_ok());
+}
+
+#[cfg(feature = "nalgebra_affine")]
+#[test]
+fn crop_invalid_qform() {
+    // minimal.nii is big endian, `pixdim[1]` is at byte 80 and `qform_code`
+    // at byte 252
+    let mut bytes = std::fs::read("resources/minimal.nii").unwrap();
+    bytes[80..84].copy_from_slice(&(-1f32).to_be_bytes());
+    bytes[252..254].copy_from_slice(&1i16.to_be_bytes());
+    let obj = InMemNiftiObject::from_reader(&bytes[..]).unwrap();
+    assert!(matches!(
+        obj.crop(&[0, 0, 0], &[1, 1, 1]),
+        Err(NiftiError::InvalidVoxelSize(d)) if d == -1.
+    ));
+}
+
+#[cfg(all(feature = "nalgebra_affine", feature = "ndarray_volumes"))]
+#[test]
+fn crop_keeps_world_coordinates() {
+    use nalgebra::{Matrix4, Vector4};
+    use ndarray::{Array, ShapeBuilder};
+    use nifti::writer::WriterOptions;
+
+    #[rustfmt::skip]
+    let affine = Matrix4::new(
+        0., 2., 0., 10.,
+        -1., 0., 0., 5.,
+        0., 0., 3., 1.,
+        0., 0., 0., 1.,
+    );
+    let mut header = NiftiHeader {
+        pixdim: [1., 1., 2., 3., 1., 1., 1., 1.],
+        ..NiftiHeader::default()
+    };
+    header.set_sform(&affine, XForm::AlignedAnat);
+    header.set_qform(&affine, XForm::ScannerAnat);
+    let data = Array::from_shape_vec((4, 3, 2).f(), (0..24).collect::<Vec<u8>>()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("crop.nii");
+    WriterOptions::new(&path)
+        .reference_header(&header)
+        .write_nifti(&data)
+        .unwrap();
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+
+    let cropped = obj.crop(&[1, 1, 0], &[2, 2, 2]).unwrap();
+    assert_eq!(&cropped.header().dim[..4], &[3, 2, 2, 2]);
+    assert_eq!(cropped.volume().dim(), &[2, 2, 2]);
+    assert_eq!(cropped.volume().get_u8(&[0, 0, 0]).unwrap(), 5);
+    assert_eq!(cropped.volume().get_u8(&[1, 1, 1]).unwrap(), 22);
+
+    let expected = affine * Vector4::new(1., 1., 0., 1.);
+    for (name, form) in [
+        ("sform", cropped.header().sform_affine::<f64>()),
+        ("qform", cropped.header().qform_affine::<f64>()),
+    ] {
+        let origin = form * Vector4::new(0., 0., 0., 1.);
+        assert!((origin - expected).norm() < 1e-4, "{}: {}", name, origin);
+    }
+
+    assert!(matches!(
+        obj.crop(&[1, 1, 0], &[4, 2, 2]),
+        Err(NiftiError::OutOfBounds(c)) if c == [4, 2, 1]
+    ));
+
+    // the start along the time axis does not move the origin
+    let data = Array::from_shape_vec((4, 3, 2, 3).f(), (0..72).collect::<Vec<u8>>()).unwrap();
+    let path = dir.path().join("crop_4d.nii");
+    WriterOptions::new(&path)
+        .reference_header(&header)
+        .write_nifti(&data)
+        .unwrap();
+    let obj = ReaderOptions::new().read_file(&path).unwrap();
+    for (starts, shape) in [([0, 0, 0, 0], [4, 3, 2, 3]), ([1, 0, 0, 2], [2, 2, 2, 1])] {
+        let cropped = obj.crop(&starts, &shape).unwrap();
+        assert_eq!(&cropped.header().dim[1..5], &shape.map(|d| d as u16));
+        let expected = affine * Vector4::new(starts[0] as f64, 0., 0., 1.);
+        for form in [
+            cropped.header().sform_affine::<f64>(),
+            cropped.header().qform_affine::<f64>(),
+        ] {
+            let origin = form * Vector4::new(0., 0., 0., 1.);
+            assert!((origin - expected).norm() < 1e-4, "{}", origin);
+        }
+    }
+}
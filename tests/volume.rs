@@ -11,7 +11,10 @@ extern crate ndarray;
 #[cfg(feature = "ndarray_volumes")]
 extern crate num_traits;
 
-use nifti::{InMemNiftiVolume, NiftiVolume, RandomAccessNiftiVolume};
+use nifti::{
+    InMemNiftiVolume, NiftiError, NiftiHeader, NiftiObject, NiftiType, NiftiVolume,
+    RandomAccessNiftiVolume, ReaderOptions, VolumeWithHeader,
+};
 
 mod util;
 
@@ -42,13 +45,157 @@ fn minimal_img_gz() {
     }
 }
 
+#[test]
+fn minimal_img_gz_raw_dim() {
+    let minimal_hdr = minimal_header_hdr_gt();
+
+    const FILE_NAME: &str = "resources/minimal.img.gz";
+    let volume = InMemNiftiVolume::from_file(FILE_NAME, &minimal_hdr).unwrap();
+
+    let raw_dim = volume.raw_dim();
+    assert_eq!(raw_dim[0], 3);
+    assert_eq!(raw_dim, minimal_hdr.dim.map(u64::from));
+}
+
+#[test]
+fn minimal_volume_with_header() {
+    let minimal_hdr = minimal_header_hdr_gt();
+
+    const FILE_NAME: &str = "resources/minimal.img.gz";
+    let volume = InMemNiftiVolume::from_file(FILE_NAME, &minimal_hdr).unwrap();
+    let bundle = VolumeWithHeader::new(minimal_hdr.clone(), volume.clone()).unwrap();
+
+    assert_eq!(bundle.header(), &minimal_hdr);
+    assert_eq!(AsRef::<NiftiHeader>::as_ref(&bundle), &minimal_hdr);
+    assert_eq!(bundle.volume(), &volume);
+    assert_eq!(bundle.dim(), [64, 64, 10].as_ref());
+    assert_eq!(bundle.get_f32(&[5, 7, 2]).unwrap(), 7.);
+
+    let (header, inner) = bundle.into_parts();
+    assert_eq!(header, minimal_hdr);
+    assert_eq!(inner, volume);
+
+    // the header must describe the volume
+    let mut other_hdr = minimal_hdr.clone();
+    other_hdr.dim[3] = 5;
+    assert!(matches!(
+        VolumeWithHeader::new(other_hdr, volume.clone()),
+        Err(NiftiError::DimMismatch(_, _))
+    ));
+    let mut other_hdr = minimal_hdr.clone();
+    other_hdr.datatype = NiftiType::Int16 as i16;
+    assert!(matches!(
+        VolumeWithHeader::new(other_hdr, volume.clone()),
+        Err(NiftiError::DataTypeMismatch(NiftiType::Int16, _))
+    ));
+    let mut other_hdr = minimal_hdr;
+    other_hdr.scl_slope = 2.;
+    assert!(matches!(
+        VolumeWithHeader::new(other_hdr, volume),
+        Err(NiftiError::ScalingMismatch((s, _), _)) if s == 2.
+    ));
+
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii.gz")
+        .unwrap();
+    let header = obj.header().clone();
+    let bundle = VolumeWithHeader::from(obj);
+    assert_eq!(bundle.header(), &header);
+    assert_eq!(bundle.dim(), [64, 64, 10].as_ref());
+}
+
+#[test]
+fn filled_volume() {
+    let volume = InMemNiftiVolume::filled(&[3, 3, 3], NiftiType::Uint8, 7.).unwrap();
+    assert_eq!(volume.dim(), [3, 3, 3].as_ref());
+    assert_eq!(volume.data_type(), NiftiType::Uint8);
+    assert_eq!(volume.raw_data(), &[7; 27][..]);
+    assert_eq!(volume.get_u8(&[2, 1, 0]).unwrap(), 7);
+    assert_eq!(volume.iter_f64().unwrap().sum::<f64>(), 7. * 27.);
+
+    // integer values are rounded and clamped
+    let volume = InMemNiftiVolume::filled(&[2, 2], NiftiType::Int16, 1e6).unwrap();
+    assert_eq!(volume.get_i16(&[1, 1]).unwrap(), i16::MAX);
+    let volume = InMemNiftiVolume::filled(&[4], NiftiType::Float32, 0.25).unwrap();
+    assert_eq!(volume.get_f32(&[3]).unwrap(), 0.25);
+
+    assert!(matches!(
+        InMemNiftiVolume::filled(&[3, 3], NiftiType::Rgb24, 1.),
+        Err(NiftiError::UnsupportedDataType(NiftiType::Rgb24))
+    ));
+    assert!(InMemNiftiVolume::filled(&[3, 0], NiftiType::Uint8, 1.).is_err());
+    assert!(matches!(
+        InMemNiftiVolume::filled(&[70000, 1, 1], NiftiType::Uint8, 1.),
+        Err(NiftiError::BadVolumeSize)
+    ));
+}
+
+// the volume size must fit in `usize`, but not in `isize`
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn huge_volume_reserve_error() {
+    let header = NiftiHeader {
+        dim: [4, 65535, 65535, 65535, 65535, 1, 1, 1],
+        datatype: 2,
+        bitpix: 8,
+        ..NiftiHeader::default()
+    };
+    let err = InMemNiftiVolume::from_reader(&[0u8; 16][..], &header).unwrap_err();
+    assert!(matches!(err, NiftiError::ReserveVolume(n, _) if n == 65535usize.pow(4)));
+    assert!(err.to_string().contains("ReaderStreamedOptions"));
+}
+
+// the scalar API must remain usable without `ndarray`
+#[cfg(not(feature = "ndarray_volumes"))]
+mod scalar_volumes {
+    use nifti::{
+        InMemNiftiVolume, NiftiObject, NiftiType, NiftiVolume, RandomAccessNiftiVolume,
+        ReaderOptions, ReaderStreamedOptions,
+    };
+
+    #[test]
+    fn minimal_nii_scalar() {
+        let obj = ReaderOptions::new()
+            .read_file("resources/minimal.nii.gz")
+            .unwrap();
+        let volume = obj.into_volume();
+        assert_eq!(volume.dim(), [64, 64, 10].as_ref());
+        assert_eq!(volume.data_type(), NiftiType::Uint8);
+        assert_eq!(volume.get_f64(&[5, 7, 2]).unwrap(), 7.);
+
+        let values: Vec<f64> = volume.iter_f64().unwrap().collect();
+        assert_eq!(values.len(), 64 * 64 * 10);
+        assert_eq!(values[5 + 7 * 64 + 2 * 64 * 64], 7.);
+        assert_eq!(volume.into_vec_f64().unwrap(), values);
+    }
+
+    #[test]
+    fn minimal_nii_streamed_scalar() {
+        let expected = ReaderOptions::new()
+            .read_file("resources/minimal.nii.gz")
+            .unwrap()
+            .into_volume();
+        let obj = ReaderStreamedOptions::new()
+            .read_file("resources/minimal.nii.gz")
+            .unwrap();
+        let slices: Vec<InMemNiftiVolume> = obj.into_volume().map(|s| s.unwrap()).collect();
+        assert_eq!(slices.len(), 10);
+        let values: Vec<f64> = slices
+            .into_iter()
+            .flat_map(|s| s.into_vec_f64().unwrap())
+            .collect();
+        assert_eq!(values, expected.into_vec_f64().unwrap());
+    }
+}
+
 #[cfg(feature = "ndarray_volumes")]
 mod ndarray_volumes {
     use super::util::minimal_header_hdr_gt;
     use ndarray::{Array, Axis, IxDyn, ShapeBuilder};
     use nifti::{
-        DataElement, InMemNiftiVolume, IntoNdArray, NiftiObject, NiftiType, NiftiVolume,
-        ReaderOptions, ReaderStreamedOptions,
+        DataElement, Endianness, InMemNiftiVolume, IntoNdArray, NiftiHeader, NiftiObject,
+        NiftiType, NiftiVolume, RandomAccessNiftiVolume, ReaderOptions, ReaderStreamedOptions,
+        StreamedNiftiVolume,
     };
     use num_complex::{Complex32, Complex64};
     use rgb::{RGB8, RGBA8};
@@ -310,6 +457,69 @@ mod ndarray_volumes {
         assert_eq!(volume[[1, 0, 0, 1]], RGB8::new(0, 55, 55));
     }
 
+    #[test]
+    fn test_resample_isotropic() {
+        let mut header = NiftiHeader::new_3d([4, 3, 2], NiftiType::Uint8).unwrap();
+        header.pixdim[1..4].copy_from_slice(&[2., 2., 2.]);
+        header.srow_x = [2., 0., 0., -3.];
+        header.srow_y = [0., 2., 0., -2.];
+        header.srow_z = [0., 0., 2., -1.];
+        let volume = InMemNiftiVolume::from_raw_data(&header, (0..24).collect()).unwrap();
+
+        let (resampled, new_header) = volume.resample_isotropic(&header, 1.).unwrap();
+        assert_eq!(resampled.dim(), &[8, 6, 4]);
+        assert_eq!(new_header.dim().unwrap(), &[8, 6, 4]);
+        assert_eq!(&new_header.pixdim[1..4], &[1., 1., 1.]);
+        assert_eq!(new_header.srow_x, [1., 0., 0., -3.]);
+        assert_eq!(new_header.srow_z, [0., 0., 1., -1.]);
+        let arr = resampled.into_ndarray::<u8>().unwrap();
+        let original = volume.into_ndarray::<u8>().unwrap();
+        assert_eq!(arr[[0, 0, 0]], original[[0, 0, 0]]);
+        assert_eq!(arr[[2, 4, 2]], original[[1, 2, 1]]);
+        assert_eq!(arr[[7, 5, 3]], original[[3, 2, 1]]);
+
+        // anisotropic voxels, in micrometers
+        header.pixdim[1..4].copy_from_slice(&[1000., 2000., 500.]);
+        header.xyzt_units = nifti::Unit::Micron as u8;
+        let volume = InMemNiftiVolume::from_raw_data(&header, (0..24).collect()).unwrap();
+        let (resampled, new_header) = volume.resample_isotropic(&header, 1.).unwrap();
+        assert_eq!(resampled.dim(), &[4, 6, 1]);
+        assert_eq!(&new_header.pixdim[1..4], &[1000., 1000., 1000.]);
+
+        assert!(matches!(
+            volume.resample_isotropic(&header, 0.),
+            Err(nifti::NiftiError::InvalidVoxelSize(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_rgb8_dedicated_ndarray() {
+        const FILE_NAME: &str = "resources/rgb/4D.nii";
+        let volume = ReaderOptions::new()
+            .read_file(FILE_NAME)
+            .unwrap()
+            .into_volume();
+        let expected = volume.clone().into_ndarray::<RGB8>().unwrap();
+        let volume = volume.into_rgb_ndarray().unwrap();
+
+        assert_eq!(volume.shape(), [3, 3, 3, 2].as_ref());
+        assert_eq!(volume[[0, 0, 0, 0]], RGB8::new(55, 55, 0));
+        assert_eq!(volume[[1, 0, 0, 1]], RGB8::new(0, 55, 55));
+        assert_eq!(volume, expected);
+
+        let volume = ReaderOptions::new()
+            .read_file("resources/minimal.nii")
+            .unwrap()
+            .into_volume();
+        assert!(matches!(
+            volume.into_rgb_ndarray(),
+            Err(nifti::NiftiError::InvalidTypeConversion(
+                NiftiType::Uint8,
+                _
+            ))
+        ));
+    }
+
     #[test]
     fn test_read_rgba8() {
         const FILE_NAME: &str = "resources/rgba/4D.nii";
@@ -416,4 +626,130 @@ mod ndarray_volumes {
         assert_eq!(volume[[0, 1]], Complex64::new(2.0, 2.0));
         assert_eq!(volume[[1, 0]], Complex64::new(3.0, 3.0));
     }
+
+    /// Build the raw bytes of a `Uint16` volume in which each voxel holds its
+    /// own linear (column-major) index.
+    fn linear_index_volume(dim: &[u16]) -> (NiftiHeader, Vec<u8>) {
+        let mut raw_dim = [1; 8];
+        raw_dim[0] = dim.len() as u16;
+        raw_dim[1..=dim.len()].copy_from_slice(dim);
+        let header = NiftiHeader {
+            dim: raw_dim,
+            datatype: NiftiType::Uint16 as i16,
+            bitpix: 16,
+            endianness: Endianness::Little,
+            ..NiftiHeader::default()
+        };
+        let len: usize = dim.iter().map(|&d| usize::from(d)).product();
+        let raw_data = (0..len as u16).flat_map(|v| v.to_le_bytes()).collect();
+        (header, raw_data)
+    }
+
+    fn assert_linear_index_ndarray(volume: InMemNiftiVolume, dim: &[u16]) {
+        let shape: Vec<usize> = dim.iter().map(|&d| usize::from(d)).collect();
+        let arr = volume.into_ndarray::<u16>().unwrap();
+        assert_eq!(arr.shape(), &shape[..]);
+        let expected =
+            Array::from_shape_vec(IxDyn(&shape).f(), (0..arr.len() as u16).collect::<Vec<_>>())
+                .unwrap();
+        assert_eq!(arr, expected);
+    }
+
+    #[test]
+    fn test_rank_6_volume() {
+        let dim = [2, 3, 2, 2, 3, 2];
+        let (header, raw_data) = linear_index_volume(&dim);
+        let volume = InMemNiftiVolume::from_raw_data(&header, raw_data).unwrap();
+        assert_eq!(volume.dimensionality(), 6);
+        assert_eq!(volume.dim(), &dim);
+        // last voxel, and one along each axis
+        assert_eq!(volume.get_u16(&[1, 2, 1, 1, 2, 1]).unwrap(), 143);
+        assert_eq!(volume.get_u16(&[0, 0, 0, 0, 0, 1]).unwrap(), 72);
+        assert_eq!(volume.get_u16(&[0, 0, 0, 0, 1, 0]).unwrap(), 24);
+        assert_eq!(volume.get_u16(&[0, 1, 0, 0, 0, 0]).unwrap(), 2);
+        assert!(volume.get_u16(&[0, 0, 0, 0, 0, 2]).is_err());
+        assert_linear_index_ndarray(volume, &dim);
+    }
+
+    #[test]
+    fn test_rank_7_volume() {
+        let dim = [2, 1, 3, 1, 2, 2, 3];
+        let (header, raw_data) = linear_index_volume(&dim);
+        let volume = InMemNiftiVolume::from_raw_data(&header, raw_data).unwrap();
+        assert_eq!(volume.dimensionality(), 7);
+        assert_eq!(volume.get_u16(&[1, 0, 2, 0, 1, 1, 2]).unwrap(), 71);
+        assert_linear_index_ndarray(volume, &dim);
+    }
+
+    #[test]
+    fn test_rank_6_streamed() {
+        let dim = [2, 3, 2, 2, 3, 2];
+        let (header, raw_data) = linear_index_volume(&dim);
+        let volume = StreamedNiftiVolume::from_reader(&raw_data[..], &header).unwrap();
+        assert_eq!(volume.slice_dim(), &dim[..5]);
+        let slices: Vec<_> = volume.collect::<Result<_, _>>().unwrap();
+        assert_eq!(slices.len(), 2);
+        for (i, slice) in slices.into_iter().enumerate() {
+            assert_eq!(slice.dim(), &dim[..5]);
+            let arr = slice.into_ndarray::<u16>().unwrap();
+            assert_eq!(arr.first(), Some(&(i as u16 * 72)));
+        }
+
+        let volume = StreamedNiftiVolume::from_reader_rank(&raw_data[..], &header, 2).unwrap();
+        assert_eq!(volume.slices_left(), 2 * 2 * 3 * 2);
+        let last = volume.last().unwrap().unwrap();
+        assert_eq!(last.dim(), &dim[..2]);
+        assert_eq!(last.get_u16(&[1, 2]).unwrap(), 143);
+    }
+
+    #[test]
+    fn test_collect_in_memory() {
+        let dim = [2, 3, 2, 2];
+        let (header, raw_data) = linear_index_volume(&dim);
+        let in_mem = InMemNiftiVolume::from_raw_data(&header, raw_data.clone()).unwrap();
+
+        let volume = StreamedNiftiVolume::from_reader_rank(&raw_data[..], &header, 2).unwrap();
+        let collected = volume.collect_in_memory().unwrap();
+        assert_eq!(collected, in_mem);
+
+        // after reading a slice, the remaining ones are stacked
+        let mut volume = StreamedNiftiVolume::from_reader_rank(&raw_data[..], &header, 2).unwrap();
+        let _ = volume.read_slice().unwrap();
+        let rest = volume.collect_in_memory().unwrap();
+        assert_eq!(rest.dim(), &[2, 3, 3]);
+        assert_eq!(rest.get_u16(&[0, 0, 0]).unwrap(), 6);
+        assert_eq!(rest.get_u16(&[1, 2, 2]).unwrap(), 23);
+    }
+
+    #[test]
+    fn test_skip_slices() {
+        let dim = [2, 3, 4];
+        let (header, raw_data) = linear_index_volume(&dim);
+
+        let mut volume = StreamedNiftiVolume::from_reader(&raw_data[..], &header).unwrap();
+        volume.skip_slices(1).unwrap();
+        assert_eq!(volume.slices_read(), 1);
+        assert_eq!(volume.slices_left(), 3);
+        let slice = volume.read_slice().unwrap();
+        assert_eq!(slice.dim(), &[2, 3]);
+        assert_eq!(slice.get_u16(&[0, 0]).unwrap(), 6);
+        assert_eq!(slice.get_u16(&[1, 2]).unwrap(), 11);
+
+        volume.skip_slices(0).unwrap();
+        assert!(matches!(
+            volume.skip_slices(3),
+            Err(nifti::NiftiError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+        assert_eq!(volume.slices_left(), 2);
+        volume.skip_slices(2).unwrap();
+        assert_eq!(volume.slices_left(), 0);
+        assert!(volume.next().is_none());
+    }
+
+    #[test]
+    fn test_rank_8_rejected() {
+        let (mut header, raw_data) = linear_index_volume(&[2, 1, 1, 1, 1, 1, 1]);
+        header.dim[0] = 8;
+        assert!(InMemNiftiVolume::from_raw_data(&header, raw_data).is_err());
+    }
 }
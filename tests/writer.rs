@@ -18,8 +18,10 @@ mod tests {
     };
 
     use approx::assert_abs_diff_eq;
+    use flate2::Compression;
     use ndarray::{
-        s, Array, Array1, Array2, Array3, Array4, Array5, Axis, Dimension, Ix2, IxDyn, ShapeBuilder,
+        s, Array, Array1, Array2, Array3, Array4, Array5, Axis, Dimension, Ix2, Ix3, IxDyn,
+        ShapeBuilder,
     };
     use rgb::{RGB8, RGBA8};
     use tempfile::tempdir;
@@ -29,11 +31,14 @@ mod tests {
         object::NiftiObject,
         volume::shape::Dim,
         writer::WriterOptions,
-        DataElement, IntoNdArray, NiftiHeader, NiftiType, ReaderOptions,
+        DataElement, IntoNdArray, NiftiError, NiftiHeader, NiftiType, ReaderOptions, SliceOrder,
+        XForm,
     };
 
     use super::util::rgb_header_gt;
 
+    // `TempDir::keep` requires tempfile 3.20
+    #[allow(deprecated)]
     fn get_temporary_path(ext: &str) -> PathBuf {
         let dir = tempdir().unwrap();
         let mut path = dir.into_path();
@@ -120,6 +125,40 @@ mod tests {
         test_write_read(arr, "test_non_contiguous.nii.gz");
     }
 
+    #[test]
+    fn fortran_writing_matches_c_writing() {
+        // Fortran-ordered data is written all at once, C-ordered data slice
+        // by slice, but both must produce the same file.
+        let shape = (1, 7, 6, 5, 3);
+        let c_arr = Array::from_shape_fn(shape, |(_, i, j, k, t)| {
+            (i * 1000 + j * 100 + k * 10 + t) as u16
+        });
+        let mut f_arr = Array::zeros(shape.f());
+        f_arr.assign(&c_arr);
+        assert!(f_arr.t().is_standard_layout());
+        let sliced = c_arr.slice(s![.., .., ..;2, .., ..]);
+        let mut f_sliced = Array::zeros(sliced.raw_dim().f());
+        f_sliced.assign(&sliced);
+
+        let header = NiftiHeader {
+            endianness: nifti::Endianness::Big,
+            ..NiftiHeader::default()
+        };
+        for (name, a, b) in [
+            ("whole", c_arr.view(), f_arr.view()),
+            ("sliced", sliced.view(), f_sliced.view()),
+        ] {
+            let c_path = get_temporary_path(&format!("{}_c.nii", name));
+            let f_path = get_temporary_path(&format!("{}_f.nii", name));
+            let options = |path| WriterOptions::new(path).reference_header(&header);
+            options(&c_path).write_nifti(&a).unwrap();
+            options(&f_path).write_nifti(&b).unwrap();
+            assert_eq!(fs::read(&c_path).unwrap(), fs::read(&f_path).unwrap());
+            let read_data = read_as_ndarray::<_, u16, IxDyn>(&f_path).1;
+            assert_eq!(read_data, a.into_dyn());
+        }
+    }
+
     #[test]
     fn c_writing() {
         // Test .nii
@@ -176,7 +215,7 @@ mod tests {
             .write_nifti(&data)
             .unwrap();
 
-        let (read_header, read_data) = read_as_ndarray(path);
+        let (read_header, read_data) = read_as_ndarray::<_, i32, _>(path);
         assert_eq!(read_header.scl_inter, 0.0);
         assert_eq!(read_header.scl_slope, 1.0);
         assert_eq!(data, read_data);
@@ -191,11 +230,49 @@ mod tests {
         for fname in &["3d.hdr", "3d.hdr.gz"] {
             let path = get_temporary_path(fname);
             WriterOptions::new(&path).write_nifti(&data).unwrap();
-            let data_read = read_as_ndarray(path).1;
+            let data_read = read_as_ndarray::<_, f64, _>(path).1;
             assert_eq!(data, data_read);
         }
     }
 
+    #[test]
+    fn write_hdr_header_compression() {
+        let mut data = Array::zeros((10, 11, 12));
+        data[(5, 0, 0)] = 1.0;
+        data[(6, 0, 0)] = 2.0;
+        let is_gz = |path: &Path| fs::read(path).unwrap().starts_with(&[0x1f, 0x8b]);
+
+        let path = get_temporary_path("pair.hdr.gz");
+        WriterOptions::new(&path)
+            .compression_level(Compression::fast())
+            .header_compression(Compression::best())
+            .write_nifti(&data)
+            .unwrap();
+        assert!(is_gz(&path));
+        assert!(is_gz(&path.with_file_name("pair.img.gz")));
+        assert_eq!(data, read_as_ndarray::<_, f64, _>(&path).1);
+
+        // setting the header compression level also compresses the header file
+        let path = get_temporary_path("forced.hdr");
+        WriterOptions::new(&path)
+            .compress(true)
+            .header_compression(Compression::best())
+            .write_nifti(&data)
+            .unwrap();
+        let path = path.with_file_name("forced.hdr.gz");
+        assert!(is_gz(&path));
+        assert_eq!(data, read_as_ndarray::<_, f64, _>(&path).1);
+
+        // otherwise, only the volume is compressed
+        let path = get_temporary_path("data_only.hdr");
+        WriterOptions::new(&path)
+            .compress(true)
+            .write_nifti(&data)
+            .unwrap();
+        assert!(!is_gz(&path));
+        assert!(is_gz(&path.with_file_name("data_only.img.gz")));
+    }
+
     #[test]
     fn write_non_contiguous() {
         let mut data = Array::from_elem((3, 4, 11), 1.5);
@@ -261,7 +338,7 @@ mod tests {
     fn write_descrip_panic() {
         let dim = [3, 3, 4, 5, 1, 1, 1, 1];
         let mut header = generate_nifti_header(dim, 1.0, 0.0, NiftiType::Float32);
-        header.descrip = (0..84).into_iter().collect();
+        header.descrip = (0..84).collect();
         let path = get_temporary_path("error_description.nii");
         let data = Array::from_elem((3, 4, 5), 1.5);
         assert!(WriterOptions::new(&path)
@@ -270,20 +347,46 @@ mod tests {
             .is_err());
     }
 
+    #[test]
+    fn check_description_before_write() {
+        let dim = [3, 3, 4, 5, 1, 1, 1, 1];
+        let mut header = generate_nifti_header(dim, 1.0, 0.0, NiftiType::Float32);
+        assert!(header.check_description().is_ok());
+        header.descrip = b"short".to_vec();
+        assert!(header.check_description().is_ok());
+        assert_eq!(header.descrip.len(), 5);
+
+        header.descrip = vec![b'a'; 81];
+        assert!(matches!(
+            header.check_description(),
+            Err(NiftiError::IncorrectDescriptionLength(81))
+        ));
+
+        // the writer rejects it before creating any file
+        let path = get_temporary_path("long_description.nii");
+        let data = Array::from_elem((3, 4, 5), 1.5);
+        let result = WriterOptions::new(&path)
+            .reference_header(&header)
+            .write_nifti(&data);
+        assert!(matches!(
+            result,
+            Err(NiftiError::IncorrectDescriptionLength(81))
+        ));
+        assert!(!path.exists());
+    }
+
     #[test]
     fn write_set_description_panic() {
         let dim = [3, 3, 4, 5, 1, 1, 1, 1];
         let mut header = generate_nifti_header(dim, 1.0, 0.0, NiftiType::Float32);
-        assert!(header
-            .set_description((0..81).into_iter().collect::<Vec<_>>())
-            .is_err());
+        assert!(header.set_description((0..81).collect::<Vec<_>>()).is_err());
     }
 
     #[test]
     fn write_set_description_str_panic() {
         let dim = [3, 3, 4, 5, 1, 1, 1, 1];
         let mut header = generate_nifti_header(dim, 1.0, 0.0, NiftiType::Float32);
-        let description: String = std::iter::repeat('é').take(41).collect();
+        let description = "é".repeat(41);
         assert!(header.set_description_str(description).is_err());
     }
 
@@ -352,12 +455,8 @@ mod tests {
         // set the bytes of vox_offset to 0.0 and of magic to MAGIC_CODE_NI1. The data bytes should
         // be identical though.
         let mut gt_bytes = fs::read("resources/rgb/3D.nii").unwrap();
-        for i in 110..114 {
-            gt_bytes[i] = 0;
-        }
-        for i in 0..4 {
-            gt_bytes[344 + i] = MAGIC_CODE_NI1[i];
-        }
+        gt_bytes[110..114].fill(0);
+        gt_bytes[344..348].copy_from_slice(MAGIC_CODE_NI1);
         assert_eq!(fs::read(header_path).unwrap(), &gt_bytes[..352]);
         assert_eq!(fs::read(data_path).unwrap(), &gt_bytes[352..]);
     }
@@ -434,6 +533,196 @@ mod tests {
         );
     }
 
+    #[test]
+    fn write_4d_rgb_datatype_override() {
+        let mut data = Array::from_elem((3, 3, 3, 2), [0u8, 0u8, 0u8]);
+        data[(0, 0, 0, 0)] = [55, 55, 0];
+        data[(0, 0, 1, 0)] = [55, 0, 55];
+        data[(0, 1, 0, 0)] = [0, 55, 55];
+        data[(0, 0, 0, 1)] = [55, 55, 0];
+        data[(0, 1, 0, 1)] = [55, 0, 55];
+        data[(1, 0, 0, 1)] = [0, 55, 55];
+
+        let path = get_temporary_path("rgb.nii");
+        let header = rgb_header_gt();
+        let options = WriterOptions::new(&path).reference_header(&header);
+        assert!(matches!(
+            options.write_nifti_pod(&data),
+            Err(NiftiError::MissingDataType)
+        ));
+        assert!(matches!(
+            options
+                .clone()
+                .datatype(NiftiType::Rgba32)
+                .write_nifti_pod(&data),
+            Err(NiftiError::UnsupportedDataType(NiftiType::Rgba32))
+        ));
+        options
+            .datatype(NiftiType::Rgb24)
+            .write_nifti_pod(&data)
+            .unwrap();
+
+        assert_eq!(
+            fs::read(path).unwrap(),
+            fs::read("resources/rgb/4D.nii").unwrap()
+        );
+    }
+
+    #[test]
+    fn read_and_write_bool_mask() {
+        let mask = Array::from_shape_fn((3, 4, 2), |(i, j, k)| ((i + j + k) % 2) as u8);
+        let path = get_temporary_path("mask_u8.nii");
+        WriterOptions::new(&path).write_nifti(&mask).unwrap();
+
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        let read = obj.into_volume().into_ndarray::<bool>().unwrap();
+        assert_eq!(read, mask.mapv(|v| v != 0).into_dyn());
+
+        let path = get_temporary_path("mask_bool.nii");
+        WriterOptions::new(&path).write_nifti_mask(&read).unwrap();
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(obj.header().data_type().unwrap(), NiftiType::Uint8);
+        let written = obj.into_volume().into_ndarray::<u8>().unwrap();
+        assert_eq!(written, mask.into_dyn());
+    }
+
+    #[test]
+    fn write_atomic() {
+        let data = Array::from_shape_fn((4, 3, 2), |(i, j, k)| (i + j + k) as u8);
+
+        let dir = get_temporary_path("");
+        let path = dir.join("atomic.nii.gz");
+        WriterOptions::new(&path)
+            .atomic(true)
+            .write_nifti(&data)
+            .unwrap();
+        let entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec!["atomic.nii.gz"]);
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(
+            obj.into_volume().into_ndarray::<u8>().unwrap(),
+            data.clone().into_dyn()
+        );
+
+        // simulate a failure once the header was written: the volume file
+        // cannot be created in place of a (non-empty) directory
+        let dir = get_temporary_path("");
+        let header_path = dir.join("pair.hdr");
+        fs::create_dir(dir.join("pair.img")).unwrap();
+        fs::write(dir.join("pair.img").join("file"), b"").unwrap();
+
+        assert!(WriterOptions::new(&header_path)
+            .atomic(true)
+            .write_nifti(&data)
+            .is_err());
+        let mut entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["pair.img"]);
+
+        // without `atomic`, the header file is left behind
+        assert!(WriterOptions::new(&header_path).write_nifti(&data).is_err());
+        assert!(header_path.exists());
+    }
+
+    #[test]
+    fn write_compressed_to_sink() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let data = Array::from_shape_fn((4, 3, 2), |(i, j, k)| (i * 6 + j * 2 + k) as f32);
+        let mut sink = Vec::new();
+        WriterOptions::new("unused.nii")
+            .write_nifti_to_compressed(&mut sink, &data, Compression::best())
+            .unwrap();
+        assert_eq!(&sink[..2], &[0x1f, 0x8b]);
+
+        let mut decoded = Vec::new();
+        let _ = GzDecoder::new(&sink[..]).read_to_end(&mut decoded).unwrap();
+        let obj = nifti::InMemNiftiObject::from_reader(&decoded[..]).unwrap();
+        assert_eq!(obj.header().magic, *MAGIC_CODE_NIP1);
+        assert_eq!(obj.header().vox_offset, 352.);
+        let read = obj.into_volume().into_ndarray::<f32>().unwrap();
+        assert_eq!(read, data.into_dyn());
+
+        // the file pair layout of the options is not used
+        let mut sink = Vec::new();
+        WriterOptions::new("unused.hdr")
+            .write_nifti_to_compressed(&mut sink, &Array1::<u8>::zeros(5), Compression::fast())
+            .unwrap();
+        let mut decoded = Vec::new();
+        let _ = GzDecoder::new(&sink[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded.len(), 352 + 5);
+    }
+
+    #[test]
+    fn write_complex256_passthrough() {
+        use nifti::Endianness;
+
+        // each voxel holds two 128-bit components, as raw bytes
+        let data = Array::from_shape_fn((2, 3, 2), |(i, j, k)| {
+            let mut voxel = [0u8; 32];
+            for (b, v) in voxel.iter_mut().enumerate() {
+                *v = (i * 100 + j * 10 + k + b) as u8;
+            }
+            voxel
+        });
+        let expected: Vec<u8> = data.t().iter().flatten().copied().collect();
+
+        let path = get_temporary_path("complex256.nii");
+        let header = NiftiHeader {
+            endianness: Endianness::native(),
+            ..NiftiHeader::default()
+        };
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .datatype(NiftiType::Complex256)
+            .write_nifti_pod(&data)
+            .unwrap();
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(obj.header().data_type().unwrap(), NiftiType::Complex256);
+        assert_eq!(obj.header().bitpix, 256);
+        assert_eq!(obj.header().dim().unwrap(), &[2, 3, 2]);
+        assert_eq!(obj.volume().raw_data(), &expected[..]);
+
+        // in the opposite byte order, each 128-bit component is swapped
+        let header = NiftiHeader {
+            endianness: Endianness::native().to_opposite(),
+            ..NiftiHeader::default()
+        };
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .datatype(NiftiType::Complex256)
+            .write_nifti_pod(&data)
+            .unwrap();
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(obj.header().endianness, Endianness::native().to_opposite());
+        let swapped: Vec<u8> = expected
+            .chunks_exact(16)
+            .flat_map(|c| c.iter().rev().copied())
+            .collect();
+        assert_eq!(obj.volume().raw_data(), &swapped[..]);
+    }
+
+    #[test]
+    fn write_datatype_override_element() {
+        let data = Array::from_shape_fn((4, 3, 2), |(i, j, k)| (i * 6 + j * 2 + k) as i8);
+        let path = get_temporary_path("override.nii");
+        WriterOptions::new(&path)
+            .datatype(NiftiType::Uint8)
+            .write_nifti(&data)
+            .unwrap();
+
+        let header = NiftiHeader::from_file(&path).unwrap();
+        assert_eq!(header.data_type().unwrap(), NiftiType::Uint8);
+        assert_eq!(header.bitpix, 8);
+    }
+
     #[test]
     fn write_4d_rgba_direct() {
         let mut data = Array::from_elem((3, 3, 3, 2), [0u8, 0u8, 0u8, 0u8]);
@@ -582,4 +871,351 @@ mod tests {
             fs::read("resources/minimal_extended_hdr.nii").unwrap()
         );
     }
+
+    #[test]
+    fn write_xform_codes() {
+        let data = Array3::<u8>::zeros((4, 5, 6));
+        let path = get_temporary_path("xform_codes.nii");
+
+        // the defaults without a reference header
+        WriterOptions::new(&path).write_nifti(&data).unwrap();
+        let header = read_as_ndarray::<_, u8, Ix3>(&path).0;
+        assert_eq!(header.sform().unwrap(), XForm::AlignedAnat);
+        assert_eq!(header.qform().unwrap(), XForm::ScannerAnat);
+
+        WriterOptions::new(&path)
+            .xform_codes(XForm::Mni152, XForm::Unknown)
+            .write_nifti(&data)
+            .unwrap();
+        let header = read_as_ndarray::<_, u8, Ix3>(&path).0;
+        assert_eq!(header.sform().unwrap(), XForm::Mni152);
+        assert_eq!(header.qform().unwrap(), XForm::Unknown);
+
+        // the codes override those of the reference header
+        let reference = NiftiHeader {
+            sform_code: 3,
+            qform_code: 3,
+            ..NiftiHeader::default()
+        };
+        WriterOptions::new(&path)
+            .reference_header(&reference)
+            .xform_codes(XForm::ScannerAnat, XForm::TemplateOther)
+            .write_nifti(&data)
+            .unwrap();
+        let header = read_as_ndarray::<_, u8, Ix3>(&path).0;
+        assert_eq!(header.sform().unwrap(), XForm::ScannerAnat);
+        assert_eq!(header.qform().unwrap(), XForm::TemplateOther);
+    }
+
+    #[test]
+    fn write_aux_file_and_intent_name() {
+        let data = Array3::<u8>::zeros((4, 5, 6));
+        let path = get_temporary_path("aux_file_intent_name.nii");
+
+        WriterOptions::new(&path)
+            .aux_file("labels.txt")
+            .intent_name("segmentation")
+            .write_nifti(&data)
+            .unwrap();
+        let header = read_as_ndarray::<_, u8, Ix3>(&path).0;
+        let mut aux_file = [0; 24];
+        aux_file[..10].copy_from_slice(b"labels.txt");
+        assert_eq!(header.aux_file, aux_file);
+        let mut intent_name = [0; 16];
+        intent_name[..12].copy_from_slice(b"segmentation");
+        assert_eq!(header.intent_name, intent_name);
+
+        // both fields may be filled up to their capacity
+        WriterOptions::new(&path)
+            .aux_file(&"a".repeat(24))
+            .intent_name(&"i".repeat(16))
+            .write_nifti(&data)
+            .unwrap();
+        let header = read_as_ndarray::<_, u8, Ix3>(&path).0;
+        assert_eq!(header.aux_file, [b'a'; 24]);
+        assert_eq!(header.intent_name, [b'i'; 16]);
+
+        let result = WriterOptions::new(&path)
+            .aux_file(&"a".repeat(25))
+            .write_nifti(&data);
+        assert!(matches!(
+            result,
+            Err(NiftiError::IncorrectFieldLength("aux_file", 25, 24))
+        ));
+        let result = WriterOptions::new(&path)
+            .intent_name(&"i".repeat(17))
+            .write_nifti(&data);
+        assert!(matches!(
+            result,
+            Err(NiftiError::IncorrectFieldLength("intent_name", 17, 16))
+        ));
+    }
+
+    #[test]
+    fn write_slice_timing() {
+        let data = Array3::<i16>::zeros((4, 5, 6));
+        let header = NiftiHeader {
+            // frequency on axis 1, phase on axis 2, slices on axis 3
+            dim_info: 1 | 2 << 2 | 3 << 4,
+            slice_code: SliceOrder::SeqInc as u8,
+            ..NiftiHeader::default()
+        };
+
+        let path = get_temporary_path("slice_timing.nii");
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .slice_timing(SliceOrder::AltDec2, -2, 3, -0.25)
+            .write_nifti(&data)
+            .unwrap();
+        let read_header = read_as_ndarray::<_, i16, Ix3>(&path).0;
+        assert_eq!(read_header.dim_info, 0b11_10_01);
+        assert_eq!(read_header.slice_order().unwrap(), SliceOrder::AltDec2);
+        assert_eq!(read_header.slice_start, -2);
+        assert_eq!(read_header.slice_end, 3);
+        assert_eq!(read_header.slice_duration, -0.25);
+
+        // without it, the reference header's fields are kept
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .write_nifti(&data)
+            .unwrap();
+        let read_header = read_as_ndarray::<_, i16, Ix3>(&path).0;
+        assert_eq!(read_header.slice_order().unwrap(), SliceOrder::SeqInc);
+        assert_eq!(read_header.slice_start, 0);
+    }
+
+    #[test]
+    fn write_f16_roundtrip() {
+        // all of these are exactly representable in half precision
+        let data = Array::from_shape_fn((3, 4, 5), |(i, j, k)| {
+            (i as f32 - 1.) * 0.5 + j as f32 * 64. - k as f32 / 8.
+        });
+
+        let path = get_temporary_path("f16.nii");
+        WriterOptions::new(&path).write_nifti_f16(&data).unwrap();
+
+        // without the option, the stored bits are read as is
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(obj.header().data_type().unwrap(), NiftiType::Int16);
+        assert!(obj.extensions().has_float16());
+
+        let obj = ReaderOptions::new()
+            .interpret_f16_extension(true)
+            .read_file(&path)
+            .unwrap();
+        assert_eq!(obj.header().data_type().unwrap(), NiftiType::Float32);
+        assert_eq!(obj.header().bitpix, 32);
+        let read_data = obj.into_volume().into_ndarray::<f32>().unwrap();
+        assert_eq!(read_data, data.into_dyn());
+    }
+
+    #[test]
+    fn write_invalid_extension() {
+        let data: Array2<f64> = Array2::zeros((8, 8));
+
+        let path = get_temporary_path("invalid_extension.nii");
+        let extension_sequence = nifti::ExtensionSequence::new(
+            nifti::Extender::from([1u8, 0u8, 0u8, 0u8]),
+            vec![nifti::Extension::new(6, b"Hello".to_vec())],
+        );
+
+        let result = WriterOptions::new(&path)
+            .with_extensions(extension_sequence)
+            .write_nifti(&data);
+        assert!(matches!(result, Err(NiftiError::InvalidExtensionSize(13))));
+        assert!(!path.exists());
+    }
+
+    fn assert_exact_roundtrip(file_name: &str) {
+        let obj = ReaderOptions::new().read_file(file_name).unwrap();
+        let path = get_temporary_path("roundtrip.nii");
+        WriterOptions::new(&path)
+            .exact_roundtrip(obj.header())
+            .with_extensions(obj.extensions().clone())
+            .write_raw_volume(obj.volume())
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), fs::read(file_name).unwrap());
+    }
+
+    #[test]
+    fn exact_roundtrip_minimal() {
+        assert_exact_roundtrip("resources/minimal.nii");
+    }
+
+    #[test]
+    fn write_object_with_extensions() {
+        let mut obj = ReaderOptions::new()
+            .read_file("resources/minimal_extended_hdr.nii")
+            .unwrap();
+        assert!(!obj.extensions().is_empty());
+        obj.header_mut().set_description_str("edited").unwrap();
+
+        for name in ["edited.nii", "edited.nii.gz"] {
+            let path = get_temporary_path(name);
+            obj.write(&path).unwrap();
+            let read_obj = ReaderOptions::new().read_file(&path).unwrap();
+            assert_eq!(read_obj.extensions(), obj.extensions());
+            assert_eq!(&read_obj.header().descrip[..6], b"edited");
+            assert_eq!(read_obj.header().dim, obj.header().dim);
+            assert_eq!(read_obj.volume(), obj.volume());
+        }
+    }
+
+    #[test]
+    fn exact_roundtrip_extended_header() {
+        assert_exact_roundtrip("resources/minimal_extended_hdr.nii");
+    }
+
+    #[test]
+    fn exact_roundtrip_rgb() {
+        assert_exact_roundtrip("resources/rgb/3D.nii");
+    }
+
+    #[test]
+    fn exact_roundtrip_swapped_endianness() {
+        let obj = ReaderOptions::new()
+            .read_file("resources/complex/complex64.nii")
+            .unwrap();
+        let mut header = obj.header().clone();
+        header.endianness = header.endianness.to_opposite();
+
+        let path = get_temporary_path("roundtrip_swapped.nii");
+        WriterOptions::new(&path)
+            .exact_roundtrip(&header)
+            .write_raw_volume(obj.volume())
+            .unwrap();
+
+        let swapped = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(swapped.header(), &header);
+        let expected = obj
+            .into_volume()
+            .into_ndarray::<num_complex::Complex64>()
+            .unwrap();
+        let got = swapped
+            .into_volume()
+            .into_ndarray::<num_complex::Complex64>()
+            .unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn write_with_nifti2_magic() {
+        let data = Array3::<u8>::zeros((4, 5, 6));
+        let header = NiftiHeader {
+            magic: *b"n+2\0",
+            ..NiftiHeader::default()
+        };
+
+        let path = get_temporary_path("magic.nii");
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .write_nifti(&data)
+            .unwrap();
+        let (read_header, read_data) = read_as_ndarray::<_, u8, _>(&path);
+        assert_eq!(&read_header.magic, MAGIC_CODE_NIP1);
+        assert_eq!(read_data, data);
+
+        let path = get_temporary_path("magic.hdr");
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .write_nifti(&data)
+            .unwrap();
+        assert_eq!(
+            &read_as_ndarray::<_, u8, Ix3>(&path).0.magic,
+            MAGIC_CODE_NI1
+        );
+
+        // even an exact round-trip can only write the NIfTI-1 layout
+        let obj = ReaderOptions::new()
+            .read_file("resources/minimal.nii")
+            .unwrap();
+        let header = NiftiHeader {
+            magic: *b"n+2\0",
+            ..obj.header().clone()
+        };
+        assert_eq!(&header.canonical_magic(), MAGIC_CODE_NIP1);
+        let path = get_temporary_path("magic_exact.nii");
+        WriterOptions::new(&path)
+            .exact_roundtrip(&header)
+            .write_raw_volume(obj.volume())
+            .unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            fs::read("resources/minimal.nii").unwrap()
+        );
+    }
+
+    #[test]
+    fn exact_roundtrip_mismatched_data() {
+        let obj = ReaderOptions::new()
+            .read_file("resources/minimal.nii")
+            .unwrap();
+        let path = get_temporary_path("roundtrip_mismatch.nii");
+        let data = Array3::<f32>::zeros((64, 64, 10));
+        assert!(WriterOptions::new(&path)
+            .exact_roundtrip(obj.header())
+            .write_nifti(&data)
+            .is_err());
+        let data = Array3::<u8>::zeros((64, 64, 9));
+        assert!(WriterOptions::new(&path)
+            .exact_roundtrip(obj.header())
+            .write_nifti(&data)
+            .is_err());
+    }
+
+    #[test]
+    fn write_complex_big_endian() {
+        let data = Array::from_shape_fn((3, 2), |(i, j)| {
+            num_complex::Complex32::new(i as f32, -(j as f32) - 0.5)
+        });
+        let header = NiftiHeader {
+            endianness: nifti::Endianness::Big,
+            ..NiftiHeader::default()
+        };
+
+        let path = get_temporary_path("complex_be.nii");
+        WriterOptions::new(&path)
+            .reference_header(&header)
+            .write_nifti(&data)
+            .unwrap();
+
+        let obj = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(obj.header().endianness, nifti::Endianness::Big);
+        let read = obj
+            .into_volume()
+            .into_ndarray::<num_complex::Complex32>()
+            .unwrap();
+        assert_eq!(read.into_dimensionality::<Ix2>().unwrap(), data);
+    }
+}
+
+#[test]
+fn write_raw_header_and_bytes() {
+    use nifti::writer::WriterOptions;
+    use nifti::{NiftiError, NiftiObject, ReaderOptions};
+
+    let obj = ReaderOptions::new()
+        .read_file("resources/minimal.nii")
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["raw.nii", "raw.hdr", "raw.nii.gz"] {
+        let path = dir.path().join(name);
+        WriterOptions::new(&path)
+            .write_raw(obj.header(), obj.volume().raw_data())
+            .unwrap();
+
+        let read = ReaderOptions::new().read_file(&path).unwrap();
+        assert_eq!(read.header().dim, obj.header().dim);
+        assert_eq!(read.header().datatype, obj.header().datatype);
+        assert_eq!(read.header().endianness, obj.header().endianness);
+        assert_eq!(read.header().scl_slope, obj.header().scl_slope);
+        assert_eq!(read.volume().raw_data(), obj.volume().raw_data());
+    }
+
+    let raw_data = &obj.volume().raw_data()[1..];
+    assert!(matches!(
+        WriterOptions::new(dir.path().join("short.nii")).write_raw(obj.header(), raw_data),
+        Err(NiftiError::IncompatibleLength(40959, 40960))
+    ));
 }